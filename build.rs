@@ -0,0 +1,256 @@
+//! Generates the static tables when the `static-tables` feature is enabled.
+//!
+//! The tables are generated for the transform lengths listed in the
+//! environment variable `YFFT_STATIC_TABLE_SIZES` (comma-separated powers of
+//! two) and the scalar types listed in `YFFT_STATIC_TABLE_TYPES` (`f32`
+//! and/or `f64`, defaults to `f32`).
+//!
+//! The tables must be identical to the ones computed at runtime. The kernel
+//! module `tablegen` is shared with the library for that purpose, and the
+//! remaining computations are duplicated here; the unit test
+//! `kernel::tables::tests::static_tables_match` verifies the result.
+use std::collections::BTreeSet;
+use std::env;
+use std::fmt::{Debug, Write as FmtWrite};
+use std::fs::File;
+use std::io::Write;
+use std::ops::{Add, Mul, Neg, Sub};
+use std::path::Path;
+
+// `tablegen` is written for `core` and `alloc`
+extern crate alloc;
+extern crate core;
+
+#[allow(dead_code)]
+#[path = "src/kernel/tablegen.rs"]
+mod tablegen;
+
+use tablegen::{bit_reversal_indices, exp_i_pi_frac};
+
+trait Scalar:
+    Copy + Debug + Add<Output = Self> + Sub<Output = Self> + Mul<Output = Self> + Neg<Output = Self>
+{
+    const NAME: &'static str;
+    fn from_f64(x: f64) -> Self;
+}
+
+impl Scalar for f32 {
+    const NAME: &'static str = "f32";
+    fn from_f64(x: f64) -> Self {
+        x as f32
+    }
+}
+
+impl Scalar for f64 {
+    const NAME: &'static str = "f64";
+    fn from_f64(x: f64) -> Self {
+        x
+    }
+}
+
+/// Formats `x` as a literal that evaluates to exactly the same value.
+fn literal<T: Scalar>(x: T) -> String {
+    // `Debug` prints the shortest representation that round-trips
+    format!("{:?}{}", x, T::NAME)
+}
+
+struct Generator {
+    code: String,
+    entries: String,
+    count: usize,
+}
+
+impl Generator {
+    fn push(&mut self, kind: &str, params: &[u64], ty: &str, elements: &[String]) {
+        let name = format!("TABLE_{}", self.count);
+        self.count += 1;
+        writeln!(self.code, "static {}: &[{}] = &[", name, ty).unwrap();
+        for element in elements {
+            writeln!(self.code, "    {},", element).unwrap();
+        }
+        writeln!(self.code, "];").unwrap();
+        writeln!(
+            self.entries,
+            "    StaticTable {{ kind: {:?}, params: &{:?}, data: &{} }},",
+            kind, params, name
+        )
+        .unwrap();
+    }
+
+    /// Mirrors `generic2::twiddle_table`.
+    fn generic2_twiddles<T: Scalar>(&mut self, radix: usize, unit: usize, inverse: bool) {
+        let full_circle = if inverse { 2 } else { -2 };
+        let mut elements = Vec::new();
+        for y in 0..unit {
+            for k in 1..radix {
+                let (c, s) = exp_i_pi_frac(full_circle * (k * y) as isize, radix * unit);
+                elements.push(format!(
+                    "Complex {{ re: {}, im: {} }}",
+                    literal(T::from_f64(c)),
+                    literal(T::from_f64(s))
+                ));
+            }
+        }
+        self.push(
+            "generic2_twiddles",
+            &[radix as u64, unit as u64, inverse as u64],
+            &format!("Complex<{}>", T::NAME),
+            &elements,
+        );
+    }
+
+    /// Mirrors `realfft::new_real_fft_coef_table`.
+    fn real_fft_coefs<T: Scalar>(&mut self, len: usize, inverse: bool, scale: f64) {
+        let zero = T::from_f64(0.0);
+        let one = T::from_f64(1.0);
+        let half = T::from_f64(0.5) * T::from_f64(scale);
+        let mut elements = Vec::new();
+        for i in 0..len / 2 {
+            let (c, s) = exp_i_pi_frac(-(i as isize), len / 2);
+            let (c, s) = (T::from_f64(c), T::from_f64(s));
+
+            // `a = (1 - i·c) / 2`, `b = (1 + i·c) / 2`, with every operation
+            // done in the same order as `Complex<T>`'s operators
+            let (jc_re, jc_im) = (-s, c);
+            let (a_re, a_im) = ((one - jc_re) * half, (zero - jc_im) * half);
+            let (b_re, b_im) = ((one + jc_re) * half, (zero + jc_im) * half);
+            if inverse {
+                elements.extend([a_re, -a_im, b_re, -b_im].iter().map(|&x| literal(x)));
+            } else {
+                elements.extend([a_re, a_im, b_re, b_im].iter().map(|&x| literal(x)));
+            }
+        }
+        self.push(
+            "real_fft_coefs",
+            &[len as u64, inverse as u64, scale.to_bits()],
+            T::NAME,
+            &elements,
+        );
+    }
+
+    fn bit_reversal_indices(&mut self, radixes: &[usize]) {
+        let elements: Vec<String> = bit_reversal_indices(radixes)
+            .iter()
+            .map(|x| x.to_string())
+            .collect();
+        let params: Vec<u64> = radixes.iter().map(|&x| x as u64).collect();
+        self.push("bit_reversal_indices", &params, "usize", &elements);
+    }
+
+    fn tables_for_type<T: Scalar>(&mut self, sizes: &[usize]) {
+        // The twiddle factor tables only depend on the radix and the unit.
+        // They are only used by the `backend-generic2` kernels
+        if env::var_os("CARGO_FEATURE_BACKEND_GENERIC2").is_some() {
+            let mut twiddles = BTreeSet::new();
+            for &size in sizes {
+                for &fft_len in &[size, size / 2] {
+                    for &radix in &[2, 4] {
+                        let mut unit = 1;
+                        while radix * unit <= fft_len {
+                            twiddles.insert((radix, unit));
+                            unit *= 2;
+                        }
+                    }
+                }
+            }
+            for &(radix, unit) in &twiddles {
+                for &inverse in &[false, true] {
+                    self.generic2_twiddles::<T>(radix, unit, inverse);
+                }
+            }
+        }
+
+        // `Setup` computes the scaling factor of the real FFT kernel as the
+        // normalization factor divided by the inherent scaling factor (`1/2`
+        // for the backward transform). It's `1` for the forward transform
+        // because the factor is folded into the bit reversal kernel, and for
+        // `Normalization::None`.
+        for &size in sizes.iter().filter(|&&size| size >= 2) {
+            self.real_fft_coefs::<T>(size, false, 1.0);
+            self.real_fft_coefs::<T>(size, true, 1.0);
+            for &factor in &[1.0 / size as f64, 1.0 / (size as f64).sqrt()] {
+                self.real_fft_coefs::<T>(size, true, factor / 0.5);
+            }
+        }
+    }
+}
+
+/// Mirrors `setup::factorize` for powers of two.
+fn factorize(len: usize) -> Vec<usize> {
+    let mut radixes = Vec::new();
+    let mut x = len;
+    while x > 1 {
+        let radix = if x % 4 == 0 { 4 } else { 2 };
+        radixes.push(radix);
+        x /= radix;
+    }
+    radixes.reverse();
+    radixes
+}
+
+fn main() {
+    println!("cargo:rerun-if-changed=build.rs");
+    println!("cargo:rerun-if-changed=src/kernel/tablegen.rs");
+    println!("cargo:rerun-if-env-changed=YFFT_STATIC_TABLE_SIZES");
+    println!("cargo:rerun-if-env-changed=YFFT_STATIC_TABLE_TYPES");
+
+    if env::var_os("CARGO_FEATURE_STATIC_TABLES").is_none() {
+        return;
+    }
+
+    let sizes: Vec<usize> = env::var("YFFT_STATIC_TABLE_SIZES")
+        .unwrap_or_default()
+        .split(',')
+        .map(|x| x.trim())
+        .filter(|x| !x.is_empty())
+        .map(|x| match x.parse::<usize>() {
+            Ok(size) if size.is_power_of_two() => size,
+            _ => panic!("YFFT_STATIC_TABLE_SIZES: `{}` is not a power of two", x),
+        })
+        .collect();
+    if sizes.is_empty() {
+        println!("cargo:warning=`static-tables` is enabled, but YFFT_STATIC_TABLE_SIZES is empty");
+    }
+
+    let types = env::var("YFFT_STATIC_TABLE_TYPES").unwrap_or_else(|_| "f32".to_owned());
+
+    let mut gen = Generator {
+        code: String::new(),
+        entries: String::new(),
+        count: 0,
+    };
+
+    for ty in types.split(',').map(|x| x.trim()) {
+        match ty {
+            "f32" => gen.tables_for_type::<f32>(&sizes),
+            "f64" => gen.tables_for_type::<f64>(&sizes),
+            _ => panic!("YFFT_STATIC_TABLE_TYPES: unknown type `{}`", ty),
+        }
+    }
+
+    // The radixes are applied in the reverse order by the decimation-in-time
+    // kernels
+    let mut radix_lists = BTreeSet::new();
+    for &size in &sizes {
+        for &fft_len in &[size, size / 2] {
+            if fft_len >= 2 {
+                let radixes = factorize(fft_len);
+                radix_lists.insert(radixes.iter().rev().cloned().collect::<Vec<_>>());
+                radix_lists.insert(radixes);
+            }
+        }
+    }
+    for radixes in &radix_lists {
+        gen.bit_reversal_indices(radixes);
+    }
+
+    let path = Path::new(&env::var("OUT_DIR").unwrap()).join("static_tables.rs");
+    let mut file = File::create(&path).unwrap();
+    writeln!(file, "use super::StaticTable;").unwrap();
+    writeln!(file, "#[allow(unused_imports)]").unwrap();
+    writeln!(file, "use num_complex::Complex;").unwrap();
+    file.write_all(gen.code.as_bytes()).unwrap();
+    writeln!(file, "pub static STATIC_TABLES: &[StaticTable] = &[").unwrap();
+    file.write_all(gen.entries.as_bytes()).unwrap();
+    writeln!(file, "];").unwrap();
+}
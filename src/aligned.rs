@@ -1,4 +1,5 @@
-use std::{
+use alloc::vec::Vec;
+use core::{
     fmt,
     mem::size_of,
     ops::{Deref, DerefMut},
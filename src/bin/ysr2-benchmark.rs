@@ -1,6 +1,6 @@
 extern crate yfft;
 use std::time;
-use yfft::{DataFormat, DataOrder, Env, Options, Setup};
+use yfft::{DataFormat, DataOrder, Env, Normalization, Options, Setup};
 
 fn duration_to_secs(d: time::Duration) -> f64 {
     d.as_secs() as f64 + d.subsec_nanos() as f64 * 1.0e-9
@@ -77,12 +77,15 @@ fn run_single_benchmark(size: usize) {
         output_data_format: DataFormat::Complex,
         len: size,
         inverse: false,
+        normalization: Normalization::None,
+        max_backend: None,
+        deterministic: false,
     })
     .unwrap();
     let mut senv = Env::new(&setup);
     let mut buf = vec![0f32; size * 2];
     let (iter_time, sd) = benchmark(move || {
-        senv.transform(buf.as_mut_slice());
+        senv.transform(buf.as_mut_slice()).unwrap();
     });
     let size_f = size as f64;
     let num_fops = size_f * size_f.log2() * 5.0;
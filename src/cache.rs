@@ -0,0 +1,288 @@
+//! Provides a process-wide cache of `Setup`s and the one-shot transform
+//! functions built on top of it.
+//!
+//! The cache holds up to `MAX_CACHED_SETUPS` `Setup`s. Inserting one more
+//! evicts the least recently used one, so the memory usage is bounded no
+//! matter how many distinct lengths are transformed. `clear_setup_cache`
+//! empties the cache.
+use num_complex::Complex;
+use std::any::{Any, TypeId};
+use std::cmp;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+
+use super::scratch::with_scratch;
+use super::{
+    complex_slice_as_scalars, complex_slice_as_scalars_mut, DataFormat, DataOrder, Normalization,
+    Num, Options, PlanError, Setup, TransformError,
+};
+
+/// The maximum number of `Setup`s held by the process-wide cache.
+const MAX_CACHED_SETUPS: usize = 64;
+
+#[derive(Default)]
+struct SetupCache {
+    /// The cached `Setup`s keyed by the scalar type and the options. Each
+    /// value is an `Arc<Setup<T>>` of the corresponding type, paired with the
+    /// value of `clock` when it was last used.
+    setups: HashMap<(TypeId, Options), (Box<Any + Send + Sync>, u64)>,
+    /// Incremented every time the cache is accessed.
+    clock: u64,
+}
+
+impl SetupCache {
+    fn get<T: Num>(&mut self, key: &(TypeId, Options)) -> Option<Arc<Setup<T>>> {
+        self.clock += 1;
+        let clock = self.clock;
+        self.setups.get_mut(key).map(|entry| {
+            entry.1 = clock;
+            Arc::clone(entry.0.downcast_ref().unwrap())
+        })
+    }
+
+    /// Inserts `setup` unless another thread has inserted one for `key` in the
+    /// meantime, and returns the cached one.
+    fn insert<T: Num>(&mut self, key: (TypeId, Options), setup: Arc<Setup<T>>) -> Arc<Setup<T>> {
+        if let Some(setup) = self.get(&key) {
+            return setup;
+        }
+        if self.setups.len() >= MAX_CACHED_SETUPS {
+            let lru_key = *self
+                .setups
+                .iter()
+                .min_by_key(|&(_, entry)| entry.1)
+                .unwrap()
+                .0;
+            self.setups.remove(&lru_key);
+        }
+        self.setups
+            .insert(key, (Box::new(Arc::clone(&setup)), self.clock));
+        setup
+    }
+}
+
+static CACHE: Mutex<Option<SetupCache>> = Mutex::new(None);
+
+impl<T> Setup<T>
+where
+    T: Num + 'static,
+{
+    /// Retrieves a `Setup` for `options` from the process-wide cache, creating
+    /// and inserting one by `Setup::new` if there isn't any.
+    ///
+    /// The cache holds up to 64 `Setup`s and evicts the least recently used
+    /// one when it's full. Errors are not cached.
+    pub fn cached(options: &Options) -> Result<Arc<Self>, PlanError> {
+        let key = (TypeId::of::<T>(), *options);
+        if let Some(setup) = with_cache(|cache| cache.get(&key)) {
+            return Ok(setup);
+        }
+
+        // Don't block other threads while planning
+        let setup = Arc::new(try!(Self::new(options)));
+
+        Ok(with_cache(|cache| cache.insert(key, setup)))
+    }
+}
+
+fn with_cache<R, F: FnOnce(&mut SetupCache) -> R>(f: F) -> R {
+    let mut cache = CACHE.lock().unwrap();
+    f(cache.get_or_insert_with(SetupCache::default))
+}
+
+/// Removes all `Setup`s from the process-wide cache used by `Setup::cached`.
+///
+/// The `Setup`s are dropped when the last `Arc` referring to them is dropped.
+pub fn clear_setup_cache() {
+    *CACHE.lock().unwrap() = None;
+}
+
+fn natural_options(
+    input_data_format: DataFormat,
+    output_data_format: DataFormat,
+    len: usize,
+    inverse: bool,
+    normalization: Normalization,
+) -> Options {
+    Options {
+        input_data_order: DataOrder::Natural,
+        output_data_order: DataOrder::Natural,
+        input_data_format,
+        output_data_format,
+        len,
+        inverse,
+        normalization,
+        max_backend: None,
+        deterministic: false,
+    }
+}
+
+/// Computes the unnormalized forward FFT of `data` in place.
+///
+/// The `Setup` is retrieved from the process-wide cache (see
+/// `Setup::cached`), and the scratch buffer from the per-thread pool used by
+/// `Setup::transform_concurrent`, so only the first call for each length
+/// performs the planning and memory allocation (unless the `Setup` has been
+/// evicted from the cache in the meantime).
+///
+/// Returns `TransformError::Plan` with the error from `Setup::new` if the
+/// length is not supported (e.g., a length with an odd factor without the
+/// `backend-generic` feature).
+pub fn fft<T: Num>(data: &mut [Complex<T>]) -> Result<(), TransformError> {
+    complex_in_place(data, false, Normalization::None)
+}
+
+/// Computes the backward FFT of `data` in place, scaled by `1 / data.len()`
+/// so that `ifft` undoes `fft`.
+///
+/// See `fft` for the caching behavior and errors.
+pub fn ifft<T: Num>(data: &mut [Complex<T>]) -> Result<(), TransformError> {
+    complex_in_place(data, true, Normalization::ByLen)
+}
+
+fn complex_in_place<T: Num>(
+    data: &mut [Complex<T>],
+    inverse: bool,
+    normalization: Normalization,
+) -> Result<(), TransformError> {
+    if data.is_empty() {
+        return Ok(());
+    }
+    let options = natural_options(
+        DataFormat::Complex,
+        DataFormat::Complex,
+        data.len(),
+        inverse,
+        normalization,
+    );
+    let setup = try!(Setup::cached(&options));
+    with_scratch(setup.scratch_len(), |scratch| {
+        setup.run(complex_slice_as_scalars_mut(data), scratch)
+    });
+    Ok(())
+}
+
+/// Computes the unnormalized forward FFT of the real sequence `input`.
+///
+/// Returns the first `input.len() / 2 + 1` elements of the spectrum (or an
+/// empty `Vec` if `input` is empty). The remaining part is implied by
+/// `X[len - k] == conj(X[k])`.
+///
+/// An even length is transformed by the real FFT (a complex FFT of the half
+/// length), reading `input` directly into the returned `Vec`. An odd length
+/// requires the complex FFT of the full length, whose output is truncated.
+///
+/// See `fft` for the caching behavior and errors.
+pub fn rfft<T: Num>(input: &[T]) -> Result<Vec<Complex<T>>, TransformError> {
+    let len = input.len();
+    if len == 0 {
+        return Ok(Vec::new());
+    }
+    let half = len / 2;
+    let output_data_format = if len % 2 == 0 {
+        DataFormat::HalfComplex
+    } else {
+        DataFormat::Complex
+    };
+    let options = natural_options(
+        DataFormat::Real,
+        output_data_format,
+        len,
+        false,
+        Normalization::None,
+    );
+    let setup = try!(Setup::cached(&options));
+
+    let mut output =
+        vec![Complex::new(T::zero(), T::zero()); cmp::max(half + 1, setup.output_len() / 2)];
+    with_scratch(setup.scratch_len(), |scratch| {
+        setup.run_from(input, complex_slice_as_scalars_mut(&mut output), scratch)
+    });
+
+    if len % 2 == 0 {
+        // Unpack `X[len / 2]` from the imaginary part of the first element
+        let nyquist = output[0].im;
+        output[0].im = T::zero();
+        output[half] = Complex::new(nyquist, T::zero());
+    }
+    output.truncate(half + 1);
+    Ok(output)
+}
+
+/// Computes the backward FFT of the spectrum of a real sequence of the length
+/// `len`, scaled by `1 / len` so that `irfft` undoes `rfft`.
+///
+/// `spectrum` must contain the first `len / 2 + 1` elements of the spectrum,
+/// as returned by `rfft`. The imaginary parts of the elements that must be
+/// real (`spectrum[0]`, and `spectrum[len / 2]` if `len` is even) are ignored.
+/// Returns an empty `Vec` without examining `spectrum` if `len == 0`.
+///
+/// Returns `TransformError::LengthMismatch` if
+/// `spectrum.len() != len / 2 + 1`, and `TransformError::Plan` with
+/// `PlanError::OddLength` if `len` is odd (this function uses the
+/// `HalfComplex` format, which requires an even length). See `fft` for the
+/// caching behavior and the other errors.
+pub fn irfft<T: Num>(spectrum: &[Complex<T>], len: usize) -> Result<Vec<T>, TransformError> {
+    if len == 0 {
+        return Ok(Vec::new());
+    }
+    if spectrum.len() != len / 2 + 1 {
+        return Err(TransformError::LengthMismatch {
+            expected: len / 2 + 1,
+            actual: spectrum.len(),
+        });
+    }
+    let options = natural_options(
+        DataFormat::HalfComplex,
+        DataFormat::Real,
+        len,
+        true,
+        Normalization::ByLen,
+    );
+    let setup = try!(Setup::cached(&options));
+
+    // Pack `X[len / 2]` into the imaginary part of the first element
+    let mut data = complex_slice_as_scalars(&spectrum[0..len / 2]).to_vec();
+    data[1] = spectrum[len / 2].re;
+    with_scratch(setup.scratch_len(), |scratch| setup.run(&mut data, scratch));
+    Ok(data)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn evicts_least_recently_used() {
+        let new_entry = |factor: usize| {
+            let options = natural_options(
+                DataFormat::Complex,
+                DataFormat::Complex,
+                16,
+                false,
+                Normalization::Custom(factor as f64),
+            );
+            let setup = Arc::new(Setup::<f64>::new(&options).unwrap());
+            ((TypeId::of::<f64>(), options), setup)
+        };
+        let mut cache = SetupCache::default();
+        let (first_key, first) = new_entry(1);
+        cache.insert(first_key, Arc::clone(&first));
+        let (second_key, second) = new_entry(2);
+        cache.insert(second_key, second);
+        for factor in 3..MAX_CACHED_SETUPS + 1 {
+            let (key, setup) = new_entry(factor);
+            cache.insert(key, setup);
+            // Keep the first one in use
+            assert!(cache.get::<f64>(&first_key).is_some());
+        }
+        assert_eq!(cache.setups.len(), MAX_CACHED_SETUPS);
+
+        // The cache is full, so this evicts the second one
+        let (key, setup) = new_entry(MAX_CACHED_SETUPS + 1);
+        cache.insert(key, setup);
+        assert_eq!(cache.setups.len(), MAX_CACHED_SETUPS);
+        assert!(cache.get::<f64>(&second_key).is_none());
+        assert!(Arc::ptr_eq(&cache.get(&first_key).unwrap(), &first));
+    }
+}
@@ -1,13 +1,305 @@
-use super::kernel::KernelParams;
-use super::{Num, Setup};
+use super::{
+    complex_slice_as_scalars, complex_slice_as_scalars_mut, DataFormat, Num, PlanError, Setup,
+};
+use alloc::vec::Vec;
+use core::borrow::Borrow;
+use core::fmt;
+use core::result::Result;
+use num_complex::Complex;
 use num_traits::Zero;
-use std::borrow::Borrow;
+#[cfg(feature = "std")]
+use std::error;
+
+/// The error type which is returned from the transformation functions of `Env`.
+#[derive(Debug, Clone, Copy, Ord, PartialOrd, Eq, PartialEq)]
+pub enum TransformError {
+    /// The supplied buffer is too short to hold the data required by the
+    /// `Setup`.
+    BufferTooShort { required: usize, actual: usize },
+
+    /// The supplied buffer doesn't have the exact number of elements required
+    /// by the `Setup`.
+    LengthMismatch { expected: usize, actual: usize },
+
+    /// The function doesn't support the data formats the `Setup` was created
+    /// for.
+    IncompatibleDataFormat {
+        input: DataFormat,
+        output: DataFormat,
+    },
+
+    /// The length of the supplied buffer is not supported by the
+    /// `MultiSizeSetup`.
+    UnsupportedLength { len: usize },
+
+    /// The index of an element described by a `StridedLayout` overflows
+    /// `usize`.
+    LayoutOverflow,
+
+    /// The channel index is not less than the number of channels.
+    ChannelOutOfRange { channel: usize, num_channels: usize },
+
+    /// A function creating the `Setup` on demand (e.g., `fft`) failed to
+    /// create it.
+    Plan(PlanError),
+}
+
+impl fmt::Display for TransformError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            TransformError::BufferTooShort { required, actual } => write!(
+                f,
+                "The buffer is too short (required: {}, actual: {}).",
+                required, actual
+            ),
+            TransformError::LengthMismatch { expected, actual } => write!(
+                f,
+                "The buffer has a wrong number of elements (expected: {}, actual: {}).",
+                expected, actual
+            ),
+            TransformError::IncompatibleDataFormat { input, output } => write!(
+                f,
+                "The operation does not support the data formats {:?} to {:?}.",
+                input, output
+            ),
+            TransformError::UnsupportedLength { len } => {
+                write!(f, "The length {} is not supported.", len)
+            }
+            TransformError::LayoutOverflow => write!(f, "The strided layout overflows."),
+            TransformError::ChannelOutOfRange {
+                channel,
+                num_channels,
+            } => write!(
+                f,
+                "The channel {} is out of range (number of channels: {}).",
+                channel, num_channels
+            ),
+            TransformError::Plan(e) => write!(f, "The setup could not be created: {}", e),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl error::Error for TransformError {
+    fn description(&self) -> &str {
+        match *self {
+            TransformError::BufferTooShort { .. } => "Buffer too short",
+            TransformError::LengthMismatch { .. } => "Length mismatch",
+            TransformError::IncompatibleDataFormat { .. } => "Incompatible data format",
+            TransformError::UnsupportedLength { .. } => "Unsupported length",
+            TransformError::LayoutOverflow => "Layout overflow",
+            TransformError::ChannelOutOfRange { .. } => "Channel out of range",
+            TransformError::Plan(_) => "Plan error",
+        }
+    }
+
+    fn source(&self) -> Option<&(error::Error + 'static)> {
+        match *self {
+            TransformError::Plan(ref e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<PlanError> for TransformError {
+    fn from(e: PlanError) -> Self {
+        TransformError::Plan(e)
+    }
+}
+
+fn check_channel(channel: usize, num_channels: usize) -> Result<(), TransformError> {
+    if channel < num_channels {
+        Ok(())
+    } else {
+        Err(TransformError::ChannelOutOfRange {
+            channel,
+            num_channels,
+        })
+    }
+}
+
+pub(crate) fn check_buffer_len(required: usize, actual: usize) -> Result<(), TransformError> {
+    if actual < required {
+        Err(TransformError::BufferTooShort { required, actual })
+    } else {
+        Ok(())
+    }
+}
+
+/// Describes where the elements of a single sequence are located in a larger
+/// buffer, such as one channel of an interleaved multichannel audio buffer.
+///
+/// The `i`-th element of the sequence is located at
+/// `data[offset + i * stride]`. For `DataFormat::Complex` and
+/// `DataFormat::HalfComplex`, this is the real component, and the imaginary
+/// component is located at `data[offset + i * stride + component_stride]`.
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub struct StridedLayout {
+    /// The index of the first scalar value of the first element.
+    pub offset: usize,
+
+    /// The distance between two consecutive elements, measured in scalar
+    /// values.
+    pub stride: usize,
+
+    /// The distance between the real and imaginary components of an element,
+    /// measured in scalar values. Ignored for `DataFormat::Real`.
+    pub component_stride: usize,
+}
+
+impl StridedLayout {
+    /// Constructs a `StridedLayout` describing a contiguous sequence in the
+    /// format `format`.
+    pub fn contiguous(format: DataFormat) -> Self {
+        StridedLayout {
+            offset: 0,
+            stride: format.element_len(),
+            component_stride: 1,
+        }
+    }
+
+    /// Constructs a `StridedLayout` describing the channel `channel` of a
+    /// buffer where the elements of `num_channels` channels are interleaved
+    /// (e.g., `LRLR...` for a stereo signal in the `Real` format, and
+    /// `(Lr Li) (Rr Ri) ...` in the `Complex` format).
+    ///
+    /// Returns `TransformError::ChannelOutOfRange` if
+    /// `channel >= num_channels`, and `TransformError::LayoutOverflow` if the
+    /// stride overflows `usize`.
+    pub fn interleaved_elements(
+        channel: usize,
+        num_channels: usize,
+        format: DataFormat,
+    ) -> Result<Self, TransformError> {
+        try!(check_channel(channel, num_channels));
+        let element_len = format.element_len();
+        Ok(StridedLayout {
+            offset: channel * element_len,
+            stride: try!(num_channels
+                .checked_mul(element_len)
+                .ok_or(TransformError::LayoutOverflow)),
+            component_stride: 1,
+        })
+    }
+
+    /// Constructs a `StridedLayout` describing the channel `channel` of a
+    /// buffer where the scalar values of `num_channels` channels are
+    /// interleaved (e.g., `LRLR...` for a stereo signal in the `Real` format,
+    /// and `Lr Rr Li Ri ...` in the `Complex` format).
+    ///
+    /// The `j`-th scalar value of the channel is always located at
+    /// `data[j * num_channels + channel]` regardless of the data format.
+    ///
+    /// Returns `TransformError::ChannelOutOfRange` if
+    /// `channel >= num_channels`, and `TransformError::LayoutOverflow` if the
+    /// stride overflows `usize`.
+    pub fn interleaved_scalars(
+        channel: usize,
+        num_channels: usize,
+        format: DataFormat,
+    ) -> Result<Self, TransformError> {
+        try!(check_channel(channel, num_channels));
+        Ok(StridedLayout {
+            offset: channel,
+            stride: try!(num_channels
+                .checked_mul(format.element_len())
+                .ok_or(TransformError::LayoutOverflow)),
+            component_stride: num_channels,
+        })
+    }
+
+    /// Checks that the elements are inside `data`. Returns
+    /// `TransformError::LayoutOverflow` if the index of the last value
+    /// overflows `usize`.
+    fn check(
+        &self,
+        data_len: usize,
+        num_elements: usize,
+        element_len: usize,
+    ) -> Result<(), TransformError> {
+        if num_elements > 0 {
+            let last = (num_elements - 1)
+                .checked_mul(self.stride)
+                .and_then(|x| x.checked_add(self.offset))
+                .and_then(|x| {
+                    (element_len - 1)
+                        .checked_mul(self.component_stride)
+                        .and_then(|y| x.checked_add(y))
+                })
+                .and_then(|x| x.checked_add(1));
+            match last {
+                Some(required) => check_buffer_len(required, data_len),
+                None => Err(TransformError::LayoutOverflow),
+            }
+        } else {
+            Ok(())
+        }
+    }
+}
+
+/// A sequence located in a buffer as described by a `StridedLayout`. This is
+/// how `Env::transform_strided` passes the sequence to the first and last
+/// stages (see `Kernel::transform_from_strided`).
+///
+/// The values of the sequence are numbered as if it were contiguous; the real
+/// and imaginary components of an element are two consecutive values.
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub struct StridedSequence {
+    layout: StridedLayout,
+    num_values: usize,
+    /// `0` for `DataFormat::Real` and `1` otherwise, so that
+    /// `j >> element_shift` is the index of the element containing the
+    /// `j`-th value.
+    element_shift: usize,
+}
+
+impl StridedSequence {
+    fn new(layout: StridedLayout, format: DataFormat, len: usize) -> Self {
+        StridedSequence {
+            layout,
+            num_values: format.num_elements(len) * format.element_len(),
+            element_shift: format.element_len() - 1,
+        }
+    }
+
+    /// Retrieves the number of scalar values in the sequence.
+    pub fn num_values(&self) -> usize {
+        self.num_values
+    }
+
+    /// Retrieves the index of the `j`-th scalar value in the buffer.
+    #[inline]
+    pub fn index(&self, j: usize) -> usize {
+        let layout = &self.layout;
+        layout.offset
+            + (j >> self.element_shift) * layout.stride
+            + (j & self.element_shift) * layout.component_stride
+    }
+
+    /// Copies the sequence from `data` to `dest[0..self.num_values()]`.
+    pub fn gather<T: Copy>(&self, data: &[T], dest: &mut [T]) {
+        for (j, x) in dest[0..self.num_values].iter_mut().enumerate() {
+            *x = data[self.index(j)];
+        }
+    }
+
+    /// Copies `src[0..self.num_values()]` to the sequence in `data`.
+    pub fn scatter<T: Copy>(&self, src: &[T], data: &mut [T]) {
+        for (j, x) in src[0..self.num_values].iter().enumerate() {
+            data[self.index(j)] = *x;
+        }
+    }
+}
 
 /// Encapsulates the working area required for a transformation.
 #[derive(Debug, Clone)]
 pub struct Env<TNum, TSetupRef> {
     setup: TSetupRef,
     work_area: Vec<TNum>,
+    /// The buffer used by `transform_strided` to hold the intermediate
+    /// results. Allocated by `new` so that `transform_strided` never
+    /// allocates.
+    staging: Vec<TNum>,
 }
 
 impl<TNum, TSetupRef> Env<TNum, TSetupRef>
@@ -16,26 +308,286 @@ where
     TSetupRef: Borrow<Setup<TNum>>,
 {
     pub fn new(setup: TSetupRef) -> Self {
-        let work_area_size = setup.borrow().required_work_area_size();
+        let work_area_size = setup.borrow().scratch_len();
+        let buffer_len = setup.borrow().buffer_len();
         Env {
             setup: setup,
             work_area: vec![Zero::zero(); work_area_size],
+            staging: vec![Zero::zero(); buffer_len],
         }
     }
 
+    /// Retrieves the `Setup` this `Env` was created for.
+    pub fn setup(&self) -> &Setup<TNum> {
+        self.setup.borrow()
+    }
+
     /// Transforms the supplied complex array `data` and writes the result to the same array (therefore this is an
     /// in-place operation).
     ///
     /// The array must be arranged in a interleaved order, in which the odd-th and even-th elements represent the real
     /// and imaginary components, respectively.
-    pub fn transform(&mut self, data: &mut [TNum]) {
-        let mut kernel_param = KernelParams {
-            coefs: data,
-            work_area: self.work_area.as_mut_slice(),
-        };
+    ///
+    /// `data` must be large enough to hold both of the input and output sequences (see `Setup::buffer_len`).
+    /// Otherwise, `TransformError::BufferTooShort` is returned and `data` is left intact.
+    pub fn transform(&mut self, data: &mut [TNum]) -> Result<(), TransformError> {
+        try!(check_buffer_len(
+            self.setup.borrow().buffer_len(),
+            data.len()
+        ));
+        unsafe { self.transform_unchecked(data) };
+        Ok(())
+    }
+
+    /// The unchecked version of `transform`.
+    ///
+    /// # Safety
+    ///
+    /// `data.len()` must be equal to or greater than `Setup::buffer_len()`. The kernels bypass bounds checking in
+    /// release builds, so violating this causes an undefined behavior.
+    pub unsafe fn transform_unchecked(&mut self, data: &mut [TNum]) {
         let setup = self.setup.borrow();
-        for kernel in &setup.kernels {
-            kernel.transform(&mut kernel_param);
+        setup.run(data, self.work_area.as_mut_slice());
+    }
+
+    /// Transforms `input` and writes the result to `output`, leaving `input`
+    /// intact (therefore this is an out-of-place operation).
+    ///
+    /// `input` must contain the input sequence in the input data format, and
+    /// `output` must be large enough to hold the output sequence in the output
+    /// data format. For example, a `Real` to `Complex` transform of the length
+    /// `len` reads `len` values from `input` and writes `len * 2` values to
+    /// `output`. Only the first elements of `output` constituting the output
+    /// sequence are modified.
+    ///
+    /// The first stage of the transformation reads `input` and writes to
+    /// `output`, and the remaining ones run in place in `output`, so `input`
+    /// is not copied to `output` beforehand. Unlike `transform`, the caller
+    /// doesn't have to allocate a buffer large enough for the intermediate
+    /// values of real FFTs.
+    ///
+    /// Returns `TransformError::BufferTooShort` if `input` or `output` is too
+    /// short.
+    pub fn transform_into(
+        &mut self,
+        input: &[TNum],
+        output: &mut [TNum],
+    ) -> Result<(), TransformError> {
+        let setup = self.setup.borrow();
+        let input_len = setup.input_len();
+        let output_len = setup.output_len();
+        try!(check_buffer_len(input_len, input.len()));
+        try!(check_buffer_len(output_len, output.len()));
+        debug_assert!(output_len >= input_len);
+
+        setup.run_from(
+            &input[0..input_len],
+            &mut output[0..output_len],
+            self.work_area.as_mut_slice(),
+        );
+        Ok(())
+    }
+
+    /// Transforms the supplied array of complex numbers `data` in place.
+    ///
+    /// The `Setup` must have been created for a `Complex` to `Complex`
+    /// transform, and `data` must contain exactly `len` elements.
+    pub fn transform_complex(&mut self, data: &mut [Complex<TNum>]) -> Result<(), TransformError> {
+        try!(self.check_formats(&[DataFormat::Complex], &[DataFormat::Complex]));
+        try!(self.check_input_len(data.len()));
+        self.transform(complex_slice_as_scalars_mut(data))
+    }
+
+    /// Transforms the supplied array of complex numbers `input` and writes the
+    /// result to `output`.
+    ///
+    /// The `Setup` must have been created for a `Complex` or `HalfComplex` to
+    /// `Complex` transform. `input` must contain exactly as many elements as
+    /// the input sequence (`len` for `Complex` and `len / 2` for
+    /// `HalfComplex`), and `output` must contain exactly `len` elements.
+    pub fn transform_complex_into(
+        &mut self,
+        input: &[Complex<TNum>],
+        output: &mut [Complex<TNum>],
+    ) -> Result<(), TransformError> {
+        try!(self.check_formats(
+            &[DataFormat::Complex, DataFormat::HalfComplex],
+            &[DataFormat::Complex],
+        ));
+        try!(self.check_input_len(input.len()));
+        try!(self.check_output_len(output.len()));
+        self.transform_into(
+            complex_slice_as_scalars(input),
+            complex_slice_as_scalars_mut(output),
+        )
+    }
+
+    /// Transforms the supplied array of real numbers `input` and writes the
+    /// result to `output`.
+    ///
+    /// The `Setup` must have been created for a `Real` to `Complex` or
+    /// `HalfComplex` transform. `input` must contain exactly `len` elements,
+    /// and `output` must contain exactly as many elements as the output
+    /// sequence (`len` for `Complex` and `len / 2` for `HalfComplex`).
+    pub fn transform_real_into(
+        &mut self,
+        input: &[TNum],
+        output: &mut [Complex<TNum>],
+    ) -> Result<(), TransformError> {
+        try!(self.check_formats(
+            &[DataFormat::Real],
+            &[DataFormat::Complex, DataFormat::HalfComplex],
+        ));
+        try!(self.check_input_len(input.len()));
+        try!(self.check_output_len(output.len()));
+        self.transform_into(input, complex_slice_as_scalars_mut(output))
+    }
+
+    /// Transforms the supplied array of complex numbers `input` in the
+    /// `HalfComplex` format and writes the real result to `output`.
+    ///
+    /// The `Setup` must have been created for a `HalfComplex` to `Real`
+    /// transform. `input` must contain exactly `len / 2` elements, and
+    /// `output` must contain exactly `len` elements.
+    pub fn transform_to_real_into(
+        &mut self,
+        input: &[Complex<TNum>],
+        output: &mut [TNum],
+    ) -> Result<(), TransformError> {
+        try!(self.check_formats(&[DataFormat::HalfComplex], &[DataFormat::Real]));
+        try!(self.check_input_len(input.len()));
+        try!(self.check_output_len(output.len()));
+        self.transform_into(complex_slice_as_scalars(input), output)
+    }
+
+    fn check_formats(
+        &self,
+        input: &[DataFormat],
+        output: &[DataFormat],
+    ) -> Result<(), TransformError> {
+        let options = self.setup.borrow().options();
+        if input.contains(&options.input_data_format)
+            && output.contains(&options.output_data_format)
+        {
+            Ok(())
+        } else {
+            Err(TransformError::IncompatibleDataFormat {
+                input: options.input_data_format,
+                output: options.output_data_format,
+            })
         }
     }
+
+    fn check_num_elements(&self, actual: usize, format: DataFormat) -> Result<(), TransformError> {
+        let expected = format.num_elements(self.setup.borrow().options().len);
+        if actual == expected {
+            Ok(())
+        } else {
+            Err(TransformError::LengthMismatch { expected, actual })
+        }
+    }
+
+    /// Checks the number of elements in the input supplied to the typed
+    /// functions.
+    pub(crate) fn check_input_len(&self, actual: usize) -> Result<(), TransformError> {
+        let format = self.setup.borrow().options().input_data_format;
+        self.check_num_elements(actual, format)
+    }
+
+    /// Checks the number of elements in the output supplied to the typed
+    /// functions.
+    pub(crate) fn check_output_len(&self, actual: usize) -> Result<(), TransformError> {
+        let format = self.setup.borrow().options().output_data_format;
+        self.check_num_elements(actual, format)
+    }
+
+    /// Transforms a sequence scattered in `data` as described by `input`, and
+    /// writes the result to the locations in `data` described by `output`.
+    ///
+    /// This makes it possible to transform one channel of an interleaved
+    /// multichannel buffer without de-interleaving it first. The other
+    /// elements of `data` are left intact.
+    ///
+    /// The first stage reads the input elements from `data` and writes the
+    /// result to an internal buffer, the intermediate stages run in place in
+    /// the buffer, and the last stage writes the output elements to `data`
+    /// (see `Kernel::transform_from_strided`). This doesn't cost extra passes
+    /// over the data as long as these stages support strided access, which
+    /// the generic kernels do. Of the SIMD kernels, only the bit reversal
+    /// kernels read strided elements directly; the others fall back to
+    /// copying the elements. The internal buffer is allocated by `new`, so
+    /// this function never allocates.
+    ///
+    /// Returns `TransformError::BufferTooShort` if `data` is too short to
+    /// contain the described elements, and `TransformError::LayoutOverflow`
+    /// if their indices overflow `usize`.
+    pub fn transform_strided(
+        &mut self,
+        data: &mut [TNum],
+        input: StridedLayout,
+        output: StridedLayout,
+    ) -> Result<(), TransformError> {
+        let setup = self.setup.borrow();
+        let options = setup.options();
+
+        let in_format = options.input_data_format;
+        let out_format = options.output_data_format;
+        try!(input.check(
+            data.len(),
+            in_format.num_elements(options.len),
+            in_format.element_len()
+        ));
+        try!(output.check(
+            data.len(),
+            out_format.num_elements(options.len),
+            out_format.element_len()
+        ));
+
+        setup.run_strided(
+            data,
+            &StridedSequence::new(input, in_format, options.len),
+            &StridedSequence::new(output, out_format, options.len),
+            &mut self.staging[0..setup.buffer_len()],
+            self.work_area.as_mut_slice(),
+        );
+        Ok(())
+    }
+
+    /// Transforms the channel `channel` of a buffer where the scalar values of
+    /// `num_channels` channels are interleaved, in place.
+    ///
+    /// The `j`-th scalar value of the channel, both on input and on output, is
+    /// located at `data[j * num_channels + channel]`. This means each call only
+    /// touches the values belonging to the specified channel, so the channels
+    /// can be transformed one by one even if the input and output formats have
+    /// different element sizes. For example, a `Real` to `HalfComplex`
+    /// transform of a stereo signal `Lx0 Rx0 Lx1 Rx1 ...` produces
+    /// `Lr0 Rr0 Li0 Ri0 ...`. `data` must contain
+    /// `num_channels` times as many values as `transform` would require.
+    ///
+    /// Returns `TransformError::ChannelOutOfRange` if
+    /// `channel >= num_channels`. See `transform_strided` for details.
+    pub fn transform_channel(
+        &mut self,
+        data: &mut [TNum],
+        channel: usize,
+        num_channels: usize,
+    ) -> Result<(), TransformError> {
+        let (input, output) = {
+            let options = self.setup.borrow().options();
+            (
+                try!(StridedLayout::interleaved_scalars(
+                    channel,
+                    num_channels,
+                    options.input_data_format,
+                )),
+                try!(StridedLayout::interleaved_scalars(
+                    channel,
+                    num_channels,
+                    options.output_data_format,
+                )),
+            )
+        };
+        self.transform_strided(data, input, output)
+    }
 }
@@ -1,10 +1,13 @@
 //! Defines the `SliceAccessor` type that can be used to bypass bounds checking
-//! on the release builds.
+//! on the release builds. Bounds checking is never bypassed if the `checked`
+//! feature is enabled.
 
-use std::{convert, ops};
+use core::{convert, ops};
 
-#[cfg(not(debug_assertions))]
-use std::slice;
+#[cfg(not(any(debug_assertions, feature = "checked")))]
+use alloc::vec::Vec;
+#[cfg(not(any(debug_assertions, feature = "checked")))]
+use core::slice;
 
 #[derive(Debug, PartialEq, Eq, PartialOrd, Ord)]
 pub struct SliceAccessor<T> {
@@ -17,6 +20,35 @@ impl<T> SliceAccessor<T> {
     }
 }
 
+// Only used by the x86 SIMD kernels
+#[cfg(all(
+    any(target_arch = "x86", target_arch = "x86_64"),
+    target_feature = "sse",
+    any(
+        feature = "backend-sse",
+        all(target_feature = "sse3", feature = "backend-sse3"),
+        all(target_feature = "avx", feature = "backend-avx")
+    )
+))]
+impl<T> SliceAccessor<&[T]> {
+    /// Retrieves a pointer to the `U` (e.g., a SIMD vector) starting at
+    /// `self[index]`. The whole `U` is bounds-checked whenever the indexing
+    /// operations are.
+    pub fn ptr_at<U>(&self, index: usize) -> *const U {
+        use core::mem;
+        if cfg!(any(debug_assertions, feature = "checked")) {
+            assert!(
+                index * mem::size_of::<T>() + mem::size_of::<U>() <= mem::size_of_val(self.slice),
+                "a {}-byte read at the index {} is out of bounds (len: {})",
+                mem::size_of::<U>(),
+                index,
+                self.slice.len()
+            );
+        }
+        &self[index] as *const T as *const U
+    }
+}
+
 impl<'a, T> convert::Into<SliceAccessor<&'a [T]>> for SliceAccessor<&'a mut [T]> {
     fn into(self) -> SliceAccessor<&'a [T]> {
         unsafe { SliceAccessor::new(self.slice) }
@@ -36,7 +68,7 @@ impl<T> ops::DerefMut for SliceAccessor<T> {
     }
 }
 
-#[cfg(not(debug_assertions))]
+#[cfg(not(any(debug_assertions, feature = "checked")))]
 impl<'a, T, I> ops::Index<I> for SliceAccessor<&'a [T]>
 where
     I: slice::SliceIndex<[T]>,
@@ -47,7 +79,7 @@ where
     }
 }
 
-#[cfg(not(debug_assertions))]
+#[cfg(not(any(debug_assertions, feature = "checked")))]
 impl<'a, T, I> ops::Index<I> for SliceAccessor<&'a Vec<T>>
 where
     I: slice::SliceIndex<[T]>,
@@ -58,7 +90,7 @@ where
     }
 }
 
-#[cfg(not(debug_assertions))]
+#[cfg(not(any(debug_assertions, feature = "checked")))]
 impl<'a, T, I> ops::Index<I> for SliceAccessor<&'a mut [T]>
 where
     I: slice::SliceIndex<[T]>,
@@ -69,7 +101,7 @@ where
     }
 }
 
-#[cfg(not(debug_assertions))]
+#[cfg(not(any(debug_assertions, feature = "checked")))]
 impl<'a, T, I> ops::IndexMut<I> for SliceAccessor<&'a mut [T]>
 where
     I: slice::SliceIndex<[T]>,
@@ -79,7 +111,7 @@ where
     }
 }
 
-#[cfg(not(debug_assertions))]
+#[cfg(not(any(debug_assertions, feature = "checked")))]
 impl<'a, T, I> ops::Index<I> for SliceAccessor<&'a mut Vec<T>>
 where
     I: slice::SliceIndex<[T]>,
@@ -90,7 +122,7 @@ where
     }
 }
 
-#[cfg(not(debug_assertions))]
+#[cfg(not(any(debug_assertions, feature = "checked")))]
 impl<'a, T, I> ops::IndexMut<I> for SliceAccessor<&'a mut Vec<T>>
 where
     I: slice::SliceIndex<[T]>,
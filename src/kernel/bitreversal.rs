@@ -1,56 +1,50 @@
-use super::{Kernel, KernelParams, SliceAccessor};
+use super::super::StridedSequence;
+use super::tablegen::bit_reversal_indices;
+use super::tables::{table, Table};
+use super::utils::copy_scaled;
+use super::{Backend, Kernel, KernelInput, KernelParams, SliceAccessor};
+use alloc::boxed::Box;
+use alloc::vec::Vec;
 
 use super::super::Num;
+use core::mem;
 
 /// Creates a bit reversal kernel.
 ///
 /// A bit reversal kernel is, as its name implies, a special kernel that performs
 /// the bit reversal operation. The kernel is supposed to be executed after DIF FFT
-/// steps.
-pub fn new_bit_reversal_kernel<T>(radixes: &[usize]) -> Box<Kernel<T>>
+/// steps, or before DIT FFT steps with `radixes` in the order they are applied.
+///
+/// Every element is multiplied by `scale` while being moved.
+pub fn new_bit_reversal_kernel<T>(
+    radixes: &[usize],
+    scale: f64,
+    max_backend: Backend,
+) -> Box<Kernel<T>>
 where
     T: Num,
 {
-    let len = radixes.iter().product();
-    let mut indices = vec![0; len];
-
-    let mut digits = vec![0; radixes.len()];
-    let mut factors = vec![0; radixes.len()];
-    factors[0] = 1;
-    for i in 0..radixes.len() - 1 {
-        factors[i + 1] = factors[i] * radixes[i];
-    }
+    let radixes_key: Vec<u64> = radixes.iter().map(|&r| r as u64).collect();
+    let indices = table("bit_reversal_indices", &radixes_key, || {
+        bit_reversal_indices(radixes)
+    });
 
-    let mut cur: usize = 0;
-    for i in 0..len {
-        indices[cur] = i;
-        if i < len - 1 {
-            digits[radixes.len() - 1] += 1;
-            cur += factors[radixes.len() - 1];
-            for k in (0..radixes.len()).rev() {
-                if digits[k] < radixes[k] {
-                    break;
-                }
-                digits[k - 1] += 1;
-                digits[k] = 0;
-                cur -= factors[k] * radixes[k];
-                cur += factors[k - 1];
-            }
-        }
-    }
-
-    unsafe { super::x86::new_x86_bit_reversal_kernel(&indices) }
-        .unwrap_or_else(|| BitReversalKernel::new(indices))
+    unsafe { super::x86::new_x86_bit_reversal_kernel(&indices, scale, max_backend) }
+        .unwrap_or_else(|| BitReversalKernel::new(indices, scale))
 }
 
 #[derive(Debug)]
 struct BitReversalKernel {
-    indices: Vec<usize>,
+    indices: Table<usize>,
+    scale: f64,
 }
 
 impl BitReversalKernel {
-    fn new<T: Num>(indices: Vec<usize>) -> Box<Kernel<T>> {
-        Box::new(Self { indices: indices })
+    fn new<T: Num>(indices: Table<usize>, scale: f64) -> Box<Kernel<T>> {
+        Box::new(Self {
+            indices: indices,
+            scale: scale,
+        })
     }
 }
 
@@ -59,18 +53,101 @@ where
     T: Num,
 {
     fn transform(&self, params: &mut KernelParams<T>) {
+        self.transform_inner(None, params);
+    }
+    fn transform_from(&self, input: &[T], params: &mut KernelParams<T>) {
+        self.transform_inner(Some(KernelInput::Contiguous(input)), params);
+    }
+    fn transform_from_strided(
+        &self,
+        data: &[T],
+        input: &StridedSequence,
+        params: &mut KernelParams<T>,
+    ) {
+        self.transform_inner(Some(KernelInput::Strided(data, input)), params);
+    }
+    fn transform_into_strided(
+        &self,
+        params: &mut KernelParams<T>,
+        data: &mut [T],
+        output: &StridedSequence,
+    ) {
         let indices = unsafe { SliceAccessor::new(&self.indices) };
         let size = self.indices.len();
-        let mut data = unsafe { SliceAccessor::new(&mut params.coefs[0..size * 2]) };
-        let mut wa = unsafe { SliceAccessor::new(&mut params.work_area[0..size * 2]) };
-        wa.copy_from_slice(*data);
+        let source = if self.scale == 1.0 {
+            &params.coefs[0..size * 2]
+        } else {
+            let wa = &mut params.work_area[0..size * 2];
+            copy_scaled(wa, &params.coefs[0..size * 2], self.scale);
+            &*wa
+        };
+        let source = unsafe { SliceAccessor::new(source) };
+        let mut data = unsafe { SliceAccessor::new(data) };
         for i in 0..size {
             let index = indices[i];
-            data[i * 2] = wa[index * 2];
-            data[i * 2 + 1] = wa[index * 2 + 1];
+            data[output.index(i * 2)] = source[index * 2];
+            data[output.index(i * 2 + 1)] = source[index * 2 + 1];
         }
     }
     fn required_work_area_size(&self) -> usize {
         self.indices.len() * 2
     }
+    fn table_size(&self) -> usize {
+        mem::size_of_val(&self.indices[..])
+    }
+    fn backend(&self) -> Backend {
+        Backend::Generic
+    }
+}
+
+impl BitReversalKernel {
+    fn transform_inner<T: Num>(&self, input: Option<KernelInput<T>>, params: &mut KernelParams<T>) {
+        let indices = unsafe { SliceAccessor::new(&self.indices) };
+        let size = self.indices.len();
+        let mut data = unsafe { SliceAccessor::new(&mut params.coefs[0..size * 2]) };
+        let mut wa = unsafe { SliceAccessor::new(&mut params.work_area[0..size * 2]) };
+        let source = gather_source(&mut wa, &data, input, self.scale);
+        for i in 0..size {
+            let index = indices[i];
+            data[i * 2] = source[index * 2];
+            data[i * 2 + 1] = source[index * 2 + 1];
+        }
+    }
+}
+
+/// Prepares the elements to be gathered by a bit reversal kernel, which can't
+/// overlap with the destination `data`.
+///
+/// Returns `input` as is if it's a contiguous sequence (i.e., the kernel is
+/// the first stage of an out-of-place transformation) and no scaling is
+/// required. Otherwise, copies `input` or `data` to `wa` while applying
+/// `scale`, and returns `wa`.
+pub(super) fn gather_source<'a, T: Num>(
+    wa: &'a mut [T],
+    data: &[T],
+    input: Option<KernelInput<'a, T>>,
+    scale: f64,
+) -> SliceAccessor<&'a [T]> {
+    let source = match input {
+        Some(KernelInput::Contiguous(input)) if scale == 1.0 => &input[0..wa.len()],
+        Some(KernelInput::Contiguous(input)) => {
+            copy_scaled(wa, &input[0..wa.len()], scale);
+            &*wa
+        }
+        Some(KernelInput::Strided(input, seq)) => {
+            seq.gather(input, wa);
+            if scale != 1.0 {
+                let scale = T::from(scale).unwrap();
+                for x in wa.iter_mut() {
+                    *x = *x * scale;
+                }
+            }
+            &*wa
+        }
+        None => {
+            copy_scaled(wa, data, scale);
+            &*wa
+        }
+    };
+    unsafe { SliceAccessor::new(source) }
 }
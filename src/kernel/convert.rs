@@ -1,31 +1,49 @@
-use super::{Kernel, KernelParams, SliceAccessor};
+use super::{Backend, Kernel, KernelParams, SliceAccessor};
+use alloc::boxed::Box;
 
 use Num;
 
 /// Creates a kernel that converts from the `Real` format to `Complex` format.
-pub fn new_real_to_complex_kernel<T>(len: usize) -> Box<Kernel<T>>
+///
+/// The real parts are multiplied by `scale`.
+pub fn new_real_to_complex_kernel<T>(len: usize, scale: f64) -> Box<Kernel<T>>
 where
     T: Num,
 {
-    Box::new(RealToComplexKernel { len })
+    Box::new(RealToComplexKernel {
+        len,
+        scale: T::from(scale).unwrap(),
+    })
 }
 
 #[derive(Debug)]
-struct RealToComplexKernel {
+struct RealToComplexKernel<T> {
     len: usize,
+    scale: T,
 }
 
-impl<T> Kernel<T> for RealToComplexKernel
+impl<T> Kernel<T> for RealToComplexKernel<T>
 where
     T: Num,
 {
     fn transform(&self, params: &mut KernelParams<T>) {
         let mut data = unsafe { SliceAccessor::new(&mut params.coefs[0..self.len * 2]) };
         for i in (0..self.len).rev() {
-            data[i * 2] = data[i];
+            data[i * 2] = data[i] * self.scale;
             data[i * 2 + 1] = T::zero();
         }
     }
+    fn transform_from(&self, input: &[T], params: &mut KernelParams<T>) {
+        let mut data = unsafe { SliceAccessor::new(&mut params.coefs[0..self.len * 2]) };
+        let input = unsafe { SliceAccessor::new(&input[0..self.len]) };
+        for i in 0..self.len {
+            data[i * 2] = input[i] * self.scale;
+            data[i * 2 + 1] = T::zero();
+        }
+    }
+    fn backend(&self) -> Backend {
+        Backend::Generic
+    }
 }
 
 /// Creates a kernel that converts from the `HalfComplex` format to `Complex` format.
@@ -56,4 +74,8 @@ where
         data[self.len + 1] = T::zero();
         data[1] = T::zero();
     }
+
+    fn backend(&self) -> Backend {
+        Backend::Generic
+    }
 }
@@ -6,61 +6,74 @@
 //! According to a benchmark result, this kernel runs about 100x slower than a commercial-level FFT library on a Skylake
 //! machine.
 
-use super::{Kernel, KernelCreationParams, KernelParams, KernelType, SliceAccessor};
+use super::tables::shared_table;
+use super::twiddle::twiddle;
+use super::{
+    Backend, Kernel, KernelCreationParams, KernelInput, KernelParams, KernelType, SliceAccessor,
+};
+use alloc::boxed::Box;
+use alloc::vec::Vec;
 
 use num_complex::Complex;
 use num_iter::range_step;
-use num_traits::{One, Zero};
+use num_traits::Zero;
 
-use super::super::{complex_from_slice, Num};
+use super::super::{complex_from_slice, Num, StridedSequence};
 
-pub fn new_generic_kernel<T: 'static>(cparams: &KernelCreationParams) -> Box<Kernel<T>>
+use alloc::sync::Arc;
+use core::mem;
+
+pub fn new_generic_kernel<T: 'static>(cparams: &KernelCreationParams, scale: f64) -> Box<Kernel<T>>
 where
     T: Num,
 {
-    let full_circle = if cparams.inverse { 2 } else { -2 };
-    let twiddle_delta = Complex::new(
-        Zero::zero(),
-        T::from(cparams.size / cparams.radix / cparams.unit).unwrap()
-            * T::from(full_circle).unwrap()
-            * T::PI()
-            / T::from(cparams.size).unwrap(),
-    )
-    .exp();
-    let coef_delta = Complex::new(
-        Zero::zero(),
-        T::from(full_circle).unwrap() * T::PI() / T::from(cparams.radix).unwrap(),
-    )
-    .exp();
+    let twiddles = new_twiddle_table(cparams.radix * cparams.unit, cparams.inverse);
+    let scale = T::from(scale).unwrap();
 
     match cparams.kernel_type {
         KernelType::Dit => Box::new(GenericDitKernel {
             cparams: *cparams,
-            twiddle_delta: twiddle_delta,
-            coef_delta: coef_delta,
+            twiddles: twiddles,
+            scale: scale,
         }),
         KernelType::Dif => Box::new(GenericDifKernel {
             cparams: *cparams,
-            twiddle_delta: twiddle_delta,
-            coef_delta: coef_delta,
+            twiddles: twiddles,
+            scale: scale,
         }),
     }
 }
 
+/// Retrieves the table of `exp(∓2πik/len)` for `0 <= k < len` from the table
+/// cache, creating it if needed.
+///
+/// Each element is computed directly in double precision (rather than by
+/// repeated multiplication), so the error doesn't grow with `len`.
+fn new_twiddle_table<T: Num>(len: usize, inverse: bool) -> Arc<Vec<Complex<T>>> {
+    let full_circle = if inverse { 2 } else { -2 };
+    shared_table("generic_twiddles", &[len as u64, inverse as u64], || {
+        (0..len)
+            .map(|k| twiddle(full_circle * k as isize, len))
+            .collect()
+    })
+}
+
 #[derive(Debug)]
 struct GenericDitKernel<T> {
     cparams: KernelCreationParams,
-    twiddle_delta: Complex<T>,
-    /// sub-FFT twiddle unit
-    coef_delta: Complex<T>,
+    /// `exp(∓2πik/(radix * unit))` for `0 <= k < radix * unit`
+    twiddles: Arc<Vec<Complex<T>>>,
+    /// The factor every output is multiplied by.
+    scale: T,
 }
 
 #[derive(Debug)]
 struct GenericDifKernel<T> {
     cparams: KernelCreationParams,
-    twiddle_delta: Complex<T>,
-    /// sub-FFT twiddle unit
-    coef_delta: Complex<T>,
+    /// `exp(∓2πik/(radix * unit))` for `0 <= k < radix * unit`
+    twiddles: Arc<Vec<Complex<T>>>,
+    /// The factor every output is multiplied by.
+    scale: T,
 }
 
 impl<T> Kernel<T> for GenericDitKernel<T>
@@ -68,82 +81,209 @@ where
     T: Num,
 {
     fn transform(&self, params: &mut KernelParams<T>) {
+        self.transform_inner(None, None, params);
+    }
+
+    fn transform_from(&self, input: &[T], params: &mut KernelParams<T>) {
+        self.transform_inner(Some(KernelInput::Contiguous(input)), None, params);
+    }
+
+    fn transform_from_strided(
+        &self,
+        data: &[T],
+        input: &StridedSequence,
+        params: &mut KernelParams<T>,
+    ) {
+        self.transform_inner(Some(KernelInput::Strided(data, input)), None, params);
+    }
+
+    fn transform_into_strided(
+        &self,
+        params: &mut KernelParams<T>,
+        data: &mut [T],
+        output: &StridedSequence,
+    ) {
+        self.transform_inner(None, Some((data, output)), params);
+    }
+
+    fn required_work_area_size(&self) -> usize {
+        self.cparams.radix * 2
+    }
+
+    fn table_size(&self) -> usize {
+        mem::size_of_val(&self.twiddles[..])
+    }
+
+    fn backend(&self) -> Backend {
+        Backend::Generic
+    }
+}
+
+impl<T> GenericDitKernel<T>
+where
+    T: Num,
+{
+    /// Reads the sequence from `input` (or `params.coefs` if `None`) and
+    /// writes the result to `output` (or `params.coefs` if `None`).
+    fn transform_inner(
+        &self,
+        input: Option<KernelInput<T>>,
+        output: Option<(&mut [T], &StridedSequence)>,
+        params: &mut KernelParams<T>,
+    ) {
         let cparams = &self.cparams;
         let mut data = unsafe { SliceAccessor::new(&mut params.coefs[0..cparams.size * 2]) };
+        let input = input.map(|x| x.prefix(cparams.size * 2));
+        let mut output = output.map(|(x, seq)| (unsafe { SliceAccessor::new(x) }, seq));
         let mut wa = unsafe { SliceAccessor::new(&mut params.work_area[0..cparams.radix * 2]) };
 
-        let twiddle_delta = self.twiddle_delta;
-        let coef_delta = self.coef_delta;
+        let twiddles = unsafe { SliceAccessor::new(&self.twiddles[..]) };
+        let len = cparams.radix * cparams.unit;
 
-        for x in range_step(0, cparams.size, cparams.unit * cparams.radix) {
-            let mut twiddle_1: Complex<T> = Complex::one();
+        for x in range_step(0, cparams.size, len) {
             for y in 0..cparams.unit {
                 for z in 0..cparams.radix {
-                    wa[z * 2] = data[(x + y + z * cparams.unit) * 2];
-                    wa[z * 2 + 1] = data[(x + y + z * cparams.unit) * 2 + 1];
+                    let i = (x + y + z * cparams.unit) * 2;
+                    let (re, im) = match input {
+                        Some(ref input) => unsafe { (input.get(i), input.get(i + 1)) },
+                        None => (data[i], data[i + 1]),
+                    };
+                    wa[z * 2] = re;
+                    wa[z * 2 + 1] = im;
                 }
-                let mut coef_1: Complex<T> = Complex::one();
                 for z in 0..cparams.radix {
+                    // The coefficient of the `w`-th input is
+                    // `twiddles[(y + z * unit) * w mod len]`
+                    let step = y + z * cparams.unit;
+                    let mut index = 0;
                     let mut c: Complex<T> = Complex::zero();
-                    let mut coef_2: Complex<T> = Complex::one();
-                    let coef_1_tw: Complex<T> = coef_1 * twiddle_1;
                     for w in 0..cparams.radix {
-                        c = c + coef_2 * complex_from_slice(&wa[w * 2..]);
-                        coef_2 = coef_2 * coef_1_tw;
+                        c = c + twiddles[index] * complex_from_slice(&wa[w * 2..]);
+                        index += step;
+                        if index >= len {
+                            index -= len;
+                        }
+                    }
+                    let i = (x + y + z * cparams.unit) * 2;
+                    let (re, im) = (c.re * self.scale, c.im * self.scale);
+                    match output {
+                        Some((ref mut output, seq)) => {
+                            output[seq.index(i)] = re;
+                            output[seq.index(i + 1)] = im;
+                        }
+                        None => {
+                            data[i] = re;
+                            data[i + 1] = im;
+                        }
                     }
-                    data[(x + y + z * cparams.unit) * 2] = c.re;
-                    data[(x + y + z * cparams.unit) * 2 + 1] = c.im;
-                    coef_1 = coef_1 * coef_delta;
                 }
-                twiddle_1 = twiddle_1 * twiddle_delta;
             }
         }
     }
+}
+
+impl<T> Kernel<T> for GenericDifKernel<T>
+where
+    T: Num,
+{
+    fn transform(&self, params: &mut KernelParams<T>) {
+        self.transform_inner(None, None, params);
+    }
+
+    fn transform_from(&self, input: &[T], params: &mut KernelParams<T>) {
+        self.transform_inner(Some(KernelInput::Contiguous(input)), None, params);
+    }
+
+    fn transform_from_strided(
+        &self,
+        data: &[T],
+        input: &StridedSequence,
+        params: &mut KernelParams<T>,
+    ) {
+        self.transform_inner(Some(KernelInput::Strided(data, input)), None, params);
+    }
+
+    fn transform_into_strided(
+        &self,
+        params: &mut KernelParams<T>,
+        data: &mut [T],
+        output: &StridedSequence,
+    ) {
+        self.transform_inner(None, Some((data, output)), params);
+    }
 
     fn required_work_area_size(&self) -> usize {
         self.cparams.radix * 2
     }
+
+    fn table_size(&self) -> usize {
+        mem::size_of_val(&self.twiddles[..])
+    }
+
+    fn backend(&self) -> Backend {
+        Backend::Generic
+    }
 }
 
-impl<T> Kernel<T> for GenericDifKernel<T>
+impl<T> GenericDifKernel<T>
 where
     T: Num,
 {
-    fn transform(&self, params: &mut KernelParams<T>) {
+    /// Reads the sequence from `input` (or `params.coefs` if `None`) and
+    /// writes the result to `output` (or `params.coefs` if `None`).
+    fn transform_inner(
+        &self,
+        input: Option<KernelInput<T>>,
+        output: Option<(&mut [T], &StridedSequence)>,
+        params: &mut KernelParams<T>,
+    ) {
         let cparams = &self.cparams;
         let mut data = unsafe { SliceAccessor::new(&mut params.coefs[0..cparams.size * 2]) };
+        let input = input.map(|x| x.prefix(cparams.size * 2));
+        let mut output = output.map(|(x, seq)| (unsafe { SliceAccessor::new(x) }, seq));
         let mut wa = unsafe { SliceAccessor::new(&mut params.work_area[0..cparams.radix * 2]) };
 
-        let twiddle_delta = self.twiddle_delta;
-        let coef_delta = self.coef_delta;
+        let twiddles = unsafe { SliceAccessor::new(&self.twiddles[..]) };
+        let len = cparams.radix * cparams.unit;
 
-        for x in range_step(0, cparams.size, cparams.unit * cparams.radix) {
-            let mut twiddle_1: Complex<T> = Complex::one();
+        for x in range_step(0, cparams.size, len) {
             for y in 0..cparams.unit {
                 for z in 0..cparams.radix {
-                    wa[z * 2] = data[(x + y + z * cparams.unit) * 2];
-                    wa[z * 2 + 1] = data[(x + y + z * cparams.unit) * 2 + 1];
+                    let i = (x + y + z * cparams.unit) * 2;
+                    let (re, im) = match input {
+                        Some(ref input) => unsafe { (input.get(i), input.get(i + 1)) },
+                        None => (data[i], data[i + 1]),
+                    };
+                    wa[z * 2] = re;
+                    wa[z * 2 + 1] = im;
                 }
-                let mut twiddle_2: Complex<T> = Complex::one();
-                let mut coef_1: Complex<T> = Complex::one();
                 for z in 0..cparams.radix {
+                    // The coefficient of the `w`-th input is
+                    // `twiddles[z * (y + w * unit) mod len]`
+                    let step = z * cparams.unit;
+                    let mut index = y * z;
                     let mut c: Complex<T> = Complex::zero();
-                    let mut coef_2 = twiddle_2;
                     for w in 0..cparams.radix {
-                        c = c + coef_2 * complex_from_slice(&wa[w * 2..]);
-                        coef_2 = coef_2 * coef_1;
+                        c = c + twiddles[index] * complex_from_slice(&wa[w * 2..]);
+                        index += step;
+                        if index >= len {
+                            index -= len;
+                        }
+                    }
+                    let i = (x + y + z * cparams.unit) * 2;
+                    let (re, im) = (c.re * self.scale, c.im * self.scale);
+                    match output {
+                        Some((ref mut output, seq)) => {
+                            output[seq.index(i)] = re;
+                            output[seq.index(i + 1)] = im;
+                        }
+                        None => {
+                            data[i] = re;
+                            data[i + 1] = im;
+                        }
                     }
-                    data[(x + y + z * cparams.unit) * 2] = c.re;
-                    data[(x + y + z * cparams.unit) * 2 + 1] = c.im;
-                    twiddle_2 = twiddle_2 * twiddle_1;
-                    coef_1 = coef_1 * coef_delta;
                 }
-                twiddle_1 = twiddle_1 * twiddle_delta;
             }
         }
     }
-
-    fn required_work_area_size(&self) -> usize {
-        self.cparams.radix * 2
-    }
 }
@@ -1,39 +1,70 @@
 //! Defines generic FFT kernels optimized for certain known radix values, but without any specific processor or
 //! instruction set specific optimizations.
 //!
+//! The kernels are only compiled if the `backend-generic2` feature is enabled.
+//! `dif_fft` is always available because `MultiSizeSetup` depends on it.
+//!
 //! Performances
 //! ------------
 //!
 //! According to a benchmark result, this kernel runs about 10x slower than a commercial-level FFT library on a Skylake
 //! machine.
 
-use super::utils::{branch_on_static_params, StaticParams, StaticParamsConsumer};
-use super::{Kernel, KernelCreationParams, KernelParams, KernelType, SliceAccessor};
+#[cfg(feature = "backend-generic2")]
+use super::staticparams::{branch_on_static_params, StaticParams, StaticParamsConsumer};
+#[cfg(feature = "backend-generic2")]
+use super::tables::{table, Table};
+#[cfg(any(feature = "backend-generic2", all(test, feature = "static-tables")))]
+use super::twiddle::twiddle;
+#[cfg(feature = "backend-generic2")]
+use super::{Backend, KernelInput, KernelParams, KernelType};
+use super::{Kernel, KernelCreationParams, SliceAccessor};
+use alloc::boxed::Box;
+#[cfg(any(feature = "backend-generic2", all(test, feature = "static-tables")))]
+use alloc::vec::Vec;
 
 use num_complex::Complex;
 use num_iter::range_step;
-use num_traits::{One, Zero};
 
+#[cfg(feature = "backend-generic2")]
+use super::super::StridedSequence;
 use super::super::{mul_pos_i, Num};
 
-use std::fmt::Debug;
-use std::marker::PhantomData;
+use core::fmt::Debug;
+#[cfg(feature = "backend-generic2")]
+use core::marker::PhantomData;
+#[cfg(feature = "backend-generic2")]
+use core::mem;
 
-pub fn new_specialized_generic_kernel<T>(cparams: &KernelCreationParams) -> Option<Box<Kernel<T>>>
+#[cfg(feature = "backend-generic2")]
+pub fn new_specialized_generic_kernel<T>(
+    cparams: &KernelCreationParams,
+    scale: f64,
+) -> Option<Box<Kernel<T>>>
 where
     T: Num,
 {
     branch_on_static_params(
         cparams,
         Factory::<T> {
-            phantom: PhantomData,
+            scale: T::from(scale).unwrap(),
         },
     )
 }
 
+#[cfg(not(feature = "backend-generic2"))]
+pub fn new_specialized_generic_kernel<T>(
+    _: &KernelCreationParams,
+    _: f64,
+) -> Option<Box<Kernel<T>>> {
+    None
+}
+
+#[cfg(feature = "backend-generic2")]
 struct Factory<T> {
-    phantom: PhantomData<T>,
+    scale: T,
 }
+#[cfg(feature = "backend-generic2")]
 impl<T: Num> StaticParamsConsumer<Option<Box<Kernel<T>>>> for Factory<T> {
     fn consume<TSParams>(
         self,
@@ -44,15 +75,7 @@ impl<T: Num> StaticParamsConsumer<Option<Box<Kernel<T>>>> for Factory<T> {
         TSParams: StaticParams,
         T: Num,
     {
-        let full_circle = if cparams.inverse { 2 } else { -2 };
-        let twiddle_delta = Complex::new(
-            Zero::zero(),
-            T::from(cparams.size / cparams.radix / cparams.unit).unwrap()
-                * T::from(full_circle).unwrap()
-                * T::PI()
-                / T::from(cparams.size).unwrap(),
-        )
-        .exp();
+        let twiddles = new_twiddle_table(cparams.radix, cparams.unit, cparams.inverse);
 
         match cparams.radix {
             2 => Some(Box::new(SpecializedGenericDitKernel::<
@@ -61,9 +84,10 @@ impl<T: Num> StaticParamsConsumer<Option<Box<Kernel<T>>>> for Factory<T> {
                 TSParams,
             > {
                 cparams: *cparams,
-                twiddle_delta: twiddle_delta,
+                twiddles: twiddles,
                 small_fft: PhantomData,
                 sparams: sparams,
+                scale: self.scale,
             })),
             4 => Some(Box::new(SpecializedGenericDitKernel::<
                 T,
@@ -71,22 +95,194 @@ impl<T: Num> StaticParamsConsumer<Option<Box<Kernel<T>>>> for Factory<T> {
                 TSParams,
             > {
                 cparams: *cparams,
-                twiddle_delta: twiddle_delta,
+                twiddles: twiddles,
                 small_fft: PhantomData,
                 sparams: sparams,
+                scale: self.scale,
             })),
             _ => None,
         }
     }
 }
 
+/// Retrieves the twiddle factor table from the static tables or the table
+/// cache, creating it if needed.
+#[cfg(feature = "backend-generic2")]
+fn new_twiddle_table<T: Num>(radix: usize, unit: usize, inverse: bool) -> Table<Complex<T>> {
+    let params = [radix as u64, unit as u64, inverse as u64];
+    table("generic2_twiddles", &params, || {
+        twiddle_table(radix, unit, inverse)
+    })
+}
+
+/// Computes the twiddle factor table. The `k`-th (`0 <= k < radix - 1`)
+/// twiddle factor for the `y`-th sub-FFT is stored at the index
+/// `y * (radix - 1) + k`.
+///
+/// Each element is computed directly in double precision (rather than by
+/// repeated multiplication), so the error doesn't grow with `unit`.
+#[cfg(any(feature = "backend-generic2", all(test, feature = "static-tables")))]
+pub(super) fn twiddle_table<T: Num>(radix: usize, unit: usize, inverse: bool) -> Vec<Complex<T>> {
+    let full_circle = if inverse { 2 } else { -2 };
+    let mut table = Vec::with_capacity((radix - 1) * unit);
+    for y in 0..unit {
+        for k in 1..radix {
+            table.push(twiddle(full_circle * (k * y) as isize, radix * unit));
+        }
+    }
+    table
+}
+
+/// Performs a radix-`radix` (2 or 4) decimation-in-frequency butterfly stage
+/// on `data` without creating a kernel. `data` holds `size` complex numbers
+/// as interleaved real and imaginary components.
+///
+/// `twiddle(k)` must return `exp(∓2πik/(radix * unit))` (the sign depends on
+/// `inverse`) for `0 <= k < radix * unit`.
+pub fn dif_butterflies<T, F>(data: &mut [T], radix: usize, unit: usize, inverse: bool, twiddle: F)
+where
+    T: Num,
+    F: Fn(usize) -> Complex<T>,
+{
+    match radix {
+        2 => dif_butterflies_inner::<T, SmallFFT2<T>, F>(data, unit, inverse, twiddle),
+        4 => dif_butterflies_inner::<T, SmallFFT4<T>, F>(data, unit, inverse, twiddle),
+        _ => unreachable!(),
+    }
+}
+
+/// Computes the complex FFT of `data` in place by a sequence of
+/// `dif_butterflies` stages (radix 4 where possible) followed by the digit
+/// reversal. `data` holds `len` complex numbers as interleaved real and
+/// imaginary components, and `len` must be a power of two.
+///
+/// `twiddle(k, n)` must return `exp(∓2πik/n)` (the sign depends on `inverse`)
+/// for every `n` dividing `len` and `0 <= k < n`. This is used by
+/// `MultiSizeSetup`, which derives the twiddle factors of every length from a
+/// single table. No memory is allocated.
+pub fn dif_fft<T, F>(data: &mut [T], inverse: bool, twiddle: F)
+where
+    T: Num,
+    F: Fn(usize, usize) -> Complex<T>,
+{
+    let len = data.len() / 2;
+    debug_assert!(len.is_power_of_two());
+    let (stage_bits, num_stages) = stage_bits(len);
+    let stage_bits = &stage_bits[..num_stages];
+
+    let mut unit = len;
+    for &bits in stage_bits {
+        let radix = 1 << bits;
+        unit /= radix;
+        dif_butterflies(data, radix, unit, inverse, |k| twiddle(k, radix * unit));
+    }
+
+    digit_reverse_in_place(data, stage_bits);
+}
+
+/// Rearranges the output of the decimation-in-frequency butterfly stages
+/// whose radixes are given by `stage_bits` (a palindrome returned by
+/// `stage_bits`) to the natural order. `data` holds complex numbers as
+/// interleaved real and imaginary components.
+pub fn digit_reverse_in_place<T>(data: &mut [T], stage_bits: &[u32]) {
+    for i in 0..data.len() / 2 {
+        let j = digit_reverse(i, stage_bits);
+        if i < j {
+            data.swap(i * 2, j * 2);
+            data.swap(i * 2 + 1, j * 2 + 1);
+        }
+    }
+}
+
+/// Chooses the radixes of the butterfly stages for the length `len` (a power
+/// of two) and returns their base-2 logarithms, in the order they are
+/// applied, and the number of the stages.
+///
+/// Radix-4 stages are preferred, and the sequence is a palindrome so that the
+/// digit reversal is an involution and can be done by swapping elements in
+/// place. This is a `const fn` so that `StaticSetup` can derive the stages
+/// at compile time.
+pub const fn stage_bits(len: usize) -> ([u32; 64], usize) {
+    let total = len.trailing_zeros() as usize;
+    // The number of the radix-2 stages, which are placed in the middle
+    let num_radix2 = match (total % 2, total / 2 % 2) {
+        (0, _) => 0,
+        (_, 0) => 1,
+        (_, _) => 3,
+    };
+    let num_radix4 = (total - num_radix2) / 2;
+    let num_stages = num_radix4 + num_radix2;
+    let mut bits = [0; 64];
+    let mut i = 0;
+    while i < num_stages {
+        bits[i] = if i < num_radix4 / 2 || i >= num_radix4 / 2 + num_radix2 {
+            2
+        } else {
+            1
+        };
+        i += 1;
+    }
+    (bits, num_stages)
+}
+
+/// Computes the position of the `i`-th output of the decimation-in-frequency
+/// FFT in the natural order (see `bit_reversal_indices`).
+#[inline]
+fn digit_reverse(i: usize, stage_bits: &[u32]) -> usize {
+    let mut rest = i;
+    let mut reversed = 0;
+    for &bits in stage_bits.iter().rev() {
+        reversed = (reversed << bits) | (rest & ((1 << bits) - 1));
+        rest >>= bits;
+    }
+    reversed
+}
+
+#[inline]
+fn dif_butterflies_inner<T, TSmallFFT, F>(data: &mut [T], unit: usize, inverse: bool, twiddle: F)
+where
+    T: Num,
+    TSmallFFT: SmallFFT<T>,
+    F: Fn(usize) -> Complex<T>,
+{
+    let size = data.len() / 2;
+    let mut data = unsafe { SliceAccessor::new(data) };
+    let mut small_fft = TSmallFFT::default();
+    let radix = TSmallFFT::radix();
+
+    let mut coefs = [Complex::new(T::zero(), T::zero()); 3];
+    for y in 0..unit {
+        for k in 1..radix {
+            coefs[k - 1] = twiddle(k * y);
+        }
+        for x in range_step(0, size, unit * radix) {
+            small_fft.load(|i| data[i], (x + y) * 2, unit * 2);
+            if inverse {
+                small_fft.transform_backward();
+            } else {
+                small_fft.transform_forward();
+            }
+            small_fft.twiddle(&coefs[..radix - 1]);
+            small_fft.store(|i, v| data[i] = v, (x + y) * 2, unit * 2);
+        }
+    }
+}
+
 trait SmallFFT<T>: Debug + Default + 'static + Sync + Send {
     fn radix() -> usize;
-    fn load(&mut self, data: &SliceAccessor<&mut [T]>, offset: usize, stride: usize);
-    fn twiddle(&mut self, c: Complex<T>);
+    /// Loads the elements starting at the `offset`-th value by calling
+    /// `read` with the index of each value.
+    fn load<F: Fn(usize) -> T>(&mut self, read: F, offset: usize, stride: usize);
+    /// Multiplies the `k`-th (`1 <= k < radix`) element by `c[k - 1]`.
+    fn twiddle(&mut self, c: &[Complex<T>]);
+    /// Multiplies every element by `scale`.
+    #[cfg(feature = "backend-generic2")]
+    fn scale(&mut self, scale: T);
     fn transform_forward(&mut self);
     fn transform_backward(&mut self);
-    fn store(&self, data: &mut SliceAccessor<&mut [T]>, offset: usize, stride: usize);
+    /// Stores the elements starting at the `offset`-th value by calling
+    /// `write` with the index and the value of each value.
+    fn store<F: FnMut(usize, T)>(&self, write: F, offset: usize, stride: usize);
 }
 
 #[derive(Debug, Clone, Copy, Default)]
@@ -101,15 +297,21 @@ impl<T: Num> SmallFFT<T> for SmallFFT2<T> {
         2
     }
     #[inline]
-    fn load(&mut self, data: &SliceAccessor<&mut [T]>, offset: usize, stride: usize) {
-        self.x1.re = data[offset];
-        self.x1.im = data[offset + 1];
-        self.x2.re = data[offset + stride];
-        self.x2.im = data[offset + stride + 1];
+    fn load<F: Fn(usize) -> T>(&mut self, read: F, offset: usize, stride: usize) {
+        self.x1.re = read(offset);
+        self.x1.im = read(offset + 1);
+        self.x2.re = read(offset + stride);
+        self.x2.im = read(offset + stride + 1);
+    }
+    #[inline]
+    fn twiddle(&mut self, c: &[Complex<T>]) {
+        self.x2 = self.x2 * c[0];
     }
     #[inline]
-    fn twiddle(&mut self, c: Complex<T>) {
-        self.x2 = self.x2 * c;
+    #[cfg(feature = "backend-generic2")]
+    fn scale(&mut self, scale: T) {
+        self.x1 = self.x1 * scale;
+        self.x2 = self.x2 * scale;
     }
     #[inline]
     fn transform_forward(&mut self) {
@@ -122,11 +324,11 @@ impl<T: Num> SmallFFT<T> for SmallFFT2<T> {
         self.transform_forward();
     }
     #[inline]
-    fn store(&self, data: &mut SliceAccessor<&mut [T]>, offset: usize, stride: usize) {
-        data[offset] = self.x1.re;
-        data[offset + 1] = self.x1.im;
-        data[offset + stride] = self.x2.re;
-        data[offset + stride + 1] = self.x2.im;
+    fn store<F: FnMut(usize, T)>(&self, mut write: F, offset: usize, stride: usize) {
+        write(offset, self.x1.re);
+        write(offset + 1, self.x1.im);
+        write(offset + stride, self.x2.re);
+        write(offset + stride + 1, self.x2.im);
     }
 }
 
@@ -144,22 +346,29 @@ impl<T: Num> SmallFFT<T> for SmallFFT4<T> {
         4
     }
     #[inline]
-    fn load(&mut self, data: &SliceAccessor<&mut [T]>, offset: usize, stride: usize) {
-        self.x1.re = data[offset];
-        self.x1.im = data[offset + 1];
-        self.x2.re = data[offset + stride];
-        self.x2.im = data[offset + stride + 1];
-        self.x3.re = data[offset + stride * 2];
-        self.x3.im = data[offset + stride * 2 + 1];
-        self.x4.re = data[offset + stride * 3];
-        self.x4.im = data[offset + stride * 3 + 1];
+    fn load<F: Fn(usize) -> T>(&mut self, read: F, offset: usize, stride: usize) {
+        self.x1.re = read(offset);
+        self.x1.im = read(offset + 1);
+        self.x2.re = read(offset + stride);
+        self.x2.im = read(offset + stride + 1);
+        self.x3.re = read(offset + stride * 2);
+        self.x3.im = read(offset + stride * 2 + 1);
+        self.x4.re = read(offset + stride * 3);
+        self.x4.im = read(offset + stride * 3 + 1);
+    }
+    #[inline]
+    fn twiddle(&mut self, c: &[Complex<T>]) {
+        self.x2 = self.x2 * c[0];
+        self.x3 = self.x3 * c[1];
+        self.x4 = self.x4 * c[2];
     }
     #[inline]
-    fn twiddle(&mut self, c: Complex<T>) {
-        let c2 = c * c;
-        self.x2 = self.x2 * c;
-        self.x3 = self.x3 * c2;
-        self.x4 = self.x4 * (c * c2);
+    #[cfg(feature = "backend-generic2")]
+    fn scale(&mut self, scale: T) {
+        self.x1 = self.x1 * scale;
+        self.x2 = self.x2 * scale;
+        self.x3 = self.x3 * scale;
+        self.x4 = self.x4 * scale;
     }
     #[inline]
     fn transform_forward(&mut self) {
@@ -184,26 +393,30 @@ impl<T: Num> SmallFFT<T> for SmallFFT4<T> {
         self.x4 = t3 - mul_pos_i(t4);
     }
     #[inline]
-    fn store(&self, data: &mut SliceAccessor<&mut [T]>, offset: usize, stride: usize) {
-        data[offset] = self.x1.re;
-        data[offset + 1] = self.x1.im;
-        data[offset + stride] = self.x2.re;
-        data[offset + stride + 1] = self.x2.im;
-        data[offset + stride * 2] = self.x3.re;
-        data[offset + stride * 2 + 1] = self.x3.im;
-        data[offset + stride * 3] = self.x4.re;
-        data[offset + stride * 3 + 1] = self.x4.im;
+    fn store<F: FnMut(usize, T)>(&self, mut write: F, offset: usize, stride: usize) {
+        write(offset, self.x1.re);
+        write(offset + 1, self.x1.im);
+        write(offset + stride, self.x2.re);
+        write(offset + stride + 1, self.x2.im);
+        write(offset + stride * 2, self.x3.re);
+        write(offset + stride * 2 + 1, self.x3.im);
+        write(offset + stride * 3, self.x4.re);
+        write(offset + stride * 3 + 1, self.x4.im);
     }
 }
 
+#[cfg(feature = "backend-generic2")]
 #[derive(Debug)]
-struct SpecializedGenericDitKernel<T, TSmallFFT, TSParams> {
+struct SpecializedGenericDitKernel<T: 'static, TSmallFFT, TSParams> {
     cparams: KernelCreationParams,
-    twiddle_delta: Complex<T>,
+    twiddles: Table<Complex<T>>,
     small_fft: PhantomData<TSmallFFT>,
     sparams: TSParams,
+    /// The factor every output is multiplied by.
+    scale: T,
 }
 
+#[cfg(feature = "backend-generic2")]
 impl<T, TSmallFFT, TSParams> Kernel<T> for SpecializedGenericDitKernel<T, TSmallFFT, TSParams>
 where
     T: Num,
@@ -211,11 +424,57 @@ where
     TSParams: StaticParams,
 {
     fn transform(&self, params: &mut KernelParams<T>) {
+        self.transform_inner(None, None, params);
+    }
+    fn transform_from(&self, input: &[T], params: &mut KernelParams<T>) {
+        self.transform_inner(Some(KernelInput::Contiguous(input)), None, params);
+    }
+    fn transform_from_strided(
+        &self,
+        data: &[T],
+        input: &StridedSequence,
+        params: &mut KernelParams<T>,
+    ) {
+        self.transform_inner(Some(KernelInput::Strided(data, input)), None, params);
+    }
+    fn transform_into_strided(
+        &self,
+        params: &mut KernelParams<T>,
+        data: &mut [T],
+        output: &StridedSequence,
+    ) {
+        self.transform_inner(None, Some((data, output)), params);
+    }
+    fn backend(&self) -> Backend {
+        Backend::Generic2
+    }
+    fn table_size(&self) -> usize {
+        mem::size_of_val(&self.twiddles[..])
+    }
+}
+
+#[cfg(feature = "backend-generic2")]
+impl<T, TSmallFFT, TSParams> SpecializedGenericDitKernel<T, TSmallFFT, TSParams>
+where
+    T: Num,
+    TSmallFFT: SmallFFT<T>,
+    TSParams: StaticParams,
+{
+    /// Reads the sequence from `input` (or `params.coefs` if `None`) and
+    /// writes the result to `output` (or `params.coefs` if `None`).
+    fn transform_inner(
+        &self,
+        input: Option<KernelInput<T>>,
+        output: Option<(&mut [T], &StridedSequence)>,
+        params: &mut KernelParams<T>,
+    ) {
         let cparams = &self.cparams;
         let sparams = &self.sparams;
         let mut data = unsafe { SliceAccessor::new(&mut params.coefs[0..cparams.size * 2]) };
+        let input = input.map(|x| x.prefix(cparams.size * 2));
+        let mut output = output.map(|(x, seq)| (unsafe { SliceAccessor::new(x) }, seq));
 
-        let twiddle_delta = self.twiddle_delta;
+        let twiddles = unsafe { SliceAccessor::new(&self.twiddles[..]) };
         let mut small_fft = TSmallFFT::default();
 
         let radix = TSmallFFT::radix();
@@ -223,11 +482,17 @@ where
 
         let pre_twiddle = sparams.kernel_type() == KernelType::Dit;
         let post_twiddle = sparams.kernel_type() == KernelType::Dif;
+        let scaled = self.scale != T::one();
 
         for x in range_step(0, cparams.size, cparams.unit * radix) {
-            let mut twiddle_1: Complex<T> = Complex::one();
             for y in 0..cparams.unit {
-                small_fft.load(&data, (x + y) * 2, cparams.unit * 2);
+                let twiddle_1 = &twiddles[y * (radix - 1)..(y + 1) * (radix - 1)];
+                match input {
+                    Some(ref input) => {
+                        small_fft.load(|i| unsafe { input.get(i) }, (x + y) * 2, cparams.unit * 2)
+                    }
+                    None => small_fft.load(|i| data[i], (x + y) * 2, cparams.unit * 2),
+                }
 
                 if pre_twiddle {
                     small_fft.twiddle(twiddle_1);
@@ -240,10 +505,18 @@ where
                 if post_twiddle {
                     small_fft.twiddle(twiddle_1);
                 }
+                if scaled {
+                    small_fft.scale(self.scale);
+                }
 
-                small_fft.store(&mut data, (x + y) * 2, cparams.unit * 2);
-
-                twiddle_1 = twiddle_1 * twiddle_delta;
+                match output {
+                    Some((ref mut output, seq)) => small_fft.store(
+                        |i, v| output[seq.index(i)] = v,
+                        (x + y) * 2,
+                        cparams.unit * 2,
+                    ),
+                    None => small_fft.store(|i, v| data[i] = v, (x + y) * 2, cparams.unit * 2),
+                }
             }
         }
     }
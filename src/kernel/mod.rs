@@ -1,44 +1,97 @@
 mod accessor;
 mod bitreversal;
 mod convert;
+#[cfg(feature = "backend-generic")]
 mod generic;
 mod generic2;
 mod realfft;
+#[cfg(feature = "std")]
+mod registry;
+// Used by the `Backend::Generic2` kernels and the x86 butterfly kernels
+#[cfg(any(
+    feature = "backend-generic2",
+    all(
+        any(target_arch = "x86", target_arch = "x86_64"),
+        target_feature = "sse",
+        any(
+            feature = "backend-sse",
+            all(target_feature = "sse3", feature = "backend-sse3"),
+            all(target_feature = "avx", feature = "backend-avx")
+        )
+    )
+))]
+mod staticparams;
+mod tablegen;
+mod tables;
+mod twiddle;
 mod utils;
+mod validate;
 
-#[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
+#[cfg(all(
+    any(target_arch = "x86", target_arch = "x86_64"),
+    target_feature = "sse"
+))]
 mod x86;
 
-// Stub for non-x86 systems
-#[cfg(not(any(target_arch = "x86", target_arch = "x86_64")))]
+// Stub for non-x86 systems and x86 systems without SSE
+#[cfg(not(all(
+    any(target_arch = "x86", target_arch = "x86_64"),
+    target_feature = "sse"
+)))]
 mod x86 {
+    use alloc::boxed::Box;
+
     pub fn new_x86_kernel<T>(
-        cparams: &super::KernelCreationParams,
+        _: &super::KernelCreationParams,
+        _: f64,
+        _: super::Backend,
     ) -> Option<Box<super::Kernel<T>>> {
         None
     }
     pub unsafe fn new_x86_bit_reversal_kernel<T>(
-        indices: &Vec<usize>,
+        _: &super::tables::Table<usize>,
+        _: f64,
+        _: super::Backend,
     ) -> Option<Box<super::Kernel<T>>> {
         None
     }
     pub fn new_x86_real_fft_pre_post_process_kernel<T>(
-        len: usize,
-        inverse: bool,
+        _: usize,
+        _: bool,
+        _: f64,
+        _: super::Backend,
     ) -> Option<Box<super::Kernel<T>>> {
         None
     }
 }
 
-use super::Num;
-use std::fmt::Debug;
+// Stub for `no_std` builds, which have no registry
+#[cfg(not(feature = "std"))]
+mod registry {
+    use alloc::boxed::Box;
+
+    pub fn new_custom_kernel<T>(_: &super::KernelCreationParams) -> Option<Box<super::Kernel<T>>> {
+        None
+    }
+}
+
+use super::{Num, StridedSequence};
+use alloc::boxed::Box;
+use core::fmt::Debug;
 
 use self::accessor::SliceAccessor;
 
 pub use self::bitreversal::new_bit_reversal_kernel;
 pub use self::convert::*;
+pub use self::generic2::{dif_fft, digit_reverse_in_place, stage_bits};
 pub use self::realfft::*;
+#[cfg(feature = "std")]
+pub use self::registry::{clear_kernel_factories, register_kernel_factory};
+pub use self::twiddle::twiddle;
+pub use self::validate::{validate_kernel, KernelValidationError};
 
+/// Specifies the formulation of a butterfly pass (see
+/// `KernelCreationParams`).
 #[derive(Debug, Clone, Copy, Ord, PartialOrd, Eq, PartialEq)]
 pub enum KernelType {
     /// Decimation-in-time.
@@ -48,41 +101,253 @@ pub enum KernelType {
     Dif,
 }
 
-// for Radix-2 DIT, (dim1, dim2) = (2, x)
-// for Radix-2 DIF, (dim1, dim2) = (x, 2) where x <= size / 2
+/// Describes a butterfly pass.
+///
+/// A butterfly pass transforms a complex sequence `data` of `size` elements in
+/// place. The sequence is divided into blocks of `radix * unit` elements. For
+/// each block starting at `x` and each `0 <= y < unit`, the `radix` elements
+/// `a[w] = data[x + y + w * unit]` (`0 <= w < radix`) are replaced with
+/// `b[z] = data[x + y + z * unit]` (`0 <= z < radix`) defined as follows,
+/// where `ω = exp(∓2πi/(radix * unit))` (the sign is positive if `inverse`
+/// is `true`):
+///
+///  - `Dit`: `b[z] = Σ_w ω^((y + z * unit) * w) * a[w]`
+///  - `Dif`: `b[z] = Σ_w ω^(z * (y + w * unit)) * a[w]`
+///
+/// A decimation-in-time transform with the radixes `r_1, …, r_n` consists of
+/// the `Dit` passes with `unit = 1, r_1, r_1 * r_2, …` in this order, and a
+/// decimation-in-frequency one consists of the `Dif` passes with
+/// `unit = size / r_1, size / (r_1 * r_2), …, 1`.
 #[derive(Debug, Clone, Copy, Eq, PartialEq)]
 pub struct KernelCreationParams {
+    /// The number of complex elements in the sequence, which is a multiple of
+    /// `radix * unit`.
     pub size: usize,
+
+    /// The formulation of the pass.
     pub kernel_type: KernelType,
+
+    /// The number of elements combined by each butterfly (at least 2).
     pub radix: usize,
 
-    /// It's kinda hard to describe so I'll just put a bound here:
-    /// `1 <= unit <= size / radix` I hope you get the idea.
+    /// The distance between the elements combined by each butterfly.
+    /// `1 <= unit <= size / radix`.
     pub unit: usize,
 
+    /// Specifies whether the inverse (backward) transformation is used.
     pub inverse: bool,
 }
 
+/// The buffers supplied to `Kernel::transform`.
 #[derive(Debug)]
 pub struct KernelParams<'a, T: 'a> {
+    /// The sequence to transform in place, stored as interleaved real and
+    /// imaginary parts. It may be longer than the sequence, in which case the
+    /// remaining values must be left intact. It is not necessarily aligned.
     pub coefs: &'a mut [T],
+
+    /// The scratch space of at least `Kernel::required_work_area_size()`
+    /// values. Its initial contents are unspecified.
     pub work_area: &'a mut [T],
 }
 
+/// The input sequence of a kernel reading somewhere other than
+/// `KernelParams::coefs` (see `Kernel::transform_from` and
+/// `Kernel::transform_from_strided`).
+#[derive(Debug, Clone, Copy)]
+pub enum KernelInput<'a, T: 'a> {
+    Contiguous(&'a [T]),
+    Strided(&'a [T], &'a StridedSequence),
+}
+
+impl<'a, T: Copy + 'a> KernelInput<'a, T> {
+    /// Limits a contiguous sequence to the first `len` values.
+    pub fn prefix(self, len: usize) -> Self {
+        match self {
+            KernelInput::Contiguous(x) => KernelInput::Contiguous(&x[0..len]),
+            KernelInput::Strided(..) => self,
+        }
+    }
+
+    /// Reads the `i`-th value of the sequence. Like `SliceAccessor`, this
+    /// doesn't check the bounds unless the `checked` feature is enabled, so
+    /// the caller must ensure `i` is in the sequence.
+    #[inline]
+    pub unsafe fn get(&self, i: usize) -> T {
+        match *self {
+            KernelInput::Contiguous(x) => SliceAccessor::new(x)[i],
+            KernelInput::Strided(x, seq) => SliceAccessor::new(x)[seq.index(i)],
+        }
+    }
+}
+
+/// Identifies the family of kernels a kernel belongs to.
+///
+/// The variants are ordered by the level of optimization, which is used by
+/// `Options::max_backend` to limit the kernel selection.
+///
+/// The kernels of each built-in backend are only included in the library if
+/// the corresponding `backend-*` feature (e.g., `backend-sse3` for `Sse3`) is
+/// enabled.
+#[derive(Debug, Clone, Copy, Ord, PartialOrd, Eq, PartialEq, Hash)]
+pub enum Backend {
+    /// The generic kernels supporting any radix and data type.
+    Generic,
+
+    /// The generic kernels specialized for particular radixes.
+    Generic2,
+
+    /// The kernels optimized by using the SSE instruction set.
+    Sse,
+
+    /// The kernels optimized by using the SSE3 instruction set.
+    Sse3,
+
+    /// The kernels optimized by using the AVX instruction set.
+    Avx,
+
+    /// The custom kernels created by the factories registered by
+    /// `register_kernel_factory`.
+    Custom,
+}
+
+/// A stage of a `Setup`, such as a butterfly pass.
+///
+/// Custom butterfly kernels implement this trait and are supplied through
+/// `register_kernel_factory`.
 pub trait Kernel<T>: Debug + Sync + Send {
+    /// Applies the stage to `params.coefs`.
     fn transform(&self, params: &mut KernelParams<T>);
+
+    /// Applies the stage to the sequence `input` and stores the result to
+    /// `params.coefs`, leaving `input` intact. The result must be identical to
+    /// that of copying `input` to the beginning of `params.coefs` and calling
+    /// `transform`, which is what the default implementation does.
+    ///
+    /// This is used for the first stage of an out-of-place transformation
+    /// (`Env::transform_into`). `input` contains the input sequence of the
+    /// `Setup`, so the stage must not read any values of `params.coefs`
+    /// beyond `input.len()`. Kernels that read each value once can override
+    /// this to read from `input` directly and skip the copy.
+    fn transform_from(&self, input: &[T], params: &mut KernelParams<T>)
+    where
+        T: Copy,
+    {
+        params.coefs[0..input.len()].copy_from_slice(input);
+        self.transform(params);
+    }
+
+    /// Applies the stage to the strided sequence `input` in `data` and stores
+    /// the result to `params.coefs`. The result must be identical to that of
+    /// `input.gather(data, params.coefs)` followed by `transform`, which is
+    /// what the default implementation does.
+    ///
+    /// This is used for the first stage of `Env::transform_strided`, in the
+    /// same way as `transform_from`.
+    fn transform_from_strided(
+        &self,
+        data: &[T],
+        input: &StridedSequence,
+        params: &mut KernelParams<T>,
+    ) where
+        T: Copy,
+    {
+        input.gather(data, params.coefs);
+        self.transform(params);
+    }
+
+    /// Applies the stage to `params.coefs` and stores the result to the
+    /// strided sequence `output` in `data`. The result must be identical to
+    /// that of `transform` followed by `output.scatter(params.coefs, data)`,
+    /// which is what the default implementation does. `params.coefs` may be
+    /// left in any state.
+    ///
+    /// This is used for the last stage of `Env::transform_strided`.
+    fn transform_into_strided(
+        &self,
+        params: &mut KernelParams<T>,
+        data: &mut [T],
+        output: &StridedSequence,
+    ) where
+        T: Copy,
+    {
+        self.transform(params);
+        output.scatter(params.coefs, data);
+    }
+
+    /// Retrieves the number of values `transform` requires in
+    /// `params.work_area`.
     fn required_work_area_size(&self) -> usize {
         0
     }
+
+    /// Identifies the family of this kernel for `PlanReport`. Defaults to
+    /// `Backend::Custom`; the built-in kernels override it.
+    fn backend(&self) -> Backend {
+        Backend::Custom
+    }
+
+    /// The number of bytes occupied by the precomputed tables.
+    fn table_size(&self) -> usize {
+        0
+    }
 }
 
 impl<T> Kernel<T>
 where
     T: Num + 'static,
 {
-    pub fn new(cparams: &KernelCreationParams) -> Box<Kernel<T>> {
-        x86::new_x86_kernel(cparams)
-            .or_else(|| generic2::new_specialized_generic_kernel(cparams))
-            .unwrap_or_else(|| generic::new_generic_kernel(cparams))
+    /// Creates the most optimized kernel whose backend doesn't exceed
+    /// `max_backend`. Every output of the kernel is multiplied by `scale`,
+    /// which must be one unless `cparams.unit == 1`.
+    ///
+    /// The custom kernels aren't given a scale, so they are skipped if
+    /// `scale != 1`.
+    ///
+    /// Panics if `is_radix_supported(cparams.radix, max_backend)` is `false`.
+    pub(crate) fn new(
+        cparams: &KernelCreationParams,
+        scale: f64,
+        max_backend: Backend,
+    ) -> Box<Kernel<T>> {
+        assert!(scale == 1.0 || cparams.unit == 1);
+        utils::if_allowed(max_backend, Backend::Custom, || {
+            if scale == 1.0 {
+                registry::new_custom_kernel(cparams)
+            } else {
+                None
+            }
+        })
+        .or_else(|| x86::new_x86_kernel(cparams, scale, max_backend))
+        .or_else(|| {
+            utils::if_allowed(max_backend, Backend::Generic2, || {
+                generic2::new_specialized_generic_kernel(cparams, scale)
+            })
+        })
+        .or_else(|| new_generic_kernel(cparams, scale))
+        .expect("no kernel is available for the radix")
     }
 }
+
+#[cfg(feature = "backend-generic")]
+fn new_generic_kernel<T: Num + 'static>(
+    cparams: &KernelCreationParams,
+    scale: f64,
+) -> Option<Box<Kernel<T>>> {
+    Some(generic::new_generic_kernel(cparams, scale))
+}
+
+#[cfg(not(feature = "backend-generic"))]
+fn new_generic_kernel<T>(_: &KernelCreationParams, _: f64) -> Option<Box<Kernel<T>>> {
+    None
+}
+
+/// Checks whether `Kernel::new` can create a kernel of the radix `radix`
+/// without exceeding `max_backend` using the kernels compiled into the
+/// library.
+pub fn is_radix_supported(radix: usize, max_backend: Backend) -> bool {
+    cfg!(feature = "backend-generic")
+        || (cfg!(feature = "backend-generic2")
+            && (radix == 2 || radix == 4)
+            && max_backend >= Backend::Generic2)
+}
@@ -1,35 +1,49 @@
-use super::{Kernel, KernelParams, SliceAccessor};
+use super::tables::{table, Table};
+use super::twiddle::twiddle;
+use super::{Backend, Kernel, KernelParams, SliceAccessor};
+use alloc::boxed::Box;
+use alloc::vec::Vec;
 
+use core::mem;
 use {mul_pos_i, Complex, Num};
 
 /// Creates a real FFT post-processing or backward real FFT pre-processing kernel.
-pub fn new_real_fft_pre_post_process_kernel<T>(len: usize, inverse: bool) -> Box<Kernel<T>>
+///
+/// The output is multiplied by `scale`.
+pub fn new_real_fft_pre_post_process_kernel<T>(
+    len: usize,
+    inverse: bool,
+    scale: f64,
+    max_backend: Backend,
+) -> Box<Kernel<T>>
 where
     T: Num,
 {
-    super::x86::new_x86_real_fft_pre_post_process_kernel(len, inverse).unwrap_or_else(|| {
-        assert!(len % 2 == 0);
-        Box::new(RealFFTPrePostProcessKernel {
-            len,
-            table: new_real_fft_coef_table(len, inverse),
-            inverse,
+    super::x86::new_x86_real_fft_pre_post_process_kernel(len, inverse, scale, max_backend)
+        .unwrap_or_else(|| {
+            assert!(len % 2 == 0);
+            let params = [len as u64, inverse as u64, scale.to_bits()];
+            let scale = T::from(scale).unwrap();
+            Box::new(RealFFTPrePostProcessKernel {
+                len,
+                table: table("real_fft_coefs", &params, || {
+                    new_real_fft_coef_table(len, inverse, scale)
+                }),
+                inverse,
+                scale,
+            })
         })
-    })
 }
 
-pub(super) fn new_real_fft_coef_table<T>(len: usize, inverse: bool) -> Vec<T>
+pub(super) fn new_real_fft_coef_table<T>(len: usize, inverse: bool, scale: T) -> Vec<T>
 where
     T: Num,
 {
     assert!(len % 2 == 0);
     let mut table = Vec::with_capacity(len * 2);
-    let half = T::from(0.5).unwrap();
+    let half = T::from(0.5).unwrap() * scale;
     for i in 0..(len / 2) {
-        let c = Complex::new(
-            T::zero(),
-            T::from(i).unwrap() * -T::PI() / T::from(len / 2).unwrap(),
-        )
-        .exp();
+        let c: Complex<T> = twiddle(-(i as isize), len / 2);
 
         let a = (Complex::new(T::one(), T::zero()) - mul_pos_i(c)) * half;
         let b = (Complex::new(T::one(), T::zero()) + mul_pos_i(c)) * half;
@@ -48,11 +62,57 @@ where
     table
 }
 
+/// Performs the same operation as the kernel created by
+/// `new_real_fft_pre_post_process_kernel` (with `scale == 1`) on `data`
+/// without a coefficient table. `data.len()` is the length of the complex FFT.
+///
+/// `twiddle(k)` must return `exp(-2πik/len)` where `len == data.len() * 2` for
+/// `0 <= k < data.len()`. This is used by `MultiSizeSetup`, which derives the
+/// twiddle factors of every length from a single table.
+pub fn real_fft_pre_post_process<T, F>(data: &mut [Complex<T>], inverse: bool, twiddle: F)
+where
+    T: Num,
+    F: Fn(usize) -> Complex<T>,
+{
+    let len_2 = data.len();
+    let half = T::from(0.5).unwrap();
+
+    let x = data[0];
+    data[0] = if inverse {
+        Complex::new(x.re + x.im, x.re - x.im) * half
+    } else {
+        Complex::new(x.re + x.im, x.re - x.im)
+    };
+
+    // A(k) = (1 - jc) / 2, B(k) = (1 + jc) / 2 where c = exp(-2πik/len)
+    let coefs = |k: usize| {
+        let jc = mul_pos_i(twiddle(k));
+        let one = Complex::new(T::one(), T::zero());
+        let a = (one - jc) * half;
+        let b = (one + jc) * half;
+        if inverse {
+            (a.conj(), b.conj())
+        } else {
+            (a, b)
+        }
+    };
+
+    for i in 1..len_2 / 2 + 1 {
+        let (a1, b1) = coefs(i);
+        let (a2, b2) = coefs(len_2 - i);
+        let x1 = data[i];
+        let x2 = data[len_2 - i];
+        data[i] = x1 * a1 + x2.conj() * b1;
+        data[len_2 - i] = x2 * a2 + x1.conj() * b2;
+    }
+}
+
 #[derive(Debug)]
-struct RealFFTPrePostProcessKernel<T> {
+struct RealFFTPrePostProcessKernel<T: 'static> {
     len: usize,
-    table: Vec<T>,
+    table: Table<T>,
     inverse: bool,
+    scale: T,
 }
 
 impl<T> Kernel<T> for RealFFTPrePostProcessKernel<T>
@@ -60,9 +120,35 @@ where
     T: Num,
 {
     fn transform(&self, params: &mut KernelParams<T>) {
+        self.transform_inner(None, params);
+    }
+    fn transform_from(&self, input: &[T], params: &mut KernelParams<T>) {
+        self.transform_inner(Some(input), params);
+    }
+    fn table_size(&self) -> usize {
+        mem::size_of_val(&self.table[..])
+    }
+    fn backend(&self) -> Backend {
+        Backend::Generic
+    }
+}
+
+impl<T> RealFFTPrePostProcessKernel<T>
+where
+    T: Num,
+{
+    fn transform_inner(&self, input: Option<&[T]>, params: &mut KernelParams<T>) {
         let mut data = unsafe { SliceAccessor::new(&mut params.coefs[0..self.len]) };
+        let input = input.map(|x| unsafe { SliceAccessor::new(&x[0..self.len]) });
         let table = unsafe { SliceAccessor::new(&self.table[..]) };
         let len_2 = self.len / 2;
+        // Each element is read before it's written, so `input` can be read in
+        // place of `data`
+        let read = |data: &SliceAccessor<&mut [T]>, i: usize| match input {
+            Some(ref input) => input[i],
+            None => data[i],
+        };
+        let (x1, x2) = (read(&data, 0), read(&data, 1));
         if !self.inverse {
             // A(0) = (1 - j) / 2, B(0) = (1 + j) / 2
             // A(k) = (1 + j) / 2, B(k) = (1 - j) / 2
@@ -71,31 +157,30 @@ where
             // Store G(0) as X_r(0)
             // Store G(N/2) as X_i(0)
             // data[1] = data[0] - data[1];
-            let (x1, x2) = (data[0], data[1]);
-            data[0] = x1 + x2;
-            data[1] = x1 - x2;
+            data[0] = (x1 + x2) * self.scale;
+            data[1] = (x1 - x2) * self.scale;
         } else {
             // A(0) = (1 + j) / 2, B(0) = (1 - j) / 2
             // A(k) = (1 - j) / 2, B(k) = (1 + j) / 2
             // Re(x) A(0) + Im(x) B(0) = (Re(x) + Im(x)) / 2 + j(Re(x) - Im(x)) /2
-            let (x1, x2) = (data[0], data[1]);
-            data[0] = (x1 + x2) * T::from(0.5).unwrap();
-            data[1] = (x1 - x2) * T::from(0.5).unwrap();
+            let half = T::from(0.5).unwrap() * self.scale;
+            data[0] = (x1 + x2) * half;
+            data[1] = (x1 - x2) * half;
         }
         for i in 1..len_2 / 2 + 1 {
             let a1r = table[i * 4];
             let b1r = table[i * 4 + 2];
             let a2r = table[(len_2 - i) * 4];
             let b2r = table[(len_2 - i) * 4 + 2];
-            let x1r = data[i * 2];
-            let x2r = data[(len_2 - i) * 2];
+            let x1r = read(&data, i * 2);
+            let x2r = read(&data, (len_2 - i) * 2);
 
             let a1i = table[i * 4 + 1];
             let b1i = table[i * 4 + 3];
             let a2i = table[(len_2 - i) * 4 + 1];
             let b2i = table[(len_2 - i) * 4 + 3];
-            let x1i = data[i * 2 + 1];
-            let x2i = data[(len_2 - i) * 2 + 1];
+            let x1i = read(&data, i * 2 + 1);
+            let x2i = read(&data, (len_2 - i) * 2 + 1);
 
             let a1 = Complex::new(a1r, a1i);
             let b1 = Complex::new(b1r, b1i);
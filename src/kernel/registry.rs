@@ -0,0 +1,69 @@
+//! Provides the process-wide registry of custom kernel factories consulted by
+//! `Kernel::new`.
+use std::any::{Any, TypeId};
+use std::collections::BTreeMap;
+use std::sync::{Arc, RwLock};
+
+use super::super::Num;
+use super::{Kernel, KernelCreationParams};
+
+type KernelFactory<T> = Fn(&KernelCreationParams) -> Option<Box<Kernel<T>>> + Send + Sync;
+
+/// The registered factories keyed by the scalar type. Each value is a
+/// `Vec<Arc<KernelFactory<T>>>` of the corresponding type.
+static FACTORIES: RwLock<BTreeMap<TypeId, Box<Any + Send + Sync>>> = RwLock::new(BTreeMap::new());
+
+/// Registers a factory of custom kernels for the scalar type `T`.
+///
+/// When a `Setup<T>` is created, each butterfly pass is first offered to the
+/// registered factories in the order of registration. The first kernel
+/// returned is used for the pass. If every factory returns `None`, the
+/// built-in kernels are used as usual. Factories are not consulted if
+/// `Options::max_backend` is lower than `Backend::Custom` or
+/// `Options::deterministic` is set.
+///
+/// Registering a factory doesn't extend the set of supported lengths and
+/// radixes, which is determined by the built-in kernels (see
+/// `PlanError::UnsupportedRadix`).
+///
+/// `Setup`s created before the registration, including those in the cache used
+/// by `Setup::cached`, are not affected. Use `validate_kernel` to test the
+/// kernels before registering their factory.
+pub fn register_kernel_factory<T, F>(factory: F)
+where
+    T: Num,
+    F: Fn(&KernelCreationParams) -> Option<Box<Kernel<T>>> + Send + Sync + 'static,
+{
+    let mut factories = FACTORIES.write().unwrap();
+    factories
+        .entry(TypeId::of::<T>())
+        .or_insert_with(|| Box::new(Vec::<Arc<KernelFactory<T>>>::new()))
+        .downcast_mut::<Vec<Arc<KernelFactory<T>>>>()
+        .unwrap()
+        .push(Arc::new(factory));
+}
+
+/// Removes all factories registered by `register_kernel_factory`.
+pub fn clear_kernel_factories() {
+    FACTORIES.write().unwrap().clear();
+}
+
+/// Creates a kernel using the first registered factory that supports
+/// `cparams`.
+pub fn new_custom_kernel<T: Num>(cparams: &KernelCreationParams) -> Option<Box<Kernel<T>>> {
+    // Don't hold the lock while the factories run; they may create `Setup`s
+    let factories: Vec<Arc<KernelFactory<T>>> = {
+        let factories = FACTORIES.read().unwrap();
+        match factories.get(&TypeId::of::<T>()) {
+            Some(x) => x
+                .downcast_ref::<Vec<Arc<KernelFactory<T>>>>()
+                .unwrap()
+                .clone(),
+            None => return None,
+        }
+    };
+    factories
+        .iter()
+        .filter_map(|factory| factory(cparams))
+        .next()
+}
@@ -0,0 +1,129 @@
+//! Computes the contents of the tables used by the kernels.
+//!
+//! This module only depends on `core` and `alloc` because the build script includes it
+//! to generate the static tables (see the `static-tables` feature), which
+//! must be identical to the ones computed at runtime.
+//!
+//! The platform's `sin` and `cos` are not required to be correctly rounded
+//! and actually produce different results on different C libraries. The
+//! functions defined here only use additions, multiplications and divisions,
+//! which are exactly specified by IEEE 754, so the generated tables are
+//! bit-identical on every conforming platform.
+
+use alloc::vec::Vec;
+use core::f64::consts::FRAC_PI_4;
+
+/// Computes `(cos(x), sin(x))` for `0 <= x <= π/4` using their Taylor series.
+///
+/// Nine terms are enough for the truncation error to fall far below the
+/// precision of `f64` on this interval.
+fn sin_cos_octant(x: f64) -> (f64, f64) {
+    let x2 = x * x;
+    let mut s = 1.0;
+    let mut c = 1.0;
+    for i in (1..10).rev() {
+        let n = (i * 2) as f64;
+        s = 1.0 - x2 / (n * (n + 1.0)) * s;
+        c = 1.0 - x2 / ((n - 1.0) * n) * c;
+    }
+    (c, x * s)
+}
+
+/// Computes `exp(iπ·num/den)` as `(cos, sin)`.
+///
+/// The argument reduction is done on the integer fraction, so no rounding
+/// error is introduced before the final polynomial evaluation.
+pub fn exp_i_pi_frac(num: isize, den: usize) -> (f64, f64) {
+    assert!(den > 0);
+    let den = den as u64;
+    let period = den * 2;
+    let m = if num >= 0 {
+        num as u64 % period
+    } else {
+        (period - (num.wrapping_neg() as u64 % period)) % period
+    };
+
+    // `angle = π/4 · (octant + rem / den)`
+    let octant = m * 4 / den;
+    let rem = m * 4 - octant * den;
+
+    let (c, s) = if octant % 2 == 0 {
+        sin_cos_octant(FRAC_PI_4 * rem as f64 / den as f64)
+    } else {
+        let (c, s) = sin_cos_octant(FRAC_PI_4 * (den - rem) as f64 / den as f64);
+        (s, c)
+    };
+
+    match octant / 2 {
+        0 => (c, s),
+        1 => (-s, c),
+        2 => (-c, -s),
+        3 => (s, -c),
+        _ => unreachable!(),
+    }
+}
+
+/// Computes the permutation performed by the bit reversal kernel.
+pub fn bit_reversal_indices(radixes: &[usize]) -> Vec<usize> {
+    if radixes.is_empty() {
+        return vec![0];
+    }
+
+    let len = radixes.iter().product();
+    let mut indices = vec![0; len];
+
+    let mut digits = vec![0; radixes.len()];
+    let mut factors = vec![0; radixes.len()];
+    factors[0] = 1;
+    for i in 0..radixes.len() - 1 {
+        factors[i + 1] = factors[i] * radixes[i];
+    }
+
+    let mut cur: usize = 0;
+    for i in 0..len {
+        indices[cur] = i;
+        if i < len - 1 {
+            digits[radixes.len() - 1] += 1;
+            cur += factors[radixes.len() - 1];
+            for k in (0..radixes.len()).rev() {
+                if digits[k] < radixes[k] {
+                    break;
+                }
+                digits[k - 1] += 1;
+                digits[k] = 0;
+                cur -= factors[k] * radixes[k];
+                cur += factors[k - 1];
+            }
+        }
+    }
+
+    indices
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use core::f64::consts::PI;
+
+    #[test]
+    fn matches_libm() {
+        for &den in &[1, 2, 3, 4, 5, 7, 8, 12, 64, 1000, 4096] {
+            for num in -(den as isize) * 3..(den as isize) * 3 {
+                let (c, s) = exp_i_pi_frac(num, den);
+                // `angle` itself is rounded, so the reference is less accurate
+                // for a larger `num`
+                let angle = PI * num as f64 / den as f64;
+                assert!((c - angle.cos()).abs() < 1.0e-14, "cos({}π/{})", num, den);
+                assert!((s - angle.sin()).abs() < 1.0e-14, "sin({}π/{})", num, den);
+            }
+        }
+    }
+
+    #[test]
+    fn exact_at_quadrants() {
+        assert_eq!(exp_i_pi_frac(0, 4), (1.0, 0.0));
+        assert_eq!(exp_i_pi_frac(2, 4), (-0.0, 1.0));
+        assert_eq!(exp_i_pi_frac(-1, 2), (0.0, -1.0));
+        assert_eq!(exp_i_pi_frac(3, 3), (-1.0, -0.0));
+    }
+}
@@ -0,0 +1,224 @@
+//! Provides a process-wide cache of the tables used by the kernels, so
+//! kernels with the same parameters share a single copy even if they belong
+//! to different `Setup`s. For example, the forward and backward `Setup`s of
+//! the same length share the bit reversal indices.
+//!
+//! The cache only holds weak references, so a table is freed as soon as the
+//! last kernel using it is dropped. The cache requires the `std` feature;
+//! without it, every kernel computes its own copy of the tables.
+//!
+//! When the `static-tables` feature is enabled, the build script precomputes
+//! some tables into static arrays (see `build.rs`). `table` returns them
+//! without consulting the cache.
+use alloc::sync::Arc;
+use alloc::vec::Vec;
+use core::any::Any;
+use core::ops::Deref;
+
+#[cfg(feature = "std")]
+use core::any::TypeId;
+#[cfg(feature = "std")]
+use std::collections::HashMap;
+#[cfg(feature = "std")]
+use std::sync::{Mutex, Weak};
+
+/// Identifies a table by its type, the kind of the table, and the parameters
+/// it was computed from.
+#[cfg(feature = "std")]
+type Key = (TypeId, &'static str, Vec<u64>);
+
+#[cfg(feature = "std")]
+static TABLES: Mutex<Option<HashMap<Key, Weak<Any + Send + Sync>>>> = Mutex::new(None);
+
+/// Retrieves the table identified by `kind` and `params` from the cache, or
+/// computes it by calling `f` if there's no live one.
+///
+/// `kind` must be unique to each function computing a table of the type `V`,
+/// and `params` must include every parameter the table depends on.
+#[cfg(feature = "std")]
+pub fn shared_table<V, F>(kind: &'static str, params: &[u64], f: F) -> Arc<V>
+where
+    V: Any + Send + Sync,
+    F: FnOnce() -> V,
+{
+    let key = (TypeId::of::<V>(), kind, params.to_vec());
+    if let Some(table) = lookup(&key) {
+        return table;
+    }
+
+    // Don't block other threads while computing the table
+    let table = Arc::new(f());
+
+    let mut tables = TABLES.lock().unwrap();
+    let tables = tables.get_or_insert_with(HashMap::new);
+    if let Some(existing) = tables.get(&key).and_then(Weak::upgrade) {
+        return existing.downcast().unwrap();
+    }
+
+    // Drop the entries of the freed tables
+    tables.retain(|_, table| table.upgrade().is_some());
+
+    let erased: Arc<Any + Send + Sync> = table.clone();
+    tables.insert(key, Arc::downgrade(&erased));
+    table
+}
+
+/// Computes the table by calling `f`. There's no cache without the `std`
+/// feature.
+#[cfg(not(feature = "std"))]
+pub fn shared_table<V, F>(_kind: &'static str, _params: &[u64], f: F) -> Arc<V>
+where
+    V: Any + Send + Sync,
+    F: FnOnce() -> V,
+{
+    Arc::new(f())
+}
+
+/// A table borrowed by a kernel.
+#[derive(Debug, Clone)]
+pub enum Table<T: 'static> {
+    /// A table computed at runtime and shared through the cache.
+    Shared(Arc<Vec<T>>),
+    /// A table precomputed by the build script.
+    Static(&'static [T]),
+}
+
+impl<T> Deref for Table<T> {
+    type Target = [T];
+
+    fn deref(&self) -> &[T] {
+        match *self {
+            Table::Shared(ref table) => &table[..],
+            Table::Static(table) => table,
+        }
+    }
+}
+
+/// Retrieves the table identified by `kind` and `params` from the static
+/// tables or the cache, or computes it by calling `f` if there's none.
+///
+/// See `shared_table` for the requirements on `kind` and `params`.
+pub fn table<T, F>(kind: &'static str, params: &[u64], f: F) -> Table<T>
+where
+    T: Any + Send + Sync,
+    F: FnOnce() -> Vec<T>,
+{
+    if let Some(table) = static_table(kind, params) {
+        Table::Static(table)
+    } else {
+        Table::Shared(shared_table(kind, params, f))
+    }
+}
+
+/// A table precomputed by the build script.
+#[cfg(feature = "static-tables")]
+struct StaticTable {
+    kind: &'static str,
+    params: &'static [u64],
+    /// `&'static [T]` where `T` is the element type.
+    data: &'static (Any + Send + Sync),
+}
+
+#[cfg(feature = "static-tables")]
+mod generated {
+    include!(concat!(env!("OUT_DIR"), "/static_tables.rs"));
+}
+
+#[cfg(feature = "static-tables")]
+fn static_table<T: Any>(kind: &str, params: &[u64]) -> Option<&'static [T]> {
+    generated::STATIC_TABLES
+        .iter()
+        .filter(|table| table.kind == kind && table.params == params)
+        .filter_map(|table| table.data.downcast_ref::<&'static [T]>())
+        .next()
+        .map(|&data| data)
+}
+
+#[cfg(not(feature = "static-tables"))]
+fn static_table<T: Any>(_kind: &str, _params: &[u64]) -> Option<&'static [T]> {
+    None
+}
+
+#[cfg(feature = "std")]
+fn lookup<V: Any + Send + Sync>(key: &Key) -> Option<Arc<V>> {
+    let tables = TABLES.lock().unwrap();
+    tables
+        .as_ref()
+        .and_then(|tables| tables.get(key))
+        .and_then(Weak::upgrade)
+        .map(|table| table.downcast().unwrap())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn shared() {
+        let a = shared_table("test", &[1, 2], || vec![1u8, 2]);
+        let b = shared_table("test", &[1, 2], || vec![3u8]);
+        let c = shared_table("test", &[1, 3], || vec![3u8]);
+        let d = shared_table("test", &[1, 2], || vec![1u16, 2]);
+        assert!(Arc::ptr_eq(&a, &b));
+        assert!(!Arc::ptr_eq(&a, &c));
+        assert_eq!(*d, vec![1u16, 2]);
+
+        // Freed tables are recomputed
+        drop((a, b));
+        let e = shared_table("test", &[1, 2], || vec![4u8]);
+        assert_eq!(*e, vec![4u8]);
+    }
+
+    /// Checks that the build script computes the tables in exactly the same
+    /// way as the kernels.
+    #[cfg(feature = "static-tables")]
+    #[test]
+    fn static_tables_match() {
+        use super::super::{generic2, realfft, tablegen};
+        use num_complex::Complex;
+
+        fn check<T: Any + ::core::fmt::Debug, F: FnOnce() -> Vec<T>>(table: &StaticTable, f: F) {
+            if let Some(data) = table.data.downcast_ref::<&'static [T]>() {
+                // Compare the `Debug` representations to distinguish `-0.0`
+                // from `0.0`
+                assert_eq!(
+                    format!("{:?}", data),
+                    format!("{:?}", f()),
+                    "{} {:?}",
+                    table.kind,
+                    table.params
+                );
+            }
+        }
+
+        for table in generated::STATIC_TABLES {
+            let p = table.params;
+            match table.kind {
+                "generic2_twiddles" => {
+                    let (radix, unit, inverse) = (p[0] as usize, p[1] as usize, p[2] != 0);
+                    check::<Complex<f32>, _>(table, || {
+                        generic2::twiddle_table(radix, unit, inverse)
+                    });
+                    check::<Complex<f64>, _>(table, || {
+                        generic2::twiddle_table(radix, unit, inverse)
+                    });
+                }
+                "bit_reversal_indices" => {
+                    let radixes: Vec<usize> = p.iter().map(|&x| x as usize).collect();
+                    check::<usize, _>(table, || tablegen::bit_reversal_indices(&radixes));
+                }
+                "real_fft_coefs" => {
+                    let (len, inverse, scale) = (p[0] as usize, p[1] != 0, f64::from_bits(p[2]));
+                    check::<f32, _>(table, || {
+                        realfft::new_real_fft_coef_table(len, inverse, scale as f32)
+                    });
+                    check::<f64, _>(table, || {
+                        realfft::new_real_fft_coef_table(len, inverse, scale)
+                    });
+                }
+                kind => panic!("unknown kind: {}", kind),
+            }
+        }
+    }
+}
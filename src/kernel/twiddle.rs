@@ -0,0 +1,11 @@
+//! Computes twiddle factors using only the basic arithmetic operations. See
+//! `tablegen` for why the platform's `sin` and `cos` are not used.
+
+use super::tablegen::exp_i_pi_frac;
+use {Complex, Num};
+
+/// Computes `exp(iπ·num/den)` as a complex number of type `T`.
+pub fn twiddle<T: Num>(num: isize, den: usize) -> Complex<T> {
+    let (c, s) = exp_i_pi_frac(num, den);
+    Complex::new(T::from(c).unwrap(), T::from(s).unwrap())
+}
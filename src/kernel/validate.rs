@@ -0,0 +1,231 @@
+//! Provides a way to test butterfly kernels (most importantly, custom ones
+//! supplied through `register_kernel_factory`) against a reference
+//! implementation.
+use alloc::vec::Vec;
+use core::fmt;
+#[cfg(feature = "std")]
+use std::error;
+
+use num_complex::Complex;
+use num_iter::range_step;
+use num_traits::Float;
+
+use super::super::Num;
+use super::twiddle::twiddle;
+use super::{Kernel, KernelCreationParams, KernelParams, KernelType};
+
+/// The number of scalar values placed after the sequence to detect
+/// out-of-bounds writes.
+const GUARD_LEN: usize = 16;
+
+/// The error type which is returned from `validate_kernel`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum KernelValidationError {
+    /// `cparams` doesn't describe a valid butterfly pass.
+    InvalidParams,
+
+    /// The `index`-th complex element of the output differs from the
+    /// reference by more than the tolerance. `error` is the magnitude of the
+    /// difference relative to the largest magnitude of the reference output.
+    Inaccurate { index: usize, error: f64 },
+
+    /// The kernel modified the `index`-th scalar value of `coefs`, which is
+    /// past the end of the sequence.
+    OutOfBounds { index: usize },
+}
+
+impl fmt::Display for KernelValidationError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            KernelValidationError::InvalidParams => {
+                write!(f, "The parameters don't describe a valid butterfly pass.")
+            }
+            KernelValidationError::Inaccurate { index, error } => write!(
+                f,
+                "The output element {} has the relative error {:e}, which exceeds the tolerance.",
+                index, error
+            ),
+            KernelValidationError::OutOfBounds { index } => write!(
+                f,
+                "The kernel modified the value {}, which is past the end of the sequence.",
+                index
+            ),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl error::Error for KernelValidationError {
+    fn description(&self) -> &str {
+        match *self {
+            KernelValidationError::InvalidParams => "Invalid parameters",
+            KernelValidationError::Inaccurate { .. } => "Inaccurate output",
+            KernelValidationError::OutOfBounds { .. } => "Out-of-bounds write",
+        }
+    }
+}
+
+/// Checks whether `kernel` performs the butterfly pass described by `cparams`.
+///
+/// The kernel is applied to a pseudo-random sequence, and the result is
+/// compared against the definition (see `KernelCreationParams`) evaluated in
+/// double precision. The error of each output element relative to the largest
+/// magnitude of the output must not exceed `8 * radix` times the machine
+/// epsilon of `T`. The values after the sequence in `coefs` must be left
+/// intact. The work area is filled with NaNs before the kernel is applied.
+pub fn validate_kernel<T: Num>(
+    kernel: &Kernel<T>,
+    cparams: &KernelCreationParams,
+) -> Result<(), KernelValidationError> {
+    let size = cparams.size;
+    if size == 0
+        || cparams.radix < 2
+        || cparams.unit == 0
+        || size % (cparams.radix * cparams.unit) != 0
+    {
+        return Err(KernelValidationError::InvalidParams);
+    }
+
+    let input = random_input(size);
+    let expected = reference_butterflies(cparams, &input);
+
+    let guard = T::from(0.5).unwrap();
+    let mut coefs: Vec<T> = input
+        .iter()
+        .flat_map(|x| vec![T::from(x.re).unwrap(), T::from(x.im).unwrap()])
+        .chain((0..GUARD_LEN).map(|_| guard))
+        .collect();
+    let mut work_area = vec![T::nan(); kernel.required_work_area_size()];
+    kernel.transform(&mut KernelParams {
+        coefs: &mut coefs,
+        work_area: &mut work_area,
+    });
+
+    if let Some(i) = (size * 2..coefs.len()).find(|&i| coefs[i] != guard) {
+        return Err(KernelValidationError::OutOfBounds { index: i });
+    }
+
+    let peak = expected
+        .iter()
+        .map(|x| Float::sqrt(x.norm_sqr()))
+        .fold(0.0, f64::max);
+    let tolerance = T::epsilon().to_f64().unwrap() * 8.0 * cparams.radix as f64;
+    for (i, x) in expected.iter().enumerate() {
+        let got = Complex::new(
+            coefs[i * 2].to_f64().unwrap(),
+            coefs[i * 2 + 1].to_f64().unwrap(),
+        );
+        let error = Float::sqrt((got - x).norm_sqr()) / peak;
+        if error.is_nan() || error > tolerance {
+            return Err(KernelValidationError::Inaccurate { index: i, error });
+        }
+    }
+
+    Ok(())
+}
+
+/// Generates a sequence of complex numbers whose components are uniformly
+/// distributed in `[-1, 1)` using a fixed seed.
+fn random_input(len: usize) -> Vec<Complex<f64>> {
+    let mut state = 0x2545f4914f6cdd1du64;
+    let mut next = || {
+        // xorshift64
+        state ^= state << 13;
+        state ^= state >> 7;
+        state ^= state << 17;
+        (state >> 11) as f64 / (1u64 << 52) as f64 - 1.0
+    };
+    (0..len).map(|_| Complex::new(next(), next())).collect()
+}
+
+/// Evaluates the definition of the butterfly pass described by `cparams`.
+fn reference_butterflies(
+    cparams: &KernelCreationParams,
+    input: &[Complex<f64>],
+) -> Vec<Complex<f64>> {
+    let radix = cparams.radix;
+    let unit = cparams.unit;
+    let len = radix * unit;
+    let full_circle = if cparams.inverse { 2 } else { -2 };
+
+    let mut output = vec![Complex::new(0.0, 0.0); input.len()];
+    for x in range_step(0, input.len(), len) {
+        for y in 0..unit {
+            for z in 0..radix {
+                output[x + y + z * unit] = (0..radix)
+                    .map(|w| {
+                        // The exponent of `exp(∓2πi/len)`
+                        let k = match cparams.kernel_type {
+                            KernelType::Dit => (y + z * unit) * w,
+                            KernelType::Dif => z * (y + w * unit),
+                        } % len;
+                        twiddle::<f64>(full_circle * k as isize, len) * input[x + y + w * unit]
+                    })
+                    .fold(Complex::new(0.0, 0.0), |x, y| x + y);
+            }
+        }
+    }
+    output
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::{is_radix_supported, Backend};
+    use super::*;
+    use alloc::boxed::Box;
+
+    /// The built-in kernels must conform to the definition.
+    fn validate_builtin_kernels<T: Num>() {
+        for &kernel_type in &[KernelType::Dit, KernelType::Dif] {
+            for &inverse in &[false, true] {
+                for &(size, radix, unit) in &[
+                    (2, 2, 1),
+                    (64, 2, 1),
+                    (64, 2, 8),
+                    (64, 4, 1),
+                    (64, 4, 2),
+                    (64, 4, 16),
+                    (60, 3, 5),
+                    (60, 5, 4),
+                ] {
+                    for &backend in &[
+                        Backend::Generic,
+                        Backend::Generic2,
+                        Backend::Sse,
+                        Backend::Sse3,
+                        Backend::Avx,
+                    ] {
+                        if !is_radix_supported(radix, backend) {
+                            continue;
+                        }
+                        let cparams = KernelCreationParams {
+                            size,
+                            kernel_type,
+                            radix,
+                            unit,
+                            inverse,
+                        };
+                        let kernel: Box<Kernel<T>> = Kernel::new(&cparams, 1.0, backend);
+                        assert_eq!(
+                            validate_kernel(&*kernel, &cparams),
+                            Ok(()),
+                            "{:?} {:?}",
+                            cparams,
+                            kernel
+                        );
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn builtin_kernels_f32() {
+        validate_builtin_kernels::<f32>();
+    }
+
+    #[test]
+    fn builtin_kernels_f64() {
+        validate_builtin_kernels::<f64>();
+    }
+}
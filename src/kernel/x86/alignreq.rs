@@ -0,0 +1,316 @@
+//! Provides `AlignReqKernel`, the interface implemented by the SIMD kernels,
+//! and `KernelIo`, which performs their memory accesses with or without the
+//! alignment assumption depending on the buffers given at run time.
+use super::super::StridedSequence;
+use super::{Backend, Kernel, KernelInput, KernelParams};
+use core::any::Any;
+use core::marker::PhantomData;
+use core::{fmt, mem, ptr};
+
+pub fn if_compatible<TExpect, TRequired, F>(f: F) -> Option<TRequired>
+where
+    Option<TRequired>: Any,
+    Option<TExpect>: Any,
+    F: FnOnce() -> Option<TExpect>,
+{
+    let mut ret_cell = None;
+    if let Some(ret) = (&mut ret_cell as &mut Any).downcast_mut() {
+        *ret = f();
+    }
+    ret_cell
+}
+
+#[derive(Debug)]
+pub struct AlignReqKernelWrapper<T>(T);
+
+impl<T> AlignReqKernelWrapper<T> {
+    pub fn new(x: T) -> Self {
+        AlignReqKernelWrapper(x)
+    }
+}
+
+impl<T> AlignReqKernelWrapper<T> {
+    fn transform_inner<S: Copy>(&self, input: Option<KernelInput<S>>, params: &mut KernelParams<S>)
+    where
+        T: AlignReqKernel<S>,
+    {
+        let a_req = self.0.alignment_requirement();
+        if cfg!(feature = "checked") {
+            assert!(a_req.is_power_of_two());
+            assert!(
+                params.work_area.len() >= self.0.required_work_area_size(),
+                "the work area is too small for {:?}",
+                self.0
+            );
+        }
+        let is_aligned = |x: &[S]| (x.as_ptr() as usize & (a_req - 1)) == 0;
+        let is_input_aligned = match input {
+            Some(KernelInput::Contiguous(x)) => is_aligned(x),
+            Some(KernelInput::Strided(..)) | None => true,
+        };
+        if is_aligned(params.coefs) && is_input_aligned {
+            let io = KernelIo::<S, AlignInfoAligned>::new(params.coefs, input);
+            self.0.transform(params, io);
+        } else {
+            let io = KernelIo::<S, AlignInfoUnaligned>::new(params.coefs, input);
+            self.0.transform(params, io);
+        }
+    }
+}
+
+impl<T: AlignReqKernel<S>, S: Copy> Kernel<S> for AlignReqKernelWrapper<T> {
+    fn transform(&self, params: &mut KernelParams<S>) {
+        self.transform_inner(None, params);
+    }
+    fn transform_from(&self, input: &[S], params: &mut KernelParams<S>) {
+        self.transform_inner(Some(KernelInput::Contiguous(input)), params);
+    }
+    fn transform_from_strided(
+        &self,
+        data: &[S],
+        input: &StridedSequence,
+        params: &mut KernelParams<S>,
+    ) {
+        if self.0.gathers_input() {
+            self.transform_inner(Some(KernelInput::Strided(data, input)), params);
+        } else {
+            input.gather(data, params.coefs);
+            self.transform(params);
+        }
+    }
+    fn required_work_area_size(&self) -> usize {
+        self.0.required_work_area_size()
+    }
+    fn backend(&self) -> Backend {
+        self.0.backend()
+    }
+    fn table_size(&self) -> usize {
+        self.0.table_size()
+    }
+}
+
+pub trait AlignReqKernel<T>: fmt::Debug + Sized + Sync + Send {
+    /// Applies the stage to `params.coefs`. The kernel must access the
+    /// sequence through `io`, which takes care of alignment and of
+    /// out-of-place operation.
+    fn transform<I: AlignInfo>(&self, params: &mut KernelParams<T>, io: KernelIo<T, I>);
+    fn required_work_area_size(&self) -> usize {
+        0
+    }
+    /// Indicates whether the kernel reads the input sequence only through
+    /// `gather_source`, which supports strided input sequences. Otherwise,
+    /// a strided input sequence is copied to `params.coefs` beforehand.
+    fn gathers_input(&self) -> bool {
+        false
+    }
+    fn alignment_requirement(&self) -> usize;
+    fn backend(&self) -> Backend;
+    fn table_size(&self) -> usize {
+        0
+    }
+}
+
+/// Performs the memory accesses of an `AlignReqKernel` to the sequence.
+///
+/// The kernel computes the locations in `params.coefs`. `read` loads the value
+/// from the location, or from the corresponding location in the input sequence
+/// if the kernel is the first stage of an out-of-place transformation (see
+/// `Kernel::transform_from`). `write` always stores to `params.coefs`. For
+/// this to work, each location must be read before it's written.
+///
+/// Every access is bounds-checked as a whole (i.e., all lanes of a SIMD
+/// vector) if the `checked` feature is enabled or in debug builds.
+#[derive(Debug, Clone, Copy)]
+pub struct KernelIo<'a, T: 'a, I> {
+    /// The address range of `params.coefs`
+    coefs: (usize, usize),
+    input: Option<KernelInput<'a, T>>,
+    align: PhantomData<I>,
+}
+
+impl<'a, T: Copy + 'a, I: AlignInfo> KernelIo<'a, T, I> {
+    fn new(coefs: &[T], input: Option<KernelInput<'a, T>>) -> Self {
+        let start = coefs.as_ptr() as usize;
+        KernelIo {
+            coefs: (start, start + mem::size_of_val(coefs)),
+            input,
+            align: PhantomData,
+        }
+    }
+
+    /// Asserts that the `U` at `p` is inside the range `start..end` if bounds
+    /// checking is enabled.
+    #[inline]
+    fn check_bounds<U>(p: *const U, (start, end): (usize, usize)) {
+        if cfg!(any(debug_assertions, feature = "checked")) {
+            let addr = p as usize;
+            assert!(
+                addr >= start && addr + mem::size_of::<U>() <= end,
+                "a {}-byte access at the offset {} is out of bounds (len: {} bytes)",
+                mem::size_of::<U>(),
+                addr.wrapping_sub(start) as isize,
+                end - start
+            );
+        }
+    }
+
+    /// Retrieves the input sequence of an out-of-place transformation.
+    #[cfg(any(
+        feature = "backend-sse",
+        all(target_feature = "avx", feature = "backend-avx")
+    ))]
+    pub fn input(&self) -> Option<KernelInput<'a, T>> {
+        self.input
+    }
+
+    /// Maps the location `p` in `params.coefs` to the location to read from.
+    #[inline]
+    fn source<U>(&self, p: *const U) -> *const U {
+        Self::check_bounds(p, self.coefs);
+        match self.input {
+            Some(KernelInput::Contiguous(input)) => {
+                let start = input.as_ptr() as usize;
+                let q = start + (p as usize - self.coefs.0);
+                Self::check_bounds(q as *const U, (start, start + mem::size_of_val(input)));
+                unsafe { (input.as_ptr() as *const u8).add(q - start) as *const U }
+            }
+            Some(KernelInput::Strided(..)) => {
+                unreachable!("strided input must be read through `gather_source`")
+            }
+            None => p,
+        }
+    }
+
+    #[inline]
+    pub unsafe fn read<U>(&self, p: *const U) -> U {
+        I::read(self.source(p))
+    }
+
+    #[inline]
+    pub unsafe fn write<U>(&self, p: *mut U, value: U) {
+        Self::check_bounds(p, self.coefs);
+        I::write(p, value)
+    }
+
+    /// `read` without the alignment assumption.
+    #[inline]
+    pub unsafe fn read_unaligned<U>(&self, p: *const U) -> U {
+        AlignInfoUnaligned::read(self.source(p))
+    }
+
+    /// `write` without the alignment assumption.
+    #[inline]
+    pub unsafe fn write_unaligned<U>(&self, p: *mut U, value: U) {
+        Self::check_bounds(p, self.coefs);
+        AlignInfoUnaligned::write(p, value)
+    }
+}
+
+pub trait AlignInfo: Sized {
+    unsafe fn read<T>(p: *const T) -> T;
+    unsafe fn write<T>(p: *mut T, value: T);
+}
+
+#[derive(Debug, Clone, Copy)]
+struct AlignInfoAligned;
+
+impl AlignInfo for AlignInfoAligned {
+    unsafe fn read<T>(p: *const T) -> T {
+        check_aligned(p);
+        ptr::read(p)
+    }
+    unsafe fn write<T>(p: *mut T, value: T) {
+        check_aligned(p);
+        ptr::write(p, value)
+    }
+}
+
+/// Verifies that `p` is properly aligned if the `checked` feature is enabled.
+#[inline]
+fn check_aligned<T>(p: *const T) {
+    if cfg!(feature = "checked") {
+        assert_eq!(
+            (p as usize) & (mem::align_of::<T>() - 1),
+            0,
+            "misaligned access through `AlignInfoAligned`"
+        );
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+struct AlignInfoUnaligned;
+
+impl AlignInfo for AlignInfoUnaligned {
+    unsafe fn read<T>(p: *const T) -> T {
+        ptr::read_unaligned(p)
+    }
+    unsafe fn write<T>(p: *mut T, value: T) {
+        ptr::write_unaligned(p, value)
+    }
+}
+
+#[cfg(all(test, feature = "checked"))]
+mod tests {
+    use super::super::SliceAccessor;
+    use super::*;
+
+    #[test]
+    fn kernel_io_in_bounds() {
+        let mut data = [1.0f32; 8];
+        let input = [2.0f32; 8];
+        let p = &mut data[4] as *mut f32 as *mut [f32; 4];
+        let io =
+            KernelIo::<f32, AlignInfoUnaligned>::new(&data, Some(KernelInput::Contiguous(&input)));
+        unsafe {
+            assert_eq!(io.read(p), [2.0; 4]);
+            io.write(p, [3.0; 4]);
+        }
+        assert_eq!(data[7], 3.0);
+    }
+
+    #[test]
+    #[should_panic(expected = "out of bounds")]
+    fn kernel_io_read_lanes_out_of_bounds() {
+        let data = [0.0f32; 6];
+        let io = KernelIo::<f32, AlignInfoUnaligned>::new(&data, None);
+        // The first lane is in bounds, but the last two are not
+        unsafe { io.read(&data[4] as *const f32 as *const [f32; 4]) };
+    }
+
+    #[test]
+    #[should_panic(expected = "out of bounds")]
+    fn kernel_io_write_lanes_out_of_bounds() {
+        let mut data = [0.0f32; 6];
+        let p = &mut data[4] as *mut f32 as *mut [f32; 4];
+        let io = KernelIo::<f32, AlignInfoUnaligned>::new(&data, None);
+        unsafe { io.write(p, [0.0; 4]) };
+    }
+
+    #[test]
+    #[should_panic(expected = "out of bounds")]
+    fn kernel_io_input_out_of_bounds() {
+        let data = [0.0f32; 8];
+        let input = [0.0f32; 4];
+        let io =
+            KernelIo::<f32, AlignInfoUnaligned>::new(&data, Some(KernelInput::Contiguous(&input)));
+        unsafe { io.read(&data[2] as *const f32 as *const [f32; 4]) };
+    }
+
+    #[test]
+    #[should_panic(expected = "a 16-byte read at the index 4 is out of bounds (len: 6)")]
+    fn ptr_at_out_of_bounds() {
+        let data = [0.0f32; 6];
+        let data = unsafe { SliceAccessor::new(&data[..]) };
+        // `data[4]` is in bounds, so only the check of `ptr_at` catches the
+        // last two lanes
+        data.ptr_at::<[f32; 4]>(4);
+    }
+
+    #[test]
+    fn ptr_at_in_bounds() {
+        let data = [0.0f32, 1.0, 2.0, 3.0, 4.0, 5.0];
+        let data = unsafe { SliceAccessor::new(&data[..]) };
+        let p = data.ptr_at::<[f32; 4]>(2);
+        assert_eq!(unsafe { ptr::read_unaligned(p) }, [2.0, 3.0, 4.0, 5.0]);
+    }
+}
@@ -1,12 +1,17 @@
+use super::super::bitreversal::gather_source;
 use super::super::Num;
-use super::utils::{if_compatible, AlignInfo, AlignReqKernel, AlignReqKernelWrapper};
-use super::{Kernel, KernelParams, SliceAccessor};
+use super::alignreq::{if_compatible, AlignInfo, AlignReqKernel, AlignReqKernelWrapper, KernelIo};
+use super::{Backend, Kernel, KernelParams, SliceAccessor, Table};
+use alloc::boxed::Box;
 
-use packed_simd::u64x2;
+use simdutils::u64x2;
 
-use std::mem;
+use core::{mem, ptr};
 
-pub unsafe fn new_x86_bit_reversal_kernel<T>(indices: &Vec<usize>) -> Option<Box<Kernel<T>>>
+pub unsafe fn new_x86_bit_reversal_kernel<T>(
+    indices: &Table<usize>,
+    scale: f64,
+) -> Option<Box<Kernel<T>>>
 where
     T: Num,
 {
@@ -14,6 +19,7 @@ where
         Some(
             Box::new(AlignReqKernelWrapper::new(SseDWordBitReversalKernel {
                 indices: indices.clone(),
+                scale,
             })) as Box<Kernel<f32>>,
         )
     })
@@ -21,18 +27,19 @@ where
 
 #[derive(Debug)]
 struct SseDWordBitReversalKernel {
-    indices: Vec<usize>,
+    indices: Table<usize>,
+    scale: f64,
 }
 
 impl<T: Num> AlignReqKernel<T> for SseDWordBitReversalKernel {
-    fn transform<I: AlignInfo>(&self, params: &mut KernelParams<T>) {
+    fn transform<I: AlignInfo>(&self, params: &mut KernelParams<T>, io: KernelIo<T, I>) {
         assert_eq!(mem::size_of::<T>(), 4);
 
         let indices = unsafe { SliceAccessor::new(&self.indices) };
         let size = self.indices.len();
         let mut data = unsafe { SliceAccessor::new(&mut params.coefs[0..size * 2]) };
         let mut wa = unsafe { SliceAccessor::new(&mut params.work_area[0..size * 2]) };
-        wa.copy_from_slice(*data);
+        let source = gather_source(&mut wa, &data, io.input(), self.scale);
         let mut i = 0;
         while i + 3 < size {
             let index1 = indices[i];
@@ -40,15 +47,17 @@ impl<T: Num> AlignReqKernel<T> for SseDWordBitReversalKernel {
             let index3 = indices[i + 2];
             let index4 = indices[i + 3];
 
-            let src1: *const u64 = &wa[index1 * 2] as *const T as *const u64;
-            let src2: *const u64 = &wa[index2 * 2] as *const T as *const u64;
-            let src3: *const u64 = &wa[index3 * 2] as *const T as *const u64;
-            let src4: *const u64 = &wa[index4 * 2] as *const T as *const u64;
+            let src1: *const u64 = source.ptr_at::<u64>(index1 * 2);
+            let src2: *const u64 = source.ptr_at::<u64>(index2 * 2);
+            let src3: *const u64 = source.ptr_at::<u64>(index3 * 2);
+            let src4: *const u64 = source.ptr_at::<u64>(index4 * 2);
             let dest: *mut u64x2 = &mut data[i * 2] as *mut T as *mut u64x2;
 
             unsafe {
-                I::write(dest, u64x2::new(*src1, *src2));
-                I::write(dest.offset(1), u64x2::new(*src3, *src4));
+                let (x1, x2) = (ptr::read_unaligned(src1), ptr::read_unaligned(src2));
+                let (x3, x4) = (ptr::read_unaligned(src3), ptr::read_unaligned(src4));
+                io.write(dest, u64x2::new(x1, x2));
+                io.write(dest.offset(1), u64x2::new(x3, x4));
             }
 
             i += 4;
@@ -56,10 +65,10 @@ impl<T: Num> AlignReqKernel<T> for SseDWordBitReversalKernel {
         while i < size {
             let index = indices[i];
 
-            let src: *const u64 = &wa[index * 2] as *const T as *const u64;
+            let src: *const u64 = source.ptr_at::<u64>(index * 2);
             let dest: *mut u64 = &mut data[i * 2] as *mut T as *mut u64;
             unsafe {
-                *dest = *src;
+                io.write_unaligned(dest, ptr::read_unaligned(src));
             }
 
             i += 1;
@@ -68,7 +77,16 @@ impl<T: Num> AlignReqKernel<T> for SseDWordBitReversalKernel {
     fn required_work_area_size(&self) -> usize {
         self.indices.len() * 2
     }
+    fn gathers_input(&self) -> bool {
+        true
+    }
     fn alignment_requirement(&self) -> usize {
         16
     }
+    fn backend(&self) -> Backend {
+        Backend::Sse
+    }
+    fn table_size(&self) -> usize {
+        mem::size_of_val(&self.indices[..])
+    }
 }
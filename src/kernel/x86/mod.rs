@@ -1,39 +1,68 @@
 //! Defines FFT kernels optimized for x86 and x86_64 systems.
 
 use super::super::Num;
-use super::utils;
-use super::{Kernel, KernelCreationParams, KernelParams, KernelType, SliceAccessor};
+use super::tables::Table;
+use super::utils::if_allowed;
+use super::{Backend, Kernel, KernelCreationParams};
+// Used by the kernel modules, some of which may be excluded
+#[allow(unused_imports)]
+use super::{KernelInput, KernelParams, KernelType, SliceAccessor};
+use alloc::boxed::Box;
 
+// The kernels of each family are only compiled if the family is enabled by
+// the `backend-*` feature and the instruction set is enabled at compile time.
+// Otherwise, the module is replaced with a stub that never creates a kernel.
+#[cfg(any(
+    feature = "backend-sse",
+    all(target_feature = "sse3", feature = "backend-sse3"),
+    all(target_feature = "avx", feature = "backend-avx")
+))]
+mod alignreq;
+#[cfg(feature = "backend-sse")]
 mod bitreversal;
-#[cfg(target_feature = "avx")]
+#[cfg(any(
+    feature = "backend-sse",
+    all(target_feature = "sse3", feature = "backend-sse3"),
+    all(target_feature = "avx", feature = "backend-avx")
+))]
+mod realfftcoefs;
+#[cfg(all(target_feature = "avx", feature = "backend-avx"))]
 mod x86avxbitreversal;
-#[cfg(target_feature = "avx")]
+#[cfg(all(target_feature = "avx", feature = "backend-avx"))]
 mod x86avxf32radix2;
-#[cfg(target_feature = "avx")]
+#[cfg(all(target_feature = "avx", feature = "backend-avx"))]
 mod x86avxf32radix4;
-#[cfg(target_feature = "avx")]
+#[cfg(all(target_feature = "avx", feature = "backend-avx"))]
 mod x86avxf32realfft;
 mod x86sse1bitreversal;
+#[cfg(feature = "backend-sse")]
 mod x86sse1radix2;
+#[cfg(feature = "backend-sse")]
 mod x86sse1radix4;
+#[cfg(feature = "backend-sse")]
 mod x86sse1realfft;
 mod x86sse2;
-#[cfg(target_feature = "sse3")]
+#[cfg(all(target_feature = "sse3", feature = "backend-sse3"))]
 mod x86sse3f32radix4;
-#[cfg(target_feature = "sse3")]
+#[cfg(all(target_feature = "sse3", feature = "backend-sse3"))]
 mod x86sse3f32realfft;
 
-#[cfg(not(target_feature = "avx"))]
+#[cfg(not(all(target_feature = "avx", feature = "backend-avx")))]
 mod x86avxf32radix2 {
+    use alloc::boxed::Box;
+
     pub fn new_x86_avx_f32_radix2_kernel<T>(
         _: &super::KernelCreationParams,
+        _: f64,
     ) -> Option<Box<super::Kernel<T>>> {
         None
     }
 }
 
-#[cfg(not(target_feature = "avx"))]
+#[cfg(not(all(target_feature = "avx", feature = "backend-avx")))]
 mod x86avxf32radix4 {
+    use alloc::boxed::Box;
+
     pub fn new_x86_avx_f32_radix4_kernel<T>(
         _: &super::KernelCreationParams,
     ) -> Option<Box<super::Kernel<T>>> {
@@ -41,18 +70,23 @@ mod x86avxf32radix4 {
     }
 }
 
-#[cfg(not(target_feature = "avx"))]
+#[cfg(not(all(target_feature = "avx", feature = "backend-avx")))]
 mod x86avxf32realfft {
+    use alloc::boxed::Box;
+
     pub fn new_x86_avx_f32_real_fft_pre_post_process_kernel<T>(
         _len: usize,
         _inverse: bool,
+        _scale: f64,
     ) -> Option<Box<super::Kernel<T>>> {
         None
     }
 }
 
-#[cfg(not(target_feature = "sse3"))]
+#[cfg(not(all(target_feature = "sse3", feature = "backend-sse3")))]
 mod x86sse3f32radix4 {
+    use alloc::boxed::Box;
+
     pub fn new_x86_sse3_f32_radix4_kernel<T>(
         _: &super::KernelCreationParams,
     ) -> Option<Box<super::Kernel<T>>> {
@@ -60,46 +94,137 @@ mod x86sse3f32radix4 {
     }
 }
 
-#[cfg(not(target_feature = "sse3"))]
+#[cfg(not(all(target_feature = "sse3", feature = "backend-sse3")))]
 mod x86sse3f32realfft {
+    use alloc::boxed::Box;
+
     pub fn new_x86_sse3_f32_real_fft_pre_post_process_kernel<T>(
         _len: usize,
         _inverse: bool,
+        _scale: f64,
     ) -> Option<Box<super::Kernel<T>>> {
         None
     }
 }
 
-#[cfg(not(target_feature = "avx"))]
+#[cfg(not(all(target_feature = "avx", feature = "backend-avx")))]
 mod x86avxbitreversal {
-    pub fn new_x86_avx_bit_reversal_kernel<T>(_: &Vec<usize>) -> Option<Box<super::Kernel<T>>> {
+    use alloc::boxed::Box;
+
+    pub fn new_x86_avx_bit_reversal_kernel<T>(
+        _: &super::Table<usize>,
+        _: f64,
+    ) -> Option<Box<super::Kernel<T>>> {
         None
     }
     pub fn new_x86_avx_radix2_bit_reversal_kernel<T>(
-        _: &Vec<usize>,
+        _: &[usize],
+        _: f64,
     ) -> Option<Box<super::Kernel<T>>> {
         None
     }
     pub fn new_x86_avx_radix4_bit_reversal_kernel<T>(
-        _: &Vec<usize>,
+        _: &[usize],
+        _: f64,
+    ) -> Option<Box<super::Kernel<T>>> {
+        None
+    }
+}
+
+#[cfg(not(feature = "backend-sse"))]
+mod bitreversal {
+    use alloc::boxed::Box;
+
+    pub unsafe fn new_x86_bit_reversal_kernel<T>(
+        _: &super::Table<usize>,
+        _: f64,
+    ) -> Option<Box<super::Kernel<T>>> {
+        None
+    }
+}
+
+#[cfg(not(feature = "backend-sse"))]
+mod x86sse1radix2 {
+    use alloc::boxed::Box;
+
+    pub fn new_x86_sse_radix2_kernel<T>(
+        _: &super::KernelCreationParams,
+        _: f64,
+    ) -> Option<Box<super::Kernel<T>>> {
+        None
+    }
+}
+
+#[cfg(not(feature = "backend-sse"))]
+mod x86sse1radix4 {
+    use alloc::boxed::Box;
+
+    pub fn new_x86_sse_radix4_kernel<T>(
+        _: &super::KernelCreationParams,
+        _: f64,
     ) -> Option<Box<super::Kernel<T>>> {
         None
     }
 }
 
-pub fn new_x86_kernel<T>(cparams: &KernelCreationParams) -> Option<Box<Kernel<T>>>
+#[cfg(not(feature = "backend-sse"))]
+mod x86sse1realfft {
+    use alloc::boxed::Box;
+
+    pub fn new_x86_sse_real_fft_pre_post_process_kernel<T>(
+        _len: usize,
+        _inverse: bool,
+        _scale: f64,
+    ) -> Option<Box<super::Kernel<T>>> {
+        None
+    }
+}
+
+/// Creates a butterfly kernel of a family if it supports the parameters.
+type KernelFactory<T> = fn(&KernelCreationParams) -> Option<Box<Kernel<T>>>;
+
+/// `KernelFactory` for the families that can multiply the output by a scale.
+type ScaledKernelFactory<T> = fn(&KernelCreationParams, f64) -> Option<Box<Kernel<T>>>;
+
+pub fn new_x86_kernel<T>(
+    cparams: &KernelCreationParams,
+    scale: f64,
+    max_backend: Backend,
+) -> Option<Box<Kernel<T>>>
 where
     T: Num,
 {
-    None.or_else(|| x86avxf32radix2::new_x86_avx_f32_radix2_kernel(cparams))
-        .or_else(|| x86avxf32radix4::new_x86_avx_f32_radix4_kernel(cparams))
-        .or_else(|| x86sse3f32radix4::new_x86_sse3_f32_radix4_kernel(cparams))
-        .or_else(|| x86sse2::new_x86_sse2_kernel(cparams))
-        .or_else(|| x86sse1radix2::new_x86_sse_radix2_kernel(cparams))
-        .or_else(|| x86sse1radix4::new_x86_sse_radix4_kernel(cparams))
+    let avx =
+        |f: ScaledKernelFactory<T>| if_allowed(max_backend, Backend::Avx, || f(cparams, scale));
+    let sse =
+        |f: ScaledKernelFactory<T>| if_allowed(max_backend, Backend::Sse, || f(cparams, scale));
+    // These families have no kernels for `unit == 1`, which is the only case
+    // where `scale` may be other than one
+    let unscaled = |backend, f: KernelFactory<T>| {
+        if scale == 1.0 {
+            if_allowed(max_backend, backend, || f(cparams))
+        } else {
+            None
+        }
+    };
+    None.or_else(|| avx(x86avxf32radix2::new_x86_avx_f32_radix2_kernel))
+        .or_else(|| unscaled(Backend::Avx, x86avxf32radix4::new_x86_avx_f32_radix4_kernel))
+        .or_else(|| {
+            unscaled(
+                Backend::Sse3,
+                x86sse3f32radix4::new_x86_sse3_f32_radix4_kernel,
+            )
+        })
+        .or_else(|| unscaled(Backend::Sse, x86sse2::new_x86_sse2_kernel))
+        .or_else(|| sse(x86sse1radix2::new_x86_sse_radix2_kernel))
+        .or_else(|| sse(x86sse1radix4::new_x86_sse_radix4_kernel))
 }
 
-pub unsafe fn new_x86_bit_reversal_kernel<T>(indices: &Vec<usize>) -> Option<Box<Kernel<T>>>
+pub unsafe fn new_x86_bit_reversal_kernel<T>(
+    indices: &Table<usize>,
+    scale: f64,
+    max_backend: Backend,
+) -> Option<Box<Kernel<T>>>
 where
     T: Num,
 {
@@ -112,33 +237,55 @@ where
                 && indices[i + indices.len() / 4 * 3] == indices[i] + 3
         });
     None.or_else(|| {
-        if radix4 {
-            x86avxbitreversal::new_x86_avx_radix4_bit_reversal_kernel(indices)
+        if radix4 && Backend::Avx <= max_backend {
+            x86avxbitreversal::new_x86_avx_radix4_bit_reversal_kernel(indices, scale)
         } else {
             None
         }
     })
     .or_else(|| {
-        if radix2 {
-            x86avxbitreversal::new_x86_avx_radix2_bit_reversal_kernel(indices)
+        if radix2 && Backend::Avx <= max_backend {
+            x86avxbitreversal::new_x86_avx_radix2_bit_reversal_kernel(indices, scale)
         } else {
             None
         }
     })
-    .or_else(|| x86avxbitreversal::new_x86_avx_bit_reversal_kernel(indices))
-    .or_else(|| bitreversal::new_x86_bit_reversal_kernel(indices))
+    .or_else(|| {
+        if_allowed(max_backend, Backend::Avx, || {
+            x86avxbitreversal::new_x86_avx_bit_reversal_kernel(indices, scale)
+        })
+    })
+    .or_else(|| {
+        if_allowed(max_backend, Backend::Sse, || {
+            bitreversal::new_x86_bit_reversal_kernel(indices, scale)
+        })
+    })
 }
 
 pub fn new_x86_real_fft_pre_post_process_kernel<T>(
     len: usize,
     inverse: bool,
+    scale: f64,
+    max_backend: Backend,
 ) -> Option<Box<Kernel<T>>>
 where
     T: Num,
 {
     None.or_else(|| {
-        x86avxf32realfft::new_x86_avx_f32_real_fft_pre_post_process_kernel(len, inverse)
+        if_allowed(max_backend, Backend::Avx, || {
+            x86avxf32realfft::new_x86_avx_f32_real_fft_pre_post_process_kernel(len, inverse, scale)
+        })
+    })
+    .or_else(|| {
+        if_allowed(max_backend, Backend::Sse3, || {
+            x86sse3f32realfft::new_x86_sse3_f32_real_fft_pre_post_process_kernel(
+                len, inverse, scale,
+            )
+        })
+    })
+    .or_else(|| {
+        if_allowed(max_backend, Backend::Sse, || {
+            x86sse1realfft::new_x86_sse_real_fft_pre_post_process_kernel(len, inverse, scale)
+        })
     })
-    .or_else(|| x86sse3f32realfft::new_x86_sse3_f32_real_fft_pre_post_process_kernel(len, inverse))
-    .or_else(|| x86sse1realfft::new_x86_sse_real_fft_pre_post_process_kernel(len, inverse))
 }
@@ -0,0 +1,46 @@
+//! Defines the coefficient tables shared by the x86 real FFT kernels.
+
+use alloc::sync::Arc;
+
+use aligned::AlignedVec;
+use kernel::tables::shared_table;
+use kernel::twiddle::twiddle;
+use {mul_pos_i, Complex};
+
+/// Retrieves the coefficient tables from the table cache, creating them if
+/// needed. The coefficients are multiplied by `scale` so the kernel can apply
+/// normalization without an additional pass.
+pub(super) fn new_real_fft_coef_table(
+    len: usize,
+    inverse: bool,
+    scale: f32,
+) -> Arc<[AlignedVec<f32>; 2]> {
+    let params = [len as u64, inverse as u64, scale.to_bits() as u64];
+    shared_table("x86_real_fft_coefs", &params, || {
+        compute_real_fft_coef_table(len, inverse, scale)
+    })
+}
+
+fn compute_real_fft_coef_table(len: usize, inverse: bool, scale: f32) -> [AlignedVec<f32>; 2] {
+    assert!(len % 2 == 0);
+    let mut table_a = AlignedVec::with_capacity(len);
+    let mut table_b = AlignedVec::with_capacity(len);
+    for i in 0..(len / 2) {
+        let c: Complex<f32> = twiddle(-(i as isize), len / 2);
+
+        let a = (Complex::new(1f32, 0f32) - mul_pos_i(c)) * (0.5f32 * scale);
+        let b = (Complex::new(1f32, 0f32) + mul_pos_i(c)) * (0.5f32 * scale);
+        if inverse {
+            table_a.push(a.re);
+            table_a.push(-a.im);
+            table_b.push(b.re);
+            table_b.push(-b.im);
+        } else {
+            table_a.push(a.re);
+            table_a.push(a.im);
+            table_b.push(b.re);
+            table_b.push(b.im);
+        }
+    }
+    [table_a, table_b]
+}
@@ -1,12 +1,18 @@
+use super::super::bitreversal::gather_source;
 use super::super::Num;
-use super::utils::{if_compatible, AlignInfo, AlignReqKernel, AlignReqKernelWrapper};
-use super::{Kernel, KernelParams, SliceAccessor};
+use super::alignreq::{if_compatible, AlignInfo, AlignReqKernel, AlignReqKernelWrapper, KernelIo};
+use super::{Backend, Kernel, KernelParams, SliceAccessor, Table};
+use alloc::boxed::Box;
+use alloc::vec::Vec;
 
-use packed_simd::{u32x4, u64x2, u64x4};
+use simdutils::{u32x4, u64x2, u64x4};
 
-use std::{mem, ptr};
+use core::{mem, ptr};
 
-pub unsafe fn new_x86_avx_bit_reversal_kernel<T>(indices: &Vec<usize>) -> Option<Box<Kernel<T>>>
+pub unsafe fn new_x86_avx_bit_reversal_kernel<T>(
+    indices: &Table<usize>,
+    scale: f64,
+) -> Option<Box<Kernel<T>>>
 where
     T: Num,
 {
@@ -19,6 +25,7 @@ where
         Some(
             Box::new(AlignReqKernelWrapper::new(AvxDWordBitReversalKernel {
                 indices: indices.clone(),
+                scale,
             })) as Box<Kernel<f32>>,
         )
     })
@@ -26,18 +33,19 @@ where
 
 #[derive(Debug)]
 struct AvxDWordBitReversalKernel {
-    indices: Vec<usize>,
+    indices: Table<usize>,
+    scale: f64,
 }
 
 impl<T: Num> AlignReqKernel<T> for AvxDWordBitReversalKernel {
-    fn transform<I: AlignInfo>(&self, params: &mut KernelParams<T>) {
+    fn transform<I: AlignInfo>(&self, params: &mut KernelParams<T>, io: KernelIo<T, I>) {
         assert_eq!(mem::size_of::<T>(), 4);
 
         let indices = unsafe { SliceAccessor::new(&self.indices) };
         let size = self.indices.len();
         let mut data = unsafe { SliceAccessor::new(&mut params.coefs[0..size * 2]) };
         let mut wa = unsafe { SliceAccessor::new(&mut params.work_area[0..size * 2]) };
-        wa.copy_from_slice(*data);
+        let source = gather_source(&mut wa, &data, io.input(), self.scale);
         let mut i = 0;
         while i + 7 < size {
             let index1 = indices[i];
@@ -49,18 +57,22 @@ impl<T: Num> AlignReqKernel<T> for AvxDWordBitReversalKernel {
             let index7 = indices[i + 6];
             let index8 = indices[i + 7];
 
-            let src1: *const u64 = &wa[index1 * 2] as *const T as *const u64;
-            let src2: *const u64 = &wa[index2 * 2] as *const T as *const u64;
-            let src3: *const u64 = &wa[index3 * 2] as *const T as *const u64;
-            let src4: *const u64 = &wa[index4 * 2] as *const T as *const u64;
-            let src5: *const u64 = &wa[index5 * 2] as *const T as *const u64;
-            let src6: *const u64 = &wa[index6 * 2] as *const T as *const u64;
-            let src7: *const u64 = &wa[index7 * 2] as *const T as *const u64;
-            let src8: *const u64 = &wa[index8 * 2] as *const T as *const u64;
+            let src1: *const u64 = source.ptr_at::<u64>(index1 * 2);
+            let src2: *const u64 = source.ptr_at::<u64>(index2 * 2);
+            let src3: *const u64 = source.ptr_at::<u64>(index3 * 2);
+            let src4: *const u64 = source.ptr_at::<u64>(index4 * 2);
+            let src5: *const u64 = source.ptr_at::<u64>(index5 * 2);
+            let src6: *const u64 = source.ptr_at::<u64>(index6 * 2);
+            let src7: *const u64 = source.ptr_at::<u64>(index7 * 2);
+            let src8: *const u64 = source.ptr_at::<u64>(index8 * 2);
             let dest: *mut u64x4 = &mut data[i * 2] as *mut T as *mut u64x4;
             unsafe {
-                I::write(dest, u64x4::new(*src1, *src2, *src3, *src4));
-                I::write(dest.offset(1), u64x4::new(*src5, *src6, *src7, *src8));
+                let (x1, x2) = (ptr::read_unaligned(src1), ptr::read_unaligned(src2));
+                let (x3, x4) = (ptr::read_unaligned(src3), ptr::read_unaligned(src4));
+                let (x5, x6) = (ptr::read_unaligned(src5), ptr::read_unaligned(src6));
+                let (x7, x8) = (ptr::read_unaligned(src7), ptr::read_unaligned(src8));
+                io.write(dest, u64x4::new(x1, x2, x3, x4));
+                io.write(dest.offset(1), u64x4::new(x5, x6, x7, x8));
             }
 
             i += 8;
@@ -68,10 +80,10 @@ impl<T: Num> AlignReqKernel<T> for AvxDWordBitReversalKernel {
         while i < size {
             let index = indices[i];
 
-            let src: *const u64 = &wa[index * 2] as *const T as *const u64;
+            let src: *const u64 = source.ptr_at::<u64>(index * 2);
             let dest: *mut u64 = &mut data[i * 2] as *mut T as *mut u64;
             unsafe {
-                *dest = *src;
+                io.write_unaligned(dest, ptr::read_unaligned(src));
             }
 
             i += 1;
@@ -80,13 +92,23 @@ impl<T: Num> AlignReqKernel<T> for AvxDWordBitReversalKernel {
     fn required_work_area_size(&self) -> usize {
         self.indices.len() * 2
     }
+    fn gathers_input(&self) -> bool {
+        true
+    }
     fn alignment_requirement(&self) -> usize {
         32
     }
+    fn backend(&self) -> Backend {
+        Backend::Avx
+    }
+    fn table_size(&self) -> usize {
+        mem::size_of_val(&self.indices[..])
+    }
 }
 
 pub unsafe fn new_x86_avx_radix2_bit_reversal_kernel<T>(
-    indices: &Vec<usize>,
+    indices: &[usize],
+    scale: f64,
 ) -> Option<Box<Kernel<T>>>
 where
     T: Num,
@@ -127,6 +149,7 @@ where
                     .map(|i| (indices[i * 4] as u32) * 2)
                     .collect(),
                 offs: u32x4::new(0, f1 as u32 * 2, f2 as u32 * 2, f3 as u32 * 2),
+                scale,
             },
         )) as Box<Kernel<f32>>)
     })
@@ -136,17 +159,18 @@ where
 struct AvxDWordRadix2BitReversalKernel {
     indices: Vec<u32>,
     offs: u32x4,
+    scale: f64,
 }
 
 impl<T: Num> AlignReqKernel<T> for AvxDWordRadix2BitReversalKernel {
-    fn transform<I: AlignInfo>(&self, params: &mut KernelParams<T>) {
+    fn transform<I: AlignInfo>(&self, params: &mut KernelParams<T>, io: KernelIo<T, I>) {
         assert_eq!(mem::size_of::<T>(), 4);
 
         let indices = unsafe { SliceAccessor::new(&self.indices) };
         let size = self.indices.len();
         let mut data = unsafe { SliceAccessor::new(&mut params.coefs[0..size * 16]) };
         let mut wa = unsafe { SliceAccessor::new(&mut params.work_area[0..size * 16]) };
-        wa.copy_from_slice(*data);
+        let source = gather_source(&mut wa, &data, io.input(), self.scale);
 
         let offs = self.offs;
 
@@ -158,10 +182,10 @@ impl<T: Num> AlignReqKernel<T> for AvxDWordRadix2BitReversalKernel {
             let index3 = index1234.extract(2) as usize;
             let index4 = index1234.extract(3) as usize;
 
-            let src1 = unsafe { ptr::read_unaligned(&wa[index1] as *const T as *const u64x2) };
-            let src2 = unsafe { ptr::read_unaligned(&wa[index2] as *const T as *const u64x2) };
-            let src3 = unsafe { ptr::read_unaligned(&wa[index3] as *const T as *const u64x2) };
-            let src4 = unsafe { ptr::read_unaligned(&wa[index4] as *const T as *const u64x2) };
+            let src1 = unsafe { ptr::read_unaligned(source.ptr_at::<u64x2>(index1)) };
+            let src2 = unsafe { ptr::read_unaligned(source.ptr_at::<u64x2>(index2)) };
+            let src3 = unsafe { ptr::read_unaligned(source.ptr_at::<u64x2>(index3)) };
+            let src4 = unsafe { ptr::read_unaligned(source.ptr_at::<u64x2>(index4)) };
 
             let t1a: u64x2 = shuffle!(src1, src2, [0, 2]); // unpcklpd
             let t2a: u64x2 = shuffle!(src3, src4, [0, 2]); // unpcklpd
@@ -176,8 +200,8 @@ impl<T: Num> AlignReqKernel<T> for AvxDWordRadix2BitReversalKernel {
             let dest2: *mut u64x4 = &mut data[(i + size) * 8] as *mut T as *mut u64x4;
 
             unsafe {
-                I::write(dest1, out1);
-                I::write(dest2, out2);
+                io.write(dest1, out1);
+                io.write(dest2, out2);
             }
 
             i += 1;
@@ -186,13 +210,23 @@ impl<T: Num> AlignReqKernel<T> for AvxDWordRadix2BitReversalKernel {
     fn required_work_area_size(&self) -> usize {
         self.indices.len() * 16
     }
+    fn gathers_input(&self) -> bool {
+        true
+    }
     fn alignment_requirement(&self) -> usize {
         32
     }
+    fn backend(&self) -> Backend {
+        Backend::Avx
+    }
+    fn table_size(&self) -> usize {
+        mem::size_of_val(&self.indices[..])
+    }
 }
 
 pub unsafe fn new_x86_avx_radix4_bit_reversal_kernel<T>(
-    indices: &Vec<usize>,
+    indices: &[usize],
+    scale: f64,
 ) -> Option<Box<Kernel<T>>>
 where
     T: Num,
@@ -233,6 +267,7 @@ where
                     .map(|i| (indices[i * 4] as u32) * 2)
                     .collect(),
                 offs: u32x4::new(0, f1 as u32 * 2, f2 as u32 * 2, f3 as u32 * 2),
+                scale,
             },
         )) as Box<Kernel<f32>>)
     })
@@ -242,17 +277,18 @@ where
 struct AvxDWordRadix4BitReversalKernel {
     indices: Vec<u32>,
     offs: u32x4,
+    scale: f64,
 }
 
 impl<T: Num> AlignReqKernel<T> for AvxDWordRadix4BitReversalKernel {
-    fn transform<I: AlignInfo>(&self, params: &mut KernelParams<T>) {
+    fn transform<I: AlignInfo>(&self, params: &mut KernelParams<T>, io: KernelIo<T, I>) {
         assert_eq!(mem::size_of::<T>(), 4);
 
         let indices = unsafe { SliceAccessor::new(&self.indices) };
         let size = self.indices.len();
         let mut data = unsafe { SliceAccessor::new(&mut params.coefs[0..size * 32]) };
         let mut wa = unsafe { SliceAccessor::new(&mut params.work_area[0..size * 32]) };
-        wa.copy_from_slice(*data);
+        let source = gather_source(&mut wa, &data, io.input(), self.scale);
 
         let offs = self.offs;
 
@@ -265,10 +301,10 @@ impl<T: Num> AlignReqKernel<T> for AvxDWordRadix4BitReversalKernel {
                 let index3 = index1234.extract(2) as usize;
                 let index4 = index1234.extract(3) as usize;
 
-                let src1 = unsafe { ptr::read_unaligned(&wa[index1] as *const T as *const u64x4) };
-                let src2 = unsafe { ptr::read_unaligned(&wa[index2] as *const T as *const u64x4) };
-                let src3 = unsafe { ptr::read_unaligned(&wa[index3] as *const T as *const u64x4) };
-                let src4 = unsafe { ptr::read_unaligned(&wa[index4] as *const T as *const u64x4) };
+                let src1 = unsafe { ptr::read_unaligned(source.ptr_at::<u64x4>(index1)) };
+                let src2 = unsafe { ptr::read_unaligned(source.ptr_at::<u64x4>(index2)) };
+                let src3 = unsafe { ptr::read_unaligned(source.ptr_at::<u64x4>(index3)) };
+                let src4 = unsafe { ptr::read_unaligned(source.ptr_at::<u64x4>(index4)) };
 
                 let t1a: u64x4 = shuffle!(src1, src2, [0, 4, 2, 6]); // unpcklpd
                 let t2a: u64x4 = shuffle!(src3, src4, [0, 4, 2, 6]); // unpcklpd
@@ -287,10 +323,10 @@ impl<T: Num> AlignReqKernel<T> for AvxDWordRadix4BitReversalKernel {
                 let dest4: *mut u64x4 = &mut data[(i + size * 3) * 8] as *mut T as *mut u64x4;
 
                 unsafe {
-                    I::write(dest1, out1);
-                    I::write(dest2, out2);
-                    I::write(dest3, out3);
-                    I::write(dest4, out4);
+                    io.write(dest1, out1);
+                    io.write(dest2, out2);
+                    io.write(dest3, out3);
+                    io.write(dest4, out4);
                 }
 
                 i += 1;
@@ -301,7 +337,16 @@ impl<T: Num> AlignReqKernel<T> for AvxDWordRadix4BitReversalKernel {
     fn required_work_area_size(&self) -> usize {
         self.indices.len() * 32
     }
+    fn gathers_input(&self) -> bool {
+        true
+    }
     fn alignment_requirement(&self) -> usize {
         32
     }
+    fn backend(&self) -> Backend {
+        Backend::Avx
+    }
+    fn table_size(&self) -> usize {
+        mem::size_of_val(&self.indices[..])
+    }
 }
@@ -7,21 +7,24 @@
 //!
 //! For small transforms ties with a commercial-level FFT library, but tends to be much slower for large transforms.
 
-use super::super::super::simdutils::{avx_f32x8_bitxor, avx_f32x8_complex_mul_riri};
-use super::utils::{
-    branch_on_static_params, if_compatible, AlignInfo, AlignReqKernel, AlignReqKernelWrapper,
-    StaticParams, StaticParamsConsumer,
-};
-use super::{Kernel, KernelCreationParams, KernelParams, KernelType, Num, SliceAccessor};
-
-use num_complex::Complex;
-use num_iter::range_step;
+use super::super::super::simdutils::{avx_f32x8_bitxor, avx_f32x8_complex_mul_riri, f32x8, u32x8};
+use super::super::staticparams::{branch_on_static_params, StaticParams, StaticParamsConsumer};
+use super::super::tables::shared_table;
+use super::super::twiddle::twiddle;
+use super::alignreq::{if_compatible, AlignInfo, AlignReqKernel, AlignReqKernelWrapper, KernelIo};
+use super::{Backend, Kernel, KernelCreationParams, KernelParams, KernelType, Num, SliceAccessor};
 
-use packed_simd::{f32x8, u32x8};
+use num_iter::range_step;
 
-use std::{f32, mem};
+use alloc::boxed::Box;
+use alloc::sync::Arc;
+use alloc::vec::Vec;
+use core::mem;
 
-pub fn new_x86_avx_f32_radix2_kernel<T>(cparams: &KernelCreationParams) -> Option<Box<Kernel<T>>>
+pub fn new_x86_avx_f32_radix2_kernel<T>(
+    cparams: &KernelCreationParams,
+    scale: f64,
+) -> Option<Box<Kernel<T>>>
 where
     T: Num,
 {
@@ -29,10 +32,12 @@ where
         return None;
     }
 
-    if_compatible(|| branch_on_static_params(cparams, Factory {}))
+    if_compatible(|| branch_on_static_params(cparams, Factory { scale }))
 }
 
-struct Factory {}
+struct Factory {
+    scale: f64,
+}
 impl StaticParamsConsumer<Option<Box<Kernel<f32>>>> for Factory {
     fn consume<T>(self, cparams: &KernelCreationParams, sparams: T) -> Option<Box<Kernel<f32>>>
     where
@@ -45,6 +50,7 @@ impl StaticParamsConsumer<Option<Box<Kernel<f32>>>> for Factory {
             1 if cparams.size % 4 == 0 => {
                 Some(Box::new(AlignReqKernelWrapper::new(AvxRadix2Kernel1 {
                     cparams: *cparams,
+                    scale: self.scale as f32,
                 })))
             }
             _ => None,
@@ -56,10 +62,12 @@ impl StaticParamsConsumer<Option<Box<Kernel<f32>>>> for Factory {
 #[derive(Debug)]
 struct AvxRadix2Kernel1 {
     cparams: KernelCreationParams,
+    /// The factor every output is multiplied by.
+    scale: f32,
 }
 
 impl AlignReqKernel<f32> for AvxRadix2Kernel1 {
-    fn transform<I: AlignInfo>(&self, params: &mut KernelParams<f32>) {
+    fn transform<I: AlignInfo>(&self, params: &mut KernelParams<f32>, io: KernelIo<f32, I>) {
         let cparams = &self.cparams;
         let mut data = unsafe { SliceAccessor::new(&mut params.coefs[0..cparams.size * 2]) };
 
@@ -72,31 +80,35 @@ impl AlignReqKernel<f32> for AvxRadix2Kernel1 {
                 0, 0, 0x80000000, 0x80000000, 0, 0, 0x80000000, 0x80000000,
             ))
         };
+        let scale = f32x8::splat(self.scale);
 
         for x in range_step(0, cparams.size * 2, 8) {
             let cur = &mut data[x] as *mut f32 as *mut f32x8;
             // t1a, t1b : Complex<f32> = X[x/2 .. x/2 + 2]
-            let t1 = unsafe { I::read(cur) };
+            let t1 = unsafe { io.read(cur) };
             // t2a, t2b = t1b, t1a
             let t2 = shuffle!(t1, t1, [2, 3, 8, 9, 6, 7, 12, 13]);
             // t3a, t3b = t1a, -t1b
             let t3 = avx_f32x8_bitxor(t1, neg_mask);
             // t4a, t4b = t2a + t3a, t3b + t3b = t1a + t1b, t1a - t1b
-            let t4 = t2 + t3;
+            let t4 = (t2 + t3) * scale;
             // Y[x/2 .. x/2 + 2] = t4a, t4b
-            unsafe { I::write(cur, t4) };
+            unsafe { io.write(cur, t4) };
         }
     }
     fn alignment_requirement(&self) -> usize {
         32
     }
+    fn backend(&self) -> Backend {
+        Backend::Avx
+    }
 }
 
 /// This Radix-2 kernel computes eight small FFTs in a single iteration.
 #[derive(Debug)]
 struct AvxRadix2Kernel2<T> {
     cparams: KernelCreationParams,
-    twiddles: Vec<f32x8>,
+    twiddles: Arc<Vec<f32x8>>,
     sparams: T,
 }
 
@@ -106,37 +118,29 @@ impl<T: StaticParams> AvxRadix2Kernel2<T> {
         assert_eq!(cparams.radix, 2);
         assert_eq!(cparams.unit % 4, 0);
 
-        let full_circle = if cparams.inverse { 2f32 } else { -2f32 };
-        let twiddles = range_step(0, cparams.unit, 4)
-            .map(|i| {
-                let c1 = Complex::new(
-                    0f32,
-                    full_circle * (i) as f32 / (cparams.radix * cparams.unit) as f32
-                        * f32::consts::PI,
-                )
-                .exp();
-                let c2 = Complex::new(
-                    0f32,
-                    full_circle * (i + 1) as f32 / (cparams.radix * cparams.unit) as f32
-                        * f32::consts::PI,
-                )
-                .exp();
-                let c3 = Complex::new(
-                    0f32,
-                    full_circle * (i + 2) as f32 / (cparams.radix * cparams.unit) as f32
-                        * f32::consts::PI,
-                )
-                .exp();
-                let c4 = Complex::new(
-                    0f32,
-                    full_circle * (i + 3) as f32 / (cparams.radix * cparams.unit) as f32
-                        * f32::consts::PI,
-                )
-                .exp();
-                // riririri format
-                f32x8::new(c1.re, c1.im, c2.re, c2.im, c3.re, c3.im, c4.re, c4.im)
-            })
-            .collect();
+        let params = [cparams.unit as u64, cparams.inverse as u64];
+        let twiddles = shared_table("x86_avx_radix2_kernel2", &params, || {
+            let full_circle = if cparams.inverse { 2 } else { -2 };
+            range_step(0, cparams.unit, 4)
+                .map(|i| {
+                    let c1 = twiddle::<f32>(full_circle * i as isize, cparams.radix * cparams.unit);
+                    let c2 = twiddle::<f32>(
+                        full_circle * (i + 1) as isize,
+                        cparams.radix * cparams.unit,
+                    );
+                    let c3 = twiddle::<f32>(
+                        full_circle * (i + 2) as isize,
+                        cparams.radix * cparams.unit,
+                    );
+                    let c4 = twiddle::<f32>(
+                        full_circle * (i + 3) as isize,
+                        cparams.radix * cparams.unit,
+                    );
+                    // riririri format
+                    f32x8::new(c1.re, c1.im, c2.re, c2.im, c3.re, c3.im, c4.re, c4.im)
+                })
+                .collect()
+        });
 
         Self {
             cparams: *cparams,
@@ -147,7 +151,7 @@ impl<T: StaticParams> AvxRadix2Kernel2<T> {
 }
 
 impl<T: StaticParams> AlignReqKernel<f32> for AvxRadix2Kernel2<T> {
-    fn transform<I: AlignInfo>(&self, params: &mut KernelParams<f32>) {
+    fn transform<I: AlignInfo>(&self, params: &mut KernelParams<f32>, io: KernelIo<f32, I>) {
         let cparams = &self.cparams;
         let sparams = &self.sparams;
         let mut data = unsafe { SliceAccessor::new(&mut params.coefs[0..cparams.size * 2]) };
@@ -165,10 +169,10 @@ impl<T: StaticParams> AlignReqKernel<f32> for AvxRadix2Kernel2<T> {
 
                 // x1a, x1b : Complex<f32> = X[x1/2 .. x1/2 + 2]
                 // (x1a.r, x1a.i, x1b.r, x1b.i, ...)
-                let x1 = unsafe { I::read(cur1) };
+                let x1 = unsafe { io.read(cur1) };
                 // y1a, y1b : Complex<f32> = X[x2/2 .. x2/2 + 2]
                 // (y1a.r, y1a.i, y1b.r, y1b.i, ...)
-                let y1 = unsafe { I::read(cur2) };
+                let y1 = unsafe { io.read(cur2) };
 
                 // apply twiddle factor
                 // (y1a.r, y1b.r, y1a.i, y1b.i)
@@ -193,12 +197,18 @@ impl<T: StaticParams> AlignReqKernel<f32> for AvxRadix2Kernel2<T> {
                     y3
                 };
 
-                unsafe { I::write(cur1, x4) };
-                unsafe { I::write(cur2, y4) };
+                unsafe { io.write(cur1, x4) };
+                unsafe { io.write(cur2, y4) };
             }
         }
     }
     fn alignment_requirement(&self) -> usize {
         32
     }
+    fn backend(&self) -> Backend {
+        Backend::Avx
+    }
+    fn table_size(&self) -> usize {
+        mem::size_of_val(&self.twiddles[..])
+    }
 }
@@ -7,21 +7,22 @@
 //!
 //! For small transforms ties with a commercial-level FFT library, but tends to be much slower for large transforms.
 
-use super::utils::{
-    branch_on_static_params, if_compatible, AlignInfo, AlignReqKernel, AlignReqKernelWrapper,
-    StaticParams, StaticParamsConsumer,
-};
-use super::{Kernel, KernelCreationParams, KernelParams, KernelType, Num, SliceAccessor};
+use super::super::staticparams::{branch_on_static_params, StaticParams, StaticParamsConsumer};
+use super::super::tables::shared_table;
+use super::super::twiddle::twiddle;
+use super::alignreq::{if_compatible, AlignInfo, AlignReqKernel, AlignReqKernelWrapper, KernelIo};
+use super::{Backend, Kernel, KernelCreationParams, KernelParams, KernelType, Num, SliceAccessor};
 use simdutils::{
-    avx_f32x8_bitxor, avx_f32x8_complex_mul_riri, avx_fma_f32x8_fmadd, avx_fma_f32x8_fmsub,
+    avx_f32x8_bitxor, avx_f32x8_complex_mul_riri, avx_fma_f32x8_fmadd, avx_fma_f32x8_fmsub, f32x8,
+    u32x8,
 };
 
-use num_complex::Complex;
 use num_iter::range_step;
 
-use packed_simd::{f32x8, u32x8};
-
-use std::{f32, mem};
+use alloc::boxed::Box;
+use alloc::sync::Arc;
+use alloc::vec::Vec;
+use core::mem;
 
 pub fn new_x86_avx_f32_radix4_kernel<T>(cparams: &KernelCreationParams) -> Option<Box<Kernel<T>>>
 where
@@ -70,22 +71,10 @@ impl<T: StaticParams> AvxRadix4Kernel2<T> {
         assert_eq!(cparams.radix, 4);
         assert_eq!(cparams.unit, 2);
 
-        let full_circle = if cparams.inverse { 2f32 } else { -2f32 };
-        let c1 = Complex::new(
-            0f32,
-            full_circle * 2 as f32 / (cparams.radix * cparams.unit) as f32 * f32::consts::PI,
-        )
-        .exp();
-        let c2 = Complex::new(
-            0f32,
-            full_circle * 1 as f32 / (cparams.radix * cparams.unit) as f32 * f32::consts::PI,
-        )
-        .exp();
-        let c3 = Complex::new(
-            0f32,
-            full_circle * 3 as f32 / (cparams.radix * cparams.unit) as f32 * f32::consts::PI,
-        )
-        .exp();
+        let full_circle = if cparams.inverse { 2 } else { -2 };
+        let c1 = twiddle::<f32>(full_circle * 2, cparams.radix * cparams.unit);
+        let c2 = twiddle::<f32>(full_circle * 1, cparams.radix * cparams.unit);
+        let c3 = twiddle::<f32>(full_circle * 3, cparams.radix * cparams.unit);
         // riri format
         let twiddles = f32x8::new(1f32, 0f32, c1.re, c1.im, c2.re, c2.im, c3.re, c3.im);
 
@@ -98,7 +87,7 @@ impl<T: StaticParams> AvxRadix4Kernel2<T> {
 }
 
 impl<T: StaticParams> AlignReqKernel<f32> for AvxRadix4Kernel2<T> {
-    fn transform<I: AlignInfo>(&self, params: &mut KernelParams<f32>) {
+    fn transform<I: AlignInfo>(&self, params: &mut KernelParams<f32>, io: KernelIo<f32, I>) {
         let cparams = &self.cparams;
         let sparams = &self.sparams;
         let mut data = unsafe { SliceAccessor::new(&mut params.coefs[0..cparams.size * 2]) };
@@ -121,8 +110,8 @@ impl<T: StaticParams> AlignReqKernel<f32> for AvxRadix4Kernel2<T> {
             let cur2 = &mut data[x + 8] as *mut f32 as *mut f32x8;
 
             // riri format
-            let xy1 = unsafe { I::read(cur1) };
-            let zw1 = unsafe { I::read(cur2) };
+            let xy1 = unsafe { io.read(cur1) };
+            let zw1 = unsafe { io.read(cur2) };
 
             // apply twiddle factor
             let (xy2, zw2) = if pre_twiddle {
@@ -173,20 +162,26 @@ impl<T: StaticParams> AlignReqKernel<f32> for AvxRadix4Kernel2<T> {
                 (xy3, zw3)
             };
 
-            unsafe { I::write(cur1, xy4) };
-            unsafe { I::write(cur2, zw4) };
+            unsafe { io.write(cur1, xy4) };
+            unsafe { io.write(cur2, zw4) };
         }
     }
     fn alignment_requirement(&self) -> usize {
         32
     }
+    fn backend(&self) -> Backend {
+        Backend::Avx
+    }
+    fn table_size(&self) -> usize {
+        mem::size_of_val(&self.twiddles)
+    }
 }
 
 /// This Radix-4 kernel computes four small FFTs in a single iteration.
 #[derive(Debug)]
 struct AvxRadix4Kernel3<T> {
     cparams: KernelCreationParams,
-    twiddles: Vec<f32x8>,
+    twiddles: Arc<Vec<f32x8>>,
     sparams: T,
 }
 
@@ -196,53 +191,41 @@ impl<T: StaticParams> AvxRadix4Kernel3<T> {
         assert_eq!(cparams.radix, 4);
         assert_eq!(cparams.unit % 4, 0);
 
-        let full_circle = if cparams.inverse { 2f32 } else { -2f32 };
-        let mut twiddles = Vec::new();
-        for i in range_step(0, cparams.unit, 4) {
-            let c1 = Complex::new(
-                0f32,
-                full_circle * (i) as f32 / (cparams.radix * cparams.unit) as f32 * f32::consts::PI,
-            )
-            .exp();
-            let c2 = Complex::new(
-                0f32,
-                full_circle * (i + 1) as f32 / (cparams.radix * cparams.unit) as f32
-                    * f32::consts::PI,
-            )
-            .exp();
-            let c3 = Complex::new(
-                0f32,
-                full_circle * (i + 2) as f32 / (cparams.radix * cparams.unit) as f32
-                    * f32::consts::PI,
-            )
-            .exp();
-            let c4 = Complex::new(
-                0f32,
-                full_circle * (i + 3) as f32 / (cparams.radix * cparams.unit) as f32
-                    * f32::consts::PI,
-            )
-            .exp();
-            // riri format
-            twiddles.push(f32x8::new(
-                c1.re, c1.im, c2.re, c2.im, c3.re, c3.im, c4.re, c4.im,
-            ));
-
-            let c12 = c1 * c1;
-            let c22 = c2 * c2;
-            let c32 = c3 * c3;
-            let c42 = c4 * c4;
-            twiddles.push(f32x8::new(
-                c12.re, c12.im, c22.re, c22.im, c32.re, c32.im, c42.re, c42.im,
-            ));
-
-            let c13 = c12 * c1;
-            let c23 = c22 * c2;
-            let c33 = c32 * c3;
-            let c43 = c42 * c4;
-            twiddles.push(f32x8::new(
-                c13.re, c13.im, c23.re, c23.im, c33.re, c33.im, c43.re, c43.im,
-            ));
-        }
+        let params = [cparams.unit as u64, cparams.inverse as u64];
+        let twiddles = shared_table("x86_avx_radix4_kernel3", &params, || {
+            let full_circle = if cparams.inverse { 2 } else { -2 };
+            let mut twiddles = Vec::new();
+            for i in range_step(0, cparams.unit, 4) {
+                let c1 = twiddle::<f32>(full_circle * i as isize, cparams.radix * cparams.unit);
+                let c2 =
+                    twiddle::<f32>(full_circle * (i + 1) as isize, cparams.radix * cparams.unit);
+                let c3 =
+                    twiddle::<f32>(full_circle * (i + 2) as isize, cparams.radix * cparams.unit);
+                let c4 =
+                    twiddle::<f32>(full_circle * (i + 3) as isize, cparams.radix * cparams.unit);
+                // riri format
+                twiddles.push(f32x8::new(
+                    c1.re, c1.im, c2.re, c2.im, c3.re, c3.im, c4.re, c4.im,
+                ));
+
+                let c12 = c1 * c1;
+                let c22 = c2 * c2;
+                let c32 = c3 * c3;
+                let c42 = c4 * c4;
+                twiddles.push(f32x8::new(
+                    c12.re, c12.im, c22.re, c22.im, c32.re, c32.im, c42.re, c42.im,
+                ));
+
+                let c13 = c12 * c1;
+                let c23 = c22 * c2;
+                let c33 = c32 * c3;
+                let c43 = c42 * c4;
+                twiddles.push(f32x8::new(
+                    c13.re, c13.im, c23.re, c23.im, c33.re, c33.im, c43.re, c43.im,
+                ));
+            }
+            twiddles
+        });
 
         Self {
             cparams: *cparams,
@@ -253,7 +236,7 @@ impl<T: StaticParams> AvxRadix4Kernel3<T> {
 }
 
 impl<T: StaticParams> AlignReqKernel<f32> for AvxRadix4Kernel3<T> {
-    fn transform<I: AlignInfo>(&self, params: &mut KernelParams<f32>) {
+    fn transform<I: AlignInfo>(&self, params: &mut KernelParams<f32>, io: KernelIo<f32, I>) {
         let cparams = &self.cparams;
         let sparams = &self.sparams;
         let mut data = unsafe { SliceAccessor::new(&mut params.coefs[0..cparams.size * 2]) };
@@ -282,10 +265,10 @@ impl<T: StaticParams> AlignReqKernel<f32> for AvxRadix4Kernel3<T> {
                 let twiddle_3 = twiddles[y * 3 + 2];
 
                 // riri format
-                let x1 = unsafe { I::read(cur1) };
-                let y1 = unsafe { I::read(cur2) };
-                let z1 = unsafe { I::read(cur3) };
-                let w1 = unsafe { I::read(cur4) };
+                let x1 = unsafe { io.read(cur1) };
+                let y1 = unsafe { io.read(cur2) };
+                let z1 = unsafe { io.read(cur3) };
+                let w1 = unsafe { io.read(cur4) };
 
                 // apply twiddle factor
                 let x2 = x1;
@@ -339,23 +322,29 @@ impl<T: StaticParams> AlignReqKernel<f32> for AvxRadix4Kernel3<T> {
                     w4
                 };
 
-                unsafe { I::write(cur1, x5) };
-                unsafe { I::write(cur2, y5) };
-                unsafe { I::write(cur3, z5) };
-                unsafe { I::write(cur4, w5) };
+                unsafe { io.write(cur1, x5) };
+                unsafe { io.write(cur2, y5) };
+                unsafe { io.write(cur3, z5) };
+                unsafe { io.write(cur4, w5) };
             }
         }
     }
     fn alignment_requirement(&self) -> usize {
         32
     }
+    fn backend(&self) -> Backend {
+        Backend::Avx
+    }
+    fn table_size(&self) -> usize {
+        mem::size_of_val(&self.twiddles[..])
+    }
 }
 
 /// This Radix-4 kernel computes eight small FFTs in a single iteration.
 #[derive(Debug)]
 struct AvxRadix4Kernel4<T: StaticParams> {
     cparams: KernelCreationParams,
-    twiddles: Vec<f32x8>,
+    twiddles: Arc<Vec<f32x8>>,
     sparams: T,
 }
 
@@ -365,95 +354,67 @@ impl<T: StaticParams> AvxRadix4Kernel4<T> {
         assert_eq!(cparams.radix, 4);
         assert_eq!(cparams.unit % 8, 0);
 
-        let full_circle = if cparams.inverse { 2f32 } else { -2f32 };
-        let mut twiddles = Vec::new();
-        for i in range_step(0, cparams.unit, 8) {
-            let c1 = Complex::new(
-                0f32,
-                full_circle * (i) as f32 / (cparams.radix * cparams.unit) as f32 * f32::consts::PI,
-            )
-            .exp();
-            let c2 = Complex::new(
-                0f32,
-                full_circle * (i + 1) as f32 / (cparams.radix * cparams.unit) as f32
-                    * f32::consts::PI,
-            )
-            .exp();
-            let c3 = Complex::new(
-                0f32,
-                full_circle * (i + 2) as f32 / (cparams.radix * cparams.unit) as f32
-                    * f32::consts::PI,
-            )
-            .exp();
-            let c4 = Complex::new(
-                0f32,
-                full_circle * (i + 3) as f32 / (cparams.radix * cparams.unit) as f32
-                    * f32::consts::PI,
-            )
-            .exp();
-            let c5 = Complex::new(
-                0f32,
-                full_circle * (i + 4) as f32 / (cparams.radix * cparams.unit) as f32
-                    * f32::consts::PI,
-            )
-            .exp();
-            let c6 = Complex::new(
-                0f32,
-                full_circle * (i + 5) as f32 / (cparams.radix * cparams.unit) as f32
-                    * f32::consts::PI,
-            )
-            .exp();
-            let c7 = Complex::new(
-                0f32,
-                full_circle * (i + 6) as f32 / (cparams.radix * cparams.unit) as f32
-                    * f32::consts::PI,
-            )
-            .exp();
-            let c8 = Complex::new(
-                0f32,
-                full_circle * (i + 7) as f32 / (cparams.radix * cparams.unit) as f32
-                    * f32::consts::PI,
-            )
-            .exp();
-            // rrrrrrrr-iiiiiiiii format
-            // 12563478
-            twiddles.push(f32x8::new(
-                c1.re, c2.re, c5.re, c6.re, c3.re, c4.re, c7.re, c8.re,
-            ));
-            twiddles.push(f32x8::new(
-                c1.im, c2.im, c5.im, c6.im, c3.im, c4.im, c7.im, c8.im,
-            ));
-
-            let c12 = c1 * c1;
-            let c22 = c2 * c2;
-            let c32 = c3 * c3;
-            let c42 = c4 * c4;
-            let c52 = c5 * c5;
-            let c62 = c6 * c6;
-            let c72 = c7 * c7;
-            let c82 = c8 * c8;
-            twiddles.push(f32x8::new(
-                c12.re, c22.re, c52.re, c62.re, c32.re, c42.re, c72.re, c82.re,
-            ));
-            twiddles.push(f32x8::new(
-                c12.im, c22.im, c52.im, c62.im, c32.im, c42.im, c72.im, c82.im,
-            ));
-
-            let c13 = c12 * c1;
-            let c23 = c22 * c2;
-            let c33 = c32 * c3;
-            let c43 = c42 * c4;
-            let c53 = c52 * c5;
-            let c63 = c62 * c6;
-            let c73 = c72 * c7;
-            let c83 = c82 * c8;
-            twiddles.push(f32x8::new(
-                c13.re, c23.re, c53.re, c63.re, c33.re, c43.re, c73.re, c83.re,
-            ));
-            twiddles.push(f32x8::new(
-                c13.im, c23.im, c53.im, c63.im, c33.im, c43.im, c73.im, c83.im,
-            ));
-        }
+        let params = [cparams.unit as u64, cparams.inverse as u64];
+        let twiddles = shared_table("x86_avx_radix4_kernel4", &params, || {
+            let full_circle = if cparams.inverse { 2 } else { -2 };
+            let mut twiddles = Vec::new();
+            for i in range_step(0, cparams.unit, 8) {
+                let c1 = twiddle::<f32>(full_circle * i as isize, cparams.radix * cparams.unit);
+                let c2 =
+                    twiddle::<f32>(full_circle * (i + 1) as isize, cparams.radix * cparams.unit);
+                let c3 =
+                    twiddle::<f32>(full_circle * (i + 2) as isize, cparams.radix * cparams.unit);
+                let c4 =
+                    twiddle::<f32>(full_circle * (i + 3) as isize, cparams.radix * cparams.unit);
+                let c5 =
+                    twiddle::<f32>(full_circle * (i + 4) as isize, cparams.radix * cparams.unit);
+                let c6 =
+                    twiddle::<f32>(full_circle * (i + 5) as isize, cparams.radix * cparams.unit);
+                let c7 =
+                    twiddle::<f32>(full_circle * (i + 6) as isize, cparams.radix * cparams.unit);
+                let c8 =
+                    twiddle::<f32>(full_circle * (i + 7) as isize, cparams.radix * cparams.unit);
+                // rrrrrrrr-iiiiiiiii format
+                // 12563478
+                twiddles.push(f32x8::new(
+                    c1.re, c2.re, c5.re, c6.re, c3.re, c4.re, c7.re, c8.re,
+                ));
+                twiddles.push(f32x8::new(
+                    c1.im, c2.im, c5.im, c6.im, c3.im, c4.im, c7.im, c8.im,
+                ));
+
+                let c12 = c1 * c1;
+                let c22 = c2 * c2;
+                let c32 = c3 * c3;
+                let c42 = c4 * c4;
+                let c52 = c5 * c5;
+                let c62 = c6 * c6;
+                let c72 = c7 * c7;
+                let c82 = c8 * c8;
+                twiddles.push(f32x8::new(
+                    c12.re, c22.re, c52.re, c62.re, c32.re, c42.re, c72.re, c82.re,
+                ));
+                twiddles.push(f32x8::new(
+                    c12.im, c22.im, c52.im, c62.im, c32.im, c42.im, c72.im, c82.im,
+                ));
+
+                let c13 = c12 * c1;
+                let c23 = c22 * c2;
+                let c33 = c32 * c3;
+                let c43 = c42 * c4;
+                let c53 = c52 * c5;
+                let c63 = c62 * c6;
+                let c73 = c72 * c7;
+                let c83 = c82 * c8;
+                twiddles.push(f32x8::new(
+                    c13.re, c23.re, c53.re, c63.re, c33.re, c43.re, c73.re, c83.re,
+                ));
+                twiddles.push(f32x8::new(
+                    c13.im, c23.im, c53.im, c63.im, c33.im, c43.im, c73.im, c83.im,
+                ));
+            }
+            twiddles
+        });
 
         Self {
             cparams: *cparams,
@@ -464,7 +425,7 @@ impl<T: StaticParams> AvxRadix4Kernel4<T> {
 }
 
 impl<T: StaticParams> AlignReqKernel<f32> for AvxRadix4Kernel4<T> {
-    fn transform<I: AlignInfo>(&self, params: &mut KernelParams<f32>) {
+    fn transform<I: AlignInfo>(&self, params: &mut KernelParams<f32>, io: KernelIo<f32, I>) {
         let cparams = &self.cparams;
         let sparams = &self.sparams;
         let mut data = unsafe { SliceAccessor::new(&mut params.coefs[0..cparams.size * 2]) };
@@ -490,14 +451,14 @@ impl<T: StaticParams> AlignReqKernel<f32> for AvxRadix4Kernel4<T> {
                 let twiddle3_r = twiddles[y * 6 + 4];
                 let twiddle3_i = twiddles[y * 6 + 5];
 
-                let x1a = unsafe { I::read(cur1a) };
-                let x1b = unsafe { I::read(cur1b) };
-                let y1a = unsafe { I::read(cur2a) };
-                let y1b = unsafe { I::read(cur2b) };
-                let z1a = unsafe { I::read(cur3a) };
-                let z1b = unsafe { I::read(cur3b) };
-                let w1a = unsafe { I::read(cur4a) };
-                let w1b = unsafe { I::read(cur4b) };
+                let x1a = unsafe { io.read(cur1a) };
+                let x1b = unsafe { io.read(cur1b) };
+                let y1a = unsafe { io.read(cur2a) };
+                let y1b = unsafe { io.read(cur2b) };
+                let z1a = unsafe { io.read(cur3a) };
+                let z1b = unsafe { io.read(cur3b) };
+                let w1a = unsafe { io.read(cur4a) };
+                let w1b = unsafe { io.read(cur4b) };
 
                 // convert riririri-riririri to rrrrrrrr-iiiiiiii (vshufps)
                 //         1 2 3 4  5 6 7 8     12563478
@@ -608,18 +569,24 @@ impl<T: StaticParams> AlignReqKernel<f32> for AvxRadix4Kernel4<T> {
                 let w7a = shuffle!(w6r, w6i, [0, 8, 1, 9, 4, 12, 5, 13]);
                 let w7b = shuffle!(w6r, w6i, [2, 10, 3, 11, 6, 14, 7, 15]);
 
-                unsafe { I::write(cur1a, x7a) };
-                unsafe { I::write(cur1b, x7b) };
-                unsafe { I::write(cur2a, y7a) };
-                unsafe { I::write(cur2b, y7b) };
-                unsafe { I::write(cur3a, z7a) };
-                unsafe { I::write(cur3b, z7b) };
-                unsafe { I::write(cur4a, w7a) };
-                unsafe { I::write(cur4b, w7b) };
+                unsafe { io.write(cur1a, x7a) };
+                unsafe { io.write(cur1b, x7b) };
+                unsafe { io.write(cur2a, y7a) };
+                unsafe { io.write(cur2b, y7b) };
+                unsafe { io.write(cur3a, z7a) };
+                unsafe { io.write(cur3b, z7b) };
+                unsafe { io.write(cur4a, w7a) };
+                unsafe { io.write(cur4b, w7b) };
             }
         }
     }
     fn alignment_requirement(&self) -> usize {
         32
     }
+    fn backend(&self) -> Backend {
+        Backend::Avx
+    }
+    fn table_size(&self) -> usize {
+        mem::size_of_val(&self.twiddles[..])
+    }
 }
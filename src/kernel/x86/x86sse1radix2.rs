@@ -7,21 +7,24 @@
 //! all optimizations and instruction sets including ones that this kernel doesn't support enabled) on a Skylake
 //! machine.
 
-use super::super::super::simdutils::{f32x4_bitxor, f32x4_complex_mul_rrii};
-use super::utils::{
-    branch_on_static_params, if_compatible, AlignInfo, AlignReqKernel, AlignReqKernelWrapper,
-    StaticParams, StaticParamsConsumer,
-};
-use super::{Kernel, KernelCreationParams, KernelParams, KernelType, Num, SliceAccessor};
-
-use num_complex::Complex;
-use num_iter::range_step;
+use super::super::super::simdutils::{f32x4, f32x4_bitxor, f32x4_complex_mul_rrii};
+use super::super::staticparams::{branch_on_static_params, StaticParams, StaticParamsConsumer};
+use super::super::tables::shared_table;
+use super::super::twiddle::twiddle;
+use super::alignreq::{if_compatible, AlignInfo, AlignReqKernel, AlignReqKernelWrapper, KernelIo};
+use super::{Backend, Kernel, KernelCreationParams, KernelParams, KernelType, Num, SliceAccessor};
 
-use packed_simd::f32x4;
+use num_iter::range_step;
 
-use std::f32;
+use alloc::boxed::Box;
+use alloc::sync::Arc;
+use alloc::vec::Vec;
+use core::{mem, ptr};
 
-pub fn new_x86_sse_radix2_kernel<T>(cparams: &KernelCreationParams) -> Option<Box<Kernel<T>>>
+pub fn new_x86_sse_radix2_kernel<T>(
+    cparams: &KernelCreationParams,
+    scale: f64,
+) -> Option<Box<Kernel<T>>>
 where
     T: Num,
 {
@@ -29,10 +32,12 @@ where
         return None;
     }
 
-    if_compatible(|| branch_on_static_params(cparams, Factory {}))
+    if_compatible(|| branch_on_static_params(cparams, Factory { scale }))
 }
 
-struct Factory {}
+struct Factory {
+    scale: f64,
+}
 impl StaticParamsConsumer<Option<Box<Kernel<f32>>>> for Factory {
     fn consume<T>(self, cparams: &KernelCreationParams, sparams: T) -> Option<Box<Kernel<f32>>>
     where
@@ -47,6 +52,7 @@ impl StaticParamsConsumer<Option<Box<Kernel<f32>>>> for Factory {
             ))),
             1 => Some(Box::new(AlignReqKernelWrapper::new(SseRadix2Kernel1 {
                 cparams: *cparams,
+                scale: self.scale as f32,
             }))),
             _ => None,
         }
@@ -57,10 +63,12 @@ impl StaticParamsConsumer<Option<Box<Kernel<f32>>>> for Factory {
 #[derive(Debug)]
 struct SseRadix2Kernel1 {
     cparams: KernelCreationParams,
+    /// The factor every output is multiplied by.
+    scale: f32,
 }
 
 impl AlignReqKernel<f32> for SseRadix2Kernel1 {
-    fn transform<I: AlignInfo>(&self, params: &mut KernelParams<f32>) {
+    fn transform<I: AlignInfo>(&self, params: &mut KernelParams<f32>, io: KernelIo<f32, I>) {
         let cparams = &self.cparams;
         let mut data = unsafe { SliceAccessor::new(&mut params.coefs[0..cparams.size * 2]) };
 
@@ -68,32 +76,36 @@ impl AlignReqKernel<f32> for SseRadix2Kernel1 {
         assert_eq!(cparams.unit, 1);
 
         let neg_mask_raw: [u32; 4] = [0, 0, 0x80000000, 0x80000000];
-        let neg_mask = unsafe { *(&neg_mask_raw as *const u32 as *const f32x4) };
+        let neg_mask = unsafe { ptr::read_unaligned(&neg_mask_raw as *const u32 as *const f32x4) };
+        let scale = f32x4::splat(self.scale);
 
         for x in range_step(0, cparams.size * 2, 4) {
             let cur = &mut data[x] as *mut f32 as *mut f32x4;
             // t1a, t1b : Complex<f32> = X[x/2 .. x/2 + 2]
-            let t1 = unsafe { I::read(cur) };
+            let t1 = unsafe { io.read(cur) };
             // t2a, t2b = t1b, t1a
             let t2 = shuffle!(t1, t1, [2, 3, 4, 5]);
             // t3a, t3b = t1a, -t1b
             let t3 = f32x4_bitxor(t1, neg_mask);
             // t4a, t4b = t2a + t3a, t3b + t3b = t1a + t1b, t1a - t1b
-            let t4 = t2 + t3;
+            let t4 = (t2 + t3) * scale;
             // Y[x/2 .. x/2 + 2] = t4a, t4b
-            unsafe { I::write(cur, t4) };
+            unsafe { io.write(cur, t4) };
         }
     }
     fn alignment_requirement(&self) -> usize {
         16
     }
+    fn backend(&self) -> Backend {
+        Backend::Sse
+    }
 }
 
 /// This Radix-2 kernel computes two small FFTs in a single iteration.
 #[derive(Debug)]
 struct SseRadix2Kernel2<T> {
     cparams: KernelCreationParams,
-    twiddles: Vec<f32x4>,
+    twiddles: Arc<Vec<f32x4>>,
     sparams: T,
 }
 
@@ -103,25 +115,21 @@ impl<T: StaticParams> SseRadix2Kernel2<T> {
         assert_eq!(cparams.radix, 2);
         assert_eq!(cparams.unit % 2, 0);
 
-        let full_circle = if cparams.inverse { 2f32 } else { -2f32 };
-        let twiddles = range_step(0, cparams.unit, 2)
-            .map(|i| {
-                let c1 = Complex::new(
-                    0f32,
-                    full_circle * (i) as f32 / (cparams.radix * cparams.unit) as f32
-                        * f32::consts::PI,
-                )
-                .exp();
-                let c2 = Complex::new(
-                    0f32,
-                    full_circle * (i + 1) as f32 / (cparams.radix * cparams.unit) as f32
-                        * f32::consts::PI,
-                )
-                .exp();
-                // rrii format
-                f32x4::new(c1.re, c2.re, c1.im, c2.im)
-            })
-            .collect();
+        let params = [cparams.unit as u64, cparams.inverse as u64];
+        let twiddles = shared_table("x86_sse_radix2_kernel2", &params, || {
+            let full_circle = if cparams.inverse { 2 } else { -2 };
+            range_step(0, cparams.unit, 2)
+                .map(|i| {
+                    let c1 = twiddle::<f32>(full_circle * i as isize, cparams.radix * cparams.unit);
+                    let c2 = twiddle::<f32>(
+                        full_circle * (i + 1) as isize,
+                        cparams.radix * cparams.unit,
+                    );
+                    // rrii format
+                    f32x4::new(c1.re, c2.re, c1.im, c2.im)
+                })
+                .collect()
+        });
 
         Self {
             cparams: *cparams,
@@ -132,7 +140,7 @@ impl<T: StaticParams> SseRadix2Kernel2<T> {
 }
 
 impl<T: StaticParams> AlignReqKernel<f32> for SseRadix2Kernel2<T> {
-    fn transform<I: AlignInfo>(&self, params: &mut KernelParams<f32>) {
+    fn transform<I: AlignInfo>(&self, params: &mut KernelParams<f32>, io: KernelIo<f32, I>) {
         // TODO: SSE3 version of this kernel
         // (SSE3 can perform complex multiplication in the RIRI format efficiently)
         let cparams = &self.cparams;
@@ -142,7 +150,7 @@ impl<T: StaticParams> AlignReqKernel<f32> for SseRadix2Kernel2<T> {
         let twiddles = unsafe { SliceAccessor::new(self.twiddles.as_slice()) };
 
         let neg_mask_raw: [u32; 4] = [0x80000000, 0x80000000, 0, 0];
-        let neg_mask = unsafe { *(&neg_mask_raw as *const u32 as *const f32x4) };
+        let neg_mask = unsafe { ptr::read_unaligned(&neg_mask_raw as *const u32 as *const f32x4) };
 
         let pre_twiddle = sparams.kernel_type() == KernelType::Dit;
         let post_twiddle = sparams.kernel_type() == KernelType::Dif;
@@ -155,10 +163,10 @@ impl<T: StaticParams> AlignReqKernel<f32> for SseRadix2Kernel2<T> {
 
                 // x1a, x1b : Complex<f32> = X[x1/2 .. x1/2 + 2]
                 // (x1a.r, x1a.i, x1b.r, x1b.i)
-                let x1 = unsafe { I::read(cur1) };
+                let x1 = unsafe { io.read(cur1) };
                 // y1a, y1b : Complex<f32> = X[x2/2 .. x2/2 + 2]
                 // (y1a.r, y1a.i, y1b.r, y1b.i)
-                let y1 = unsafe { I::read(cur2) };
+                let y1 = unsafe { io.read(cur2) };
 
                 // apply twiddle factor
                 // (y1a.r, y1b.r, y1a.i, y1b.i)
@@ -187,21 +195,27 @@ impl<T: StaticParams> AlignReqKernel<f32> for SseRadix2Kernel2<T> {
                     y3
                 };
 
-                unsafe { I::write(cur1, x4) };
-                unsafe { I::write(cur2, y4) };
+                unsafe { io.write(cur1, x4) };
+                unsafe { io.write(cur2, y4) };
             }
         }
     }
     fn alignment_requirement(&self) -> usize {
         16
     }
+    fn backend(&self) -> Backend {
+        Backend::Sse
+    }
+    fn table_size(&self) -> usize {
+        mem::size_of_val(&self.twiddles[..])
+    }
 }
 
 /// This Radix-2 kernel computes four small FFTs in a single iteration.
 #[derive(Debug)]
 struct SseRadix2Kernel3<T> {
     cparams: KernelCreationParams,
-    twiddles: Vec<f32x4>,
+    twiddles: Arc<Vec<f32x4>>,
     sparams: T,
 }
 
@@ -211,43 +225,35 @@ impl<T: StaticParams> SseRadix2Kernel3<T> {
         assert_eq!(cparams.radix, 2);
         assert_eq!(cparams.unit % 4, 0);
 
-        let full_circle = if cparams.inverse { 2f32 } else { -2f32 };
-        let twiddles = range_step(0, cparams.unit, 2)
-            .map(|i| {
-                let k = i / 4 * 4;
-                let c1 = Complex::new(
-                    0f32,
-                    full_circle * (k) as f32 / (cparams.radix * cparams.unit) as f32
-                        * f32::consts::PI,
-                )
-                .exp();
-                let c2 = Complex::new(
-                    0f32,
-                    full_circle * (k + 1) as f32 / (cparams.radix * cparams.unit) as f32
-                        * f32::consts::PI,
-                )
-                .exp();
-                let c3 = Complex::new(
-                    0f32,
-                    full_circle * (k + 2) as f32 / (cparams.radix * cparams.unit) as f32
-                        * f32::consts::PI,
-                )
-                .exp();
-                let c4 = Complex::new(
-                    0f32,
-                    full_circle * (k + 3) as f32 / (cparams.radix * cparams.unit) as f32
-                        * f32::consts::PI,
-                )
-                .exp();
-                // rrrr-iiii format
-                // TODO: more efficient creation
-                if i % 4 != 0 {
-                    f32x4::new(c1.im, c2.im, c3.im, c4.im)
-                } else {
-                    f32x4::new(c1.re, c2.re, c3.re, c4.re)
-                }
-            })
-            .collect();
+        let params = [cparams.unit as u64, cparams.inverse as u64];
+        let twiddles = shared_table("x86_sse_radix2_kernel3", &params, || {
+            let full_circle = if cparams.inverse { 2 } else { -2 };
+            range_step(0, cparams.unit, 2)
+                .map(|i| {
+                    let k = i / 4 * 4;
+                    let c1 = twiddle::<f32>(full_circle * k as isize, cparams.radix * cparams.unit);
+                    let c2 = twiddle::<f32>(
+                        full_circle * (k + 1) as isize,
+                        cparams.radix * cparams.unit,
+                    );
+                    let c3 = twiddle::<f32>(
+                        full_circle * (k + 2) as isize,
+                        cparams.radix * cparams.unit,
+                    );
+                    let c4 = twiddle::<f32>(
+                        full_circle * (k + 3) as isize,
+                        cparams.radix * cparams.unit,
+                    );
+                    // rrrr-iiii format
+                    // TODO: more efficient creation
+                    if i % 4 != 0 {
+                        f32x4::new(c1.im, c2.im, c3.im, c4.im)
+                    } else {
+                        f32x4::new(c1.re, c2.re, c3.re, c4.re)
+                    }
+                })
+                .collect()
+        });
 
         Self {
             cparams: *cparams,
@@ -258,7 +264,7 @@ impl<T: StaticParams> SseRadix2Kernel3<T> {
 }
 
 impl<T: StaticParams> AlignReqKernel<f32> for SseRadix2Kernel3<T> {
-    fn transform<I: AlignInfo>(&self, params: &mut KernelParams<f32>) {
+    fn transform<I: AlignInfo>(&self, params: &mut KernelParams<f32>, io: KernelIo<f32, I>) {
         let cparams = &self.cparams;
         let sparams = &self.sparams;
         let mut data = unsafe { SliceAccessor::new(&mut params.coefs[0..cparams.size * 2]) };
@@ -277,10 +283,10 @@ impl<T: StaticParams> AlignReqKernel<f32> for SseRadix2Kernel3<T> {
                 let twiddle_r = twiddles[y * 2];
                 let twiddle_i = twiddles[y * 2 + 1];
 
-                let x1a = unsafe { I::read(cur1a) };
-                let x1b = unsafe { I::read(cur1b) };
-                let y1a = unsafe { I::read(cur2a) };
-                let y1b = unsafe { I::read(cur2b) };
+                let x1a = unsafe { io.read(cur1a) };
+                let x1b = unsafe { io.read(cur1b) };
+                let y1a = unsafe { io.read(cur2a) };
+                let y1b = unsafe { io.read(cur2b) };
 
                 // convert riri-riri to rrrr-iiii (shufps)
                 let x2r = shuffle!(x1a, x1b, [0, 2, 4, 6]);
@@ -328,14 +334,20 @@ impl<T: StaticParams> AlignReqKernel<f32> for SseRadix2Kernel3<T> {
                 let y6a = shuffle!(y5r, y5i, [0, 4, 1, 5]);
                 let y6b = shuffle!(y5r, y5i, [2, 6, 3, 7]);
 
-                unsafe { I::write(cur1a, x6a) };
-                unsafe { I::write(cur1b, x6b) };
-                unsafe { I::write(cur2a, y6a) };
-                unsafe { I::write(cur2b, y6b) };
+                unsafe { io.write(cur1a, x6a) };
+                unsafe { io.write(cur1b, x6b) };
+                unsafe { io.write(cur2a, y6a) };
+                unsafe { io.write(cur2b, y6b) };
             }
         }
     }
     fn alignment_requirement(&self) -> usize {
         16
     }
+    fn backend(&self) -> Backend {
+        Backend::Sse
+    }
+    fn table_size(&self) -> usize {
+        mem::size_of_val(&self.twiddles[..])
+    }
 }
@@ -7,21 +7,24 @@
 //! all optimizations and instruction sets including ones that this kernel doesn't support enabled) on a Skylake
 //! machine.
 
-use super::super::super::simdutils::{f32x4_bitxor, f32x4_complex_mul_rrii};
-use super::utils::{
-    branch_on_static_params, if_compatible, AlignInfo, AlignReqKernel, AlignReqKernelWrapper,
-    StaticParams, StaticParamsConsumer,
-};
-use super::{Kernel, KernelCreationParams, KernelParams, KernelType, Num, SliceAccessor};
-
-use num_complex::Complex;
-use num_iter::range_step;
+use super::super::super::simdutils::{f32x4, f32x4_bitxor, f32x4_complex_mul_rrii};
+use super::super::staticparams::{branch_on_static_params, StaticParams, StaticParamsConsumer};
+use super::super::tables::shared_table;
+use super::super::twiddle::twiddle;
+use super::alignreq::{if_compatible, AlignInfo, AlignReqKernel, AlignReqKernelWrapper, KernelIo};
+use super::{Backend, Kernel, KernelCreationParams, KernelParams, KernelType, Num, SliceAccessor};
 
-use packed_simd::f32x4;
+use num_iter::range_step;
 
-use std::f32;
+use alloc::boxed::Box;
+use alloc::sync::Arc;
+use alloc::vec::Vec;
+use core::{mem, ptr};
 
-pub fn new_x86_sse_radix4_kernel<T>(cparams: &KernelCreationParams) -> Option<Box<Kernel<T>>>
+pub fn new_x86_sse_radix4_kernel<T>(
+    cparams: &KernelCreationParams,
+    scale: f64,
+) -> Option<Box<Kernel<T>>>
 where
     T: Num,
 {
@@ -29,10 +32,12 @@ where
         return None;
     }
 
-    if_compatible(|| branch_on_static_params(cparams, Factory {}))
+    if_compatible(|| branch_on_static_params(cparams, Factory { scale }))
 }
 
-struct Factory {}
+struct Factory {
+    scale: f64,
+}
 impl StaticParamsConsumer<Option<Box<Kernel<f32>>>> for Factory {
     fn consume<T>(self, cparams: &KernelCreationParams, sparams: T) -> Option<Box<Kernel<f32>>>
     where
@@ -46,7 +51,9 @@ impl StaticParamsConsumer<Option<Box<Kernel<f32>>>> for Factory {
                 SseRadix4Kernel2::new(cparams, sparams),
             ))),
             1 => Some(Box::new(AlignReqKernelWrapper::new(SseRadix4Kernel1::new(
-                cparams, sparams,
+                cparams,
+                sparams,
+                self.scale as f32,
             )))),
             _ => None,
         }
@@ -58,22 +65,25 @@ impl StaticParamsConsumer<Option<Box<Kernel<f32>>>> for Factory {
 struct SseRadix4Kernel1<T> {
     cparams: KernelCreationParams,
     sparams: T,
+    /// The factor every output is multiplied by.
+    scale: f32,
 }
 
 impl<T: StaticParams> SseRadix4Kernel1<T> {
-    fn new(cparams: &KernelCreationParams, sparams: T) -> Self {
+    fn new(cparams: &KernelCreationParams, sparams: T, scale: f32) -> Self {
         sparams.check_param(cparams);
         assert_eq!(cparams.radix, 4);
         assert_eq!(cparams.unit, 1);
         Self {
             cparams: *cparams,
             sparams: sparams,
+            scale,
         }
     }
 }
 
 impl<T: StaticParams> AlignReqKernel<f32> for SseRadix4Kernel1<T> {
-    fn transform<I: AlignInfo>(&self, params: &mut KernelParams<f32>) {
+    fn transform<I: AlignInfo>(&self, params: &mut KernelParams<f32>, io: KernelIo<f32, I>) {
         let cparams = &self.cparams;
         let sparams = &self.sparams;
         let mut data = unsafe { SliceAccessor::new(&mut params.coefs[0..cparams.size * 2]) };
@@ -83,15 +93,16 @@ impl<T: StaticParams> AlignReqKernel<f32> for SseRadix4Kernel1<T> {
         } else {
             [0, 0, 0, 0x80000000]
         };
-        let neg_mask = unsafe { *(&neg_mask_raw as *const u32 as *const f32x4) };
+        let neg_mask = unsafe { ptr::read_unaligned(&neg_mask_raw as *const u32 as *const f32x4) };
+        let scale = f32x4::splat(self.scale);
 
         for x in range_step(0, cparams.size * 2, 8) {
             let cur1 = &mut data[x] as *mut f32 as *mut f32x4;
             let cur2 = &mut data[x + 4] as *mut f32 as *mut f32x4;
 
             // riri format
-            let x1 = unsafe { I::read(cur1) };
-            let y1 = unsafe { I::read(cur2) };
+            let x1 = unsafe { io.read(cur1) };
+            let y1 = unsafe { io.read(cur2) };
 
             // perform size-4 small FFT (see generic2.rs for human-readable code)
             let t_1_2 = x1 + y1;
@@ -101,23 +112,26 @@ impl<T: StaticParams> AlignReqKernel<f32> for SseRadix4Kernel1<T> {
 
             // multiply the last elem (t4) by I (backward) or -I (forward)
             let t_2_4 = f32x4_bitxor(t_2_4t, neg_mask);
-            let x2 = t_1_3 + t_2_4;
-            let y2 = t_1_3 - t_2_4;
+            let x2 = (t_1_3 + t_2_4) * scale;
+            let y2 = (t_1_3 - t_2_4) * scale;
 
-            unsafe { I::write(cur1, x2) };
-            unsafe { I::write(cur2, y2) };
+            unsafe { io.write(cur1, x2) };
+            unsafe { io.write(cur2, y2) };
         }
     }
     fn alignment_requirement(&self) -> usize {
         16
     }
+    fn backend(&self) -> Backend {
+        Backend::Sse
+    }
 }
 
 /// This Radix-4 kernel computes two small FFTs in a single iteration.
 #[derive(Debug)]
 struct SseRadix4Kernel2<T> {
     cparams: KernelCreationParams,
-    twiddles: Vec<f32x4>,
+    twiddles: Arc<Vec<f32x4>>,
     sparams: T,
 }
 
@@ -127,31 +141,27 @@ impl<T: StaticParams> SseRadix4Kernel2<T> {
         assert_eq!(cparams.radix, 4);
         assert_eq!(cparams.unit % 2, 0);
 
-        let full_circle = if cparams.inverse { 2f32 } else { -2f32 };
-        let mut twiddles = Vec::new();
-        for i in range_step(0, cparams.unit, 2) {
-            let c1 = Complex::new(
-                0f32,
-                full_circle * (i) as f32 / (cparams.radix * cparams.unit) as f32 * f32::consts::PI,
-            )
-            .exp();
-            let c2 = Complex::new(
-                0f32,
-                full_circle * (i + 1) as f32 / (cparams.radix * cparams.unit) as f32
-                    * f32::consts::PI,
-            )
-            .exp();
-            // rr-ii format
-            twiddles.push(f32x4::new(c1.re, c2.re, c1.im, c2.im));
-
-            let c12 = c1 * c1;
-            let c22 = c2 * c2;
-            twiddles.push(f32x4::new(c12.re, c22.re, c12.im, c22.im));
-
-            let c13 = c12 * c1;
-            let c23 = c22 * c2;
-            twiddles.push(f32x4::new(c13.re, c23.re, c13.im, c23.im));
-        }
+        let params = [cparams.unit as u64, cparams.inverse as u64];
+        let twiddles = shared_table("x86_sse_radix4_kernel2", &params, || {
+            let full_circle = if cparams.inverse { 2 } else { -2 };
+            let mut twiddles = Vec::new();
+            for i in range_step(0, cparams.unit, 2) {
+                let c1 = twiddle::<f32>(full_circle * i as isize, cparams.radix * cparams.unit);
+                let c2 =
+                    twiddle::<f32>(full_circle * (i + 1) as isize, cparams.radix * cparams.unit);
+                // rr-ii format
+                twiddles.push(f32x4::new(c1.re, c2.re, c1.im, c2.im));
+
+                let c12 = c1 * c1;
+                let c22 = c2 * c2;
+                twiddles.push(f32x4::new(c12.re, c22.re, c12.im, c22.im));
+
+                let c13 = c12 * c1;
+                let c23 = c22 * c2;
+                twiddles.push(f32x4::new(c13.re, c23.re, c13.im, c23.im));
+            }
+            twiddles
+        });
 
         Self {
             cparams: *cparams,
@@ -162,7 +172,7 @@ impl<T: StaticParams> SseRadix4Kernel2<T> {
 }
 
 impl<T: StaticParams> AlignReqKernel<f32> for SseRadix4Kernel2<T> {
-    fn transform<I: AlignInfo>(&self, params: &mut KernelParams<f32>) {
+    fn transform<I: AlignInfo>(&self, params: &mut KernelParams<f32>, io: KernelIo<f32, I>) {
         let cparams = &self.cparams;
         let sparams = &self.sparams;
         let mut data = unsafe { SliceAccessor::new(&mut params.coefs[0..cparams.size * 2]) };
@@ -170,10 +180,11 @@ impl<T: StaticParams> AlignReqKernel<f32> for SseRadix4Kernel2<T> {
         let twiddles = unsafe { SliceAccessor::new(self.twiddles.as_slice()) };
 
         let neg_mask_raw: [u32; 4] = [0x80000000, 0x80000000, 0, 0];
-        let neg_mask = unsafe { *(&neg_mask_raw as *const u32 as *const f32x4) };
+        let neg_mask = unsafe { ptr::read_unaligned(&neg_mask_raw as *const u32 as *const f32x4) };
 
         let neg_mask2_raw: [u32; 4] = [0x80000000, 0, 0x80000000, 0];
-        let neg_mask2 = unsafe { *(&neg_mask2_raw as *const u32 as *const f32x4) };
+        let neg_mask2 =
+            unsafe { ptr::read_unaligned(&neg_mask2_raw as *const u32 as *const f32x4) };
 
         let pre_twiddle = sparams.kernel_type() == KernelType::Dit;
         let post_twiddle = sparams.kernel_type() == KernelType::Dif;
@@ -191,10 +202,10 @@ impl<T: StaticParams> AlignReqKernel<f32> for SseRadix4Kernel2<T> {
                 let twiddle_3 = twiddles[y * 3 + 2];
 
                 // riri format
-                let x1 = unsafe { I::read(cur1) };
-                let y1 = unsafe { I::read(cur2) };
-                let z1 = unsafe { I::read(cur3) };
-                let w1 = unsafe { I::read(cur4) };
+                let x1 = unsafe { io.read(cur1) };
+                let y1 = unsafe { io.read(cur2) };
+                let z1 = unsafe { io.read(cur3) };
+                let w1 = unsafe { io.read(cur4) };
 
                 // apply twiddle factor
                 let x2 = x1;
@@ -259,23 +270,29 @@ impl<T: StaticParams> AlignReqKernel<f32> for SseRadix4Kernel2<T> {
                     w4
                 };
 
-                unsafe { I::write(cur1, x5) };
-                unsafe { I::write(cur2, y5) };
-                unsafe { I::write(cur3, z5) };
-                unsafe { I::write(cur4, w5) };
+                unsafe { io.write(cur1, x5) };
+                unsafe { io.write(cur2, y5) };
+                unsafe { io.write(cur3, z5) };
+                unsafe { io.write(cur4, w5) };
             }
         }
     }
     fn alignment_requirement(&self) -> usize {
         16
     }
+    fn backend(&self) -> Backend {
+        Backend::Sse
+    }
+    fn table_size(&self) -> usize {
+        mem::size_of_val(&self.twiddles[..])
+    }
 }
 
 /// This Radix-4 kernel computes four small FFTs in a single iteration.
 #[derive(Debug)]
 struct SseRadix4Kernel3<T: StaticParams> {
     cparams: KernelCreationParams,
-    twiddles: Vec<f32x4>,
+    twiddles: Arc<Vec<f32x4>>,
     sparams: T,
 }
 
@@ -285,50 +302,38 @@ impl<T: StaticParams> SseRadix4Kernel3<T> {
         assert_eq!(cparams.radix, 4);
         assert_eq!(cparams.unit % 4, 0);
 
-        let full_circle = if cparams.inverse { 2f32 } else { -2f32 };
-        let mut twiddles = Vec::new();
-        for i in range_step(0, cparams.unit, 4) {
-            let c1 = Complex::new(
-                0f32,
-                full_circle * (i) as f32 / (cparams.radix * cparams.unit) as f32 * f32::consts::PI,
-            )
-            .exp();
-            let c2 = Complex::new(
-                0f32,
-                full_circle * (i + 1) as f32 / (cparams.radix * cparams.unit) as f32
-                    * f32::consts::PI,
-            )
-            .exp();
-            let c3 = Complex::new(
-                0f32,
-                full_circle * (i + 2) as f32 / (cparams.radix * cparams.unit) as f32
-                    * f32::consts::PI,
-            )
-            .exp();
-            let c4 = Complex::new(
-                0f32,
-                full_circle * (i + 3) as f32 / (cparams.radix * cparams.unit) as f32
-                    * f32::consts::PI,
-            )
-            .exp();
-            // rrrr-iiii format
-            twiddles.push(f32x4::new(c1.re, c2.re, c3.re, c4.re));
-            twiddles.push(f32x4::new(c1.im, c2.im, c3.im, c4.im));
-
-            let c12 = c1 * c1;
-            let c22 = c2 * c2;
-            let c32 = c3 * c3;
-            let c42 = c4 * c4;
-            twiddles.push(f32x4::new(c12.re, c22.re, c32.re, c42.re));
-            twiddles.push(f32x4::new(c12.im, c22.im, c32.im, c42.im));
-
-            let c13 = c12 * c1;
-            let c23 = c22 * c2;
-            let c33 = c32 * c3;
-            let c43 = c42 * c4;
-            twiddles.push(f32x4::new(c13.re, c23.re, c33.re, c43.re));
-            twiddles.push(f32x4::new(c13.im, c23.im, c33.im, c43.im));
-        }
+        let params = [cparams.unit as u64, cparams.inverse as u64];
+        let twiddles = shared_table("x86_sse_radix4_kernel3", &params, || {
+            let full_circle = if cparams.inverse { 2 } else { -2 };
+            let mut twiddles = Vec::new();
+            for i in range_step(0, cparams.unit, 4) {
+                let c1 = twiddle::<f32>(full_circle * i as isize, cparams.radix * cparams.unit);
+                let c2 =
+                    twiddle::<f32>(full_circle * (i + 1) as isize, cparams.radix * cparams.unit);
+                let c3 =
+                    twiddle::<f32>(full_circle * (i + 2) as isize, cparams.radix * cparams.unit);
+                let c4 =
+                    twiddle::<f32>(full_circle * (i + 3) as isize, cparams.radix * cparams.unit);
+                // rrrr-iiii format
+                twiddles.push(f32x4::new(c1.re, c2.re, c3.re, c4.re));
+                twiddles.push(f32x4::new(c1.im, c2.im, c3.im, c4.im));
+
+                let c12 = c1 * c1;
+                let c22 = c2 * c2;
+                let c32 = c3 * c3;
+                let c42 = c4 * c4;
+                twiddles.push(f32x4::new(c12.re, c22.re, c32.re, c42.re));
+                twiddles.push(f32x4::new(c12.im, c22.im, c32.im, c42.im));
+
+                let c13 = c12 * c1;
+                let c23 = c22 * c2;
+                let c33 = c32 * c3;
+                let c43 = c42 * c4;
+                twiddles.push(f32x4::new(c13.re, c23.re, c33.re, c43.re));
+                twiddles.push(f32x4::new(c13.im, c23.im, c33.im, c43.im));
+            }
+            twiddles
+        });
 
         Self {
             cparams: *cparams,
@@ -339,7 +344,7 @@ impl<T: StaticParams> SseRadix4Kernel3<T> {
 }
 
 impl<T: StaticParams> AlignReqKernel<f32> for SseRadix4Kernel3<T> {
-    fn transform<I: AlignInfo>(&self, params: &mut KernelParams<f32>) {
+    fn transform<I: AlignInfo>(&self, params: &mut KernelParams<f32>, io: KernelIo<f32, I>) {
         let cparams = &self.cparams;
         let sparams = &self.sparams;
         let mut data = unsafe { SliceAccessor::new(&mut params.coefs[0..cparams.size * 2]) };
@@ -365,14 +370,14 @@ impl<T: StaticParams> AlignReqKernel<f32> for SseRadix4Kernel3<T> {
                 let twiddle3_r = twiddles[y * 6 + 4];
                 let twiddle3_i = twiddles[y * 6 + 5];
 
-                let x1a = unsafe { I::read(cur1a) };
-                let x1b = unsafe { I::read(cur1b) };
-                let y1a = unsafe { I::read(cur2a) };
-                let y1b = unsafe { I::read(cur2b) };
-                let z1a = unsafe { I::read(cur3a) };
-                let z1b = unsafe { I::read(cur3b) };
-                let w1a = unsafe { I::read(cur4a) };
-                let w1b = unsafe { I::read(cur4b) };
+                let x1a = unsafe { io.read(cur1a) };
+                let x1b = unsafe { io.read(cur1b) };
+                let y1a = unsafe { io.read(cur2a) };
+                let y1b = unsafe { io.read(cur2b) };
+                let z1a = unsafe { io.read(cur3a) };
+                let z1b = unsafe { io.read(cur3b) };
+                let w1a = unsafe { io.read(cur4a) };
+                let w1b = unsafe { io.read(cur4b) };
 
                 // convert riri-riri to rrrr-iiii (shufps)
                 let x2r = shuffle!(x1a, x1b, [0, 2, 4, 6]);
@@ -482,18 +487,24 @@ impl<T: StaticParams> AlignReqKernel<f32> for SseRadix4Kernel3<T> {
                 let w7a = shuffle!(w6r, w6i, [0, 4, 1, 5]);
                 let w7b = shuffle!(w6r, w6i, [2, 6, 3, 7]);
 
-                unsafe { I::write(cur1a, x7a) };
-                unsafe { I::write(cur1b, x7b) };
-                unsafe { I::write(cur2a, y7a) };
-                unsafe { I::write(cur2b, y7b) };
-                unsafe { I::write(cur3a, z7a) };
-                unsafe { I::write(cur3b, z7b) };
-                unsafe { I::write(cur4a, w7a) };
-                unsafe { I::write(cur4b, w7b) };
+                unsafe { io.write(cur1a, x7a) };
+                unsafe { io.write(cur1b, x7b) };
+                unsafe { io.write(cur2a, y7a) };
+                unsafe { io.write(cur2b, y7b) };
+                unsafe { io.write(cur3a, z7a) };
+                unsafe { io.write(cur3b, z7b) };
+                unsafe { io.write(cur4a, w7a) };
+                unsafe { io.write(cur4b, w7b) };
             }
         }
     }
     fn alignment_requirement(&self) -> usize {
         16
     }
+    fn backend(&self) -> Backend {
+        Backend::Sse
+    }
+    fn table_size(&self) -> usize {
+        mem::size_of_val(&self.twiddles[..])
+    }
 }
@@ -1,20 +1,23 @@
-use super::utils::{if_compatible, AlignInfo, AlignReqKernel, AlignReqKernelWrapper};
-use super::{Kernel, KernelParams, SliceAccessor};
+use super::alignreq::{if_compatible, AlignInfo, AlignReqKernel, AlignReqKernelWrapper, KernelIo};
+use super::{Backend, Kernel, KernelParams, SliceAccessor};
 
+use alloc::boxed::Box;
+use alloc::sync::Arc;
+use core::mem;
+use core::ptr::read_unaligned;
 use num_iter::range_step;
-use packed_simd::{f32x4, u32x4};
-use std::f32;
-use std::mem;
-use std::ptr::{read_unaligned, write_unaligned};
 
 use aligned::AlignedVec;
-use simdutils::{f32x4_bitxor, f32x4_complex_mul_rrii};
-use {mul_pos_i, Complex, Num};
+use simdutils::{f32x4, f32x4_bitxor, f32x4_complex_mul_rrii, u32x4};
+use Num;
+
+use super::realfftcoefs::new_real_fft_coef_table;
 
 /// Creates a real FFT post-processing or backward real FFT pre-processing kernel.
 pub fn new_x86_sse_real_fft_pre_post_process_kernel<T>(
     len: usize,
     inverse: bool,
+    scale: f64,
 ) -> Option<Box<Kernel<T>>>
 where
     T: Num,
@@ -22,7 +25,7 @@ where
     if_compatible(|| {
         if len % 8 == 0 && len > 8 {
             Some(Box::new(AlignReqKernelWrapper::new(
-                SseRealFFTPrePostProcessKernel::new(len, inverse),
+                SseRealFFTPrePostProcessKernel::new(len, inverse, scale as f32),
             )) as Box<Kernel<f32>>)
         } else {
             None
@@ -30,61 +33,38 @@ where
     })
 }
 
-pub(super) fn new_real_fft_coef_table(len: usize, inverse: bool) -> [AlignedVec<f32>; 2] {
-    assert!(len % 2 == 0);
-    let mut table_a = AlignedVec::with_capacity(len);
-    let mut table_b = AlignedVec::with_capacity(len);
-    for i in 0..(len / 2) {
-        let c = Complex::new(0f32, (i as f32) * -f32::consts::PI / (len / 2) as f32).exp();
-
-        let a = (Complex::new(1f32, 0f32) - mul_pos_i(c)) * 0.5f32;
-        let b = (Complex::new(1f32, 0f32) + mul_pos_i(c)) * 0.5f32;
-        if inverse {
-            table_a.push(a.re);
-            table_a.push(-a.im);
-            table_b.push(b.re);
-            table_b.push(-b.im);
-        } else {
-            table_a.push(a.re);
-            table_a.push(a.im);
-            table_b.push(b.re);
-            table_b.push(b.im);
-        }
-    }
-    [table_a, table_b]
-}
-
 #[derive(Debug)]
 struct SseRealFFTPrePostProcessKernel {
     len: usize,
-    table: [AlignedVec<f32>; 2],
+    table: Arc<[AlignedVec<f32>; 2]>,
     inverse: bool,
+    scale: f32,
 }
 
 impl SseRealFFTPrePostProcessKernel {
-    fn new(len: usize, inverse: bool) -> Self {
+    fn new(len: usize, inverse: bool, scale: f32) -> Self {
         Self {
             len,
-            table: new_real_fft_coef_table(len, inverse),
+            table: new_real_fft_coef_table(len, inverse, scale),
             inverse,
+            scale,
         }
     }
 }
 
 impl AlignReqKernel<f32> for SseRealFFTPrePostProcessKernel {
-    fn transform<I: AlignInfo>(&self, params: &mut KernelParams<f32>) {
+    fn transform<I: AlignInfo>(&self, params: &mut KernelParams<f32>, io: KernelIo<f32, I>) {
         let mut data = unsafe { SliceAccessor::new(&mut params.coefs[0..self.len]) };
         let table_a = unsafe { SliceAccessor::new(&self.table[0][..]) };
         let table_b = unsafe { SliceAccessor::new(&self.table[1][..]) };
         let len_2 = self.len / 2;
+        let (x1, x2) = unsafe { (io.read_unaligned(&data[0]), io.read_unaligned(&data[1])) };
         if !self.inverse {
-            let (x1, x2) = (data[0], data[1]);
-            data[0] = x1 + x2;
-            data[1] = x1 - x2;
+            data[0] = (x1 + x2) * self.scale;
+            data[1] = (x1 - x2) * self.scale;
         } else {
-            let (x1, x2) = (data[0], data[1]);
-            data[0] = (x1 + x2) * 0.5f32;
-            data[1] = (x1 - x2) * 0.5f32;
+            data[0] = (x1 + x2) * (0.5f32 * self.scale);
+            data[1] = (x1 - x2) * (0.5f32 * self.scale);
         }
 
         let neg_mask: f32x4 = unsafe { mem::transmute(u32x4::new(0x80000000, 0x80000000, 0, 0)) };
@@ -93,14 +73,14 @@ impl AlignReqKernel<f32> for SseRealFFTPrePostProcessKernel {
             let cur1 = &mut data[i * 2] as *mut f32 as *mut f32x4;
             let cur2 = &mut data[(len_2 - i - 1) * 2] as *mut f32 as *mut f32x4;
 
-            let a_p1 = &table_a[i * 2] as *const f32 as *const f32x4;
-            let a_p2 = &table_a[(len_2 - i - 1) * 2] as *const f32 as *const f32x4;
-            let b_p1 = &table_b[i * 2] as *const f32 as *const f32x4;
-            let b_p2 = &table_b[(len_2 - i - 1) * 2] as *const f32 as *const f32x4;
+            let a_p1 = table_a.ptr_at::<f32x4>(i * 2);
+            let a_p2 = table_a.ptr_at::<f32x4>((len_2 - i - 1) * 2);
+            let b_p1 = table_b.ptr_at::<f32x4>(i * 2);
+            let b_p2 = table_b.ptr_at::<f32x4>((len_2 - i - 1) * 2);
 
             // riri
-            let x1 = unsafe { read_unaligned(cur1) };
-            let x2 = unsafe { I::read(cur2) };
+            let x1 = unsafe { io.read_unaligned(cur1) };
+            let x2 = unsafe { io.read(cur2) };
             let a1i = unsafe { read_unaligned(a_p1) };
             let a2i = unsafe { *a_p2 };
             let b1i = unsafe { read_unaligned(b_p1) };
@@ -129,12 +109,18 @@ impl AlignReqKernel<f32> for SseRealFFTPrePostProcessKernel {
             let y2 = shuffle!(g2, g2, [0, 2, 5, 7]);
 
             unsafe {
-                write_unaligned(cur1, y1);
-                I::write(cur2, y2);
+                io.write_unaligned(cur1, y1);
+                io.write(cur2, y2);
             }
         }
     }
     fn alignment_requirement(&self) -> usize {
         16
     }
+    fn backend(&self) -> Backend {
+        Backend::Sse
+    }
+    fn table_size(&self) -> usize {
+        mem::size_of_val(&self.table[0][..]) + mem::size_of_val(&self.table[1][..])
+    }
 }
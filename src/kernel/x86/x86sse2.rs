@@ -6,6 +6,7 @@
 //! Yet to be measured.
 
 use super::{Kernel, KernelCreationParams, Num};
+use alloc::boxed::Box;
 
 pub fn new_x86_sse2_kernel<T>(_: &KernelCreationParams) -> Option<Box<Kernel<T>>>
 where
@@ -11,19 +11,19 @@
 //!
 //! For small transforms ties with a commercial-level FFT library, but tends to be much slower for large transforms.
 
-use super::super::super::simdutils::{f32x4_bitxor, sse3_f32x4_complex_mul_riri};
-use super::utils::{
-    branch_on_static_params, if_compatible, AlignInfo, AlignReqKernel, AlignReqKernelWrapper,
-    StaticParams, StaticParamsConsumer,
-};
-use super::{Kernel, KernelCreationParams, KernelParams, KernelType, Num, SliceAccessor};
-
-use num_complex::Complex;
-use num_iter::range_step;
+use super::super::super::simdutils::{f32x4, f32x4_bitxor, sse3_f32x4_complex_mul_riri};
+use super::super::staticparams::{branch_on_static_params, StaticParams, StaticParamsConsumer};
+use super::super::tables::shared_table;
+use super::super::twiddle::twiddle;
+use super::alignreq::{if_compatible, AlignInfo, AlignReqKernel, AlignReqKernelWrapper, KernelIo};
+use super::{Backend, Kernel, KernelCreationParams, KernelParams, KernelType, Num, SliceAccessor};
 
-use packed_simd::f32x4;
+use num_iter::range_step;
 
-use std::f32;
+use alloc::boxed::Box;
+use alloc::sync::Arc;
+use alloc::vec::Vec;
+use core::{mem, ptr};
 
 pub fn new_x86_sse3_f32_radix4_kernel<T>(cparams: &KernelCreationParams) -> Option<Box<Kernel<T>>>
 where
@@ -57,7 +57,7 @@ impl StaticParamsConsumer<Option<Box<Kernel<f32>>>> for Factory {
 #[derive(Debug)]
 struct Sse3Radix4Kernel2<T> {
     cparams: KernelCreationParams,
-    twiddles: Vec<f32x4>,
+    twiddles: Arc<Vec<f32x4>>,
     sparams: T,
 }
 
@@ -67,31 +67,27 @@ impl<T: StaticParams> Sse3Radix4Kernel2<T> {
         assert_eq!(cparams.radix, 4);
         assert_eq!(cparams.unit % 2, 0);
 
-        let full_circle = if cparams.inverse { 2f32 } else { -2f32 };
-        let mut twiddles = Vec::new();
-        for i in range_step(0, cparams.unit, 2) {
-            let c1 = Complex::new(
-                0f32,
-                full_circle * (i) as f32 / (cparams.radix * cparams.unit) as f32 * f32::consts::PI,
-            )
-            .exp();
-            let c2 = Complex::new(
-                0f32,
-                full_circle * (i + 1) as f32 / (cparams.radix * cparams.unit) as f32
-                    * f32::consts::PI,
-            )
-            .exp();
-            // riri format
-            twiddles.push(f32x4::new(c1.re, c1.im, c2.re, c2.im));
-
-            let c12 = c1 * c1;
-            let c22 = c2 * c2;
-            twiddles.push(f32x4::new(c12.re, c12.im, c22.re, c22.im));
-
-            let c13 = c12 * c1;
-            let c23 = c22 * c2;
-            twiddles.push(f32x4::new(c13.re, c13.im, c23.re, c23.im));
-        }
+        let params = [cparams.unit as u64, cparams.inverse as u64];
+        let twiddles = shared_table("x86_sse3_radix4_kernel2", &params, || {
+            let full_circle = if cparams.inverse { 2 } else { -2 };
+            let mut twiddles = Vec::new();
+            for i in range_step(0, cparams.unit, 2) {
+                let c1 = twiddle::<f32>(full_circle * i as isize, cparams.radix * cparams.unit);
+                let c2 =
+                    twiddle::<f32>(full_circle * (i + 1) as isize, cparams.radix * cparams.unit);
+                // riri format
+                twiddles.push(f32x4::new(c1.re, c1.im, c2.re, c2.im));
+
+                let c12 = c1 * c1;
+                let c22 = c2 * c2;
+                twiddles.push(f32x4::new(c12.re, c12.im, c22.re, c22.im));
+
+                let c13 = c12 * c1;
+                let c23 = c22 * c2;
+                twiddles.push(f32x4::new(c13.re, c13.im, c23.re, c23.im));
+            }
+            twiddles
+        });
 
         Self {
             cparams: *cparams,
@@ -102,7 +98,7 @@ impl<T: StaticParams> Sse3Radix4Kernel2<T> {
 }
 
 impl<T: StaticParams> AlignReqKernel<f32> for Sse3Radix4Kernel2<T> {
-    fn transform<I: AlignInfo>(&self, params: &mut KernelParams<f32>) {
+    fn transform<I: AlignInfo>(&self, params: &mut KernelParams<f32>, io: KernelIo<f32, I>) {
         let cparams = &self.cparams;
         let sparams = &self.sparams;
         let mut data = unsafe { SliceAccessor::new(&mut params.coefs[0..cparams.size * 2]) };
@@ -110,7 +106,8 @@ impl<T: StaticParams> AlignReqKernel<f32> for Sse3Radix4Kernel2<T> {
         let twiddles = unsafe { SliceAccessor::new(self.twiddles.as_slice()) };
 
         let neg_mask2_raw: [u32; 4] = [0x80000000, 0, 0x80000000, 0];
-        let neg_mask2 = unsafe { *(&neg_mask2_raw as *const u32 as *const f32x4) };
+        let neg_mask2 =
+            unsafe { ptr::read_unaligned(&neg_mask2_raw as *const u32 as *const f32x4) };
 
         let pre_twiddle = sparams.kernel_type() == KernelType::Dit;
         let post_twiddle = sparams.kernel_type() == KernelType::Dif;
@@ -128,10 +125,10 @@ impl<T: StaticParams> AlignReqKernel<f32> for Sse3Radix4Kernel2<T> {
                 let twiddle_3 = twiddles[y * 3 + 2];
 
                 // riri format
-                let x1 = unsafe { I::read(cur1) };
-                let y1 = unsafe { I::read(cur2) };
-                let z1 = unsafe { I::read(cur3) };
-                let w1 = unsafe { I::read(cur4) };
+                let x1 = unsafe { io.read(cur1) };
+                let y1 = unsafe { io.read(cur2) };
+                let z1 = unsafe { io.read(cur3) };
+                let w1 = unsafe { io.read(cur4) };
 
                 // apply twiddle factor
                 let x2 = x1;
@@ -184,14 +181,20 @@ impl<T: StaticParams> AlignReqKernel<f32> for Sse3Radix4Kernel2<T> {
                     w4
                 };
 
-                unsafe { I::write(cur1, x5) };
-                unsafe { I::write(cur2, y5) };
-                unsafe { I::write(cur3, z5) };
-                unsafe { I::write(cur4, w5) };
+                unsafe { io.write(cur1, x5) };
+                unsafe { io.write(cur2, y5) };
+                unsafe { io.write(cur3, z5) };
+                unsafe { io.write(cur4, w5) };
             }
         }
     }
     fn alignment_requirement(&self) -> usize {
         16
     }
+    fn backend(&self) -> Backend {
+        Backend::Sse3
+    }
+    fn table_size(&self) -> usize {
+        mem::size_of_val(&self.twiddles[..])
+    }
 }
@@ -1,22 +1,24 @@
-use super::utils::{if_compatible, AlignInfo, AlignReqKernel, AlignReqKernelWrapper};
-use super::{Kernel, KernelParams, SliceAccessor};
+use super::alignreq::{if_compatible, AlignInfo, AlignReqKernel, AlignReqKernelWrapper, KernelIo};
+use super::{Backend, Kernel, KernelParams, SliceAccessor};
 
+use alloc::boxed::Box;
+use alloc::sync::Arc;
+use core::f32;
+use core::mem;
+use core::ptr::read_unaligned;
 use num_iter::range_step;
-use packed_simd::{f32x4, u32x4};
-use std::f32;
-use std::mem;
-use std::ptr::{read_unaligned, write_unaligned};
 
 use aligned::AlignedVec;
-use simdutils::{f32x4_bitxor, sse3_f32x4_complex_mul_riri};
+use simdutils::{f32x4, f32x4_bitxor, sse3_f32x4_complex_mul_riri, u32x4};
 use Num;
 
-use super::x86sse1realfft::new_real_fft_coef_table;
+use super::realfftcoefs::new_real_fft_coef_table;
 
 /// Creates a real FFT post-processing or backward real FFT pre-processing kernel.
 pub fn new_x86_sse3_f32_real_fft_pre_post_process_kernel<T>(
     len: usize,
     inverse: bool,
+    scale: f64,
 ) -> Option<Box<Kernel<T>>>
 where
     T: Num,
@@ -24,7 +26,7 @@ where
     if_compatible(|| {
         if len % 8 == 0 && len > 8 {
             Some(Box::new(AlignReqKernelWrapper::new(
-                Sse3F32RealFFTPrePostProcessKernel::new(len, inverse),
+                Sse3F32RealFFTPrePostProcessKernel::new(len, inverse, scale as f32),
             )) as Box<Kernel<f32>>)
         } else {
             None
@@ -35,34 +37,35 @@ where
 #[derive(Debug)]
 struct Sse3F32RealFFTPrePostProcessKernel {
     len: usize,
-    table: [AlignedVec<f32>; 2],
+    table: Arc<[AlignedVec<f32>; 2]>,
     inverse: bool,
+    scale: f32,
 }
 
 impl Sse3F32RealFFTPrePostProcessKernel {
-    fn new(len: usize, inverse: bool) -> Self {
+    fn new(len: usize, inverse: bool, scale: f32) -> Self {
         Self {
             len,
-            table: new_real_fft_coef_table(len, inverse),
+            table: new_real_fft_coef_table(len, inverse, scale),
             inverse,
+            scale,
         }
     }
 }
 
 impl AlignReqKernel<f32> for Sse3F32RealFFTPrePostProcessKernel {
-    fn transform<I: AlignInfo>(&self, params: &mut KernelParams<f32>) {
+    fn transform<I: AlignInfo>(&self, params: &mut KernelParams<f32>, io: KernelIo<f32, I>) {
         let mut data = unsafe { SliceAccessor::new(&mut params.coefs[0..self.len]) };
         let table_a = unsafe { SliceAccessor::new(&self.table[0][..]) };
         let table_b = unsafe { SliceAccessor::new(&self.table[1][..]) };
         let len_2 = self.len / 2;
+        let (x1, x2) = unsafe { (io.read_unaligned(&data[0]), io.read_unaligned(&data[1])) };
         if !self.inverse {
-            let (x1, x2) = (data[0], data[1]);
-            data[0] = x1 + x2;
-            data[1] = x1 - x2;
+            data[0] = (x1 + x2) * self.scale;
+            data[1] = (x1 - x2) * self.scale;
         } else {
-            let (x1, x2) = (data[0], data[1]);
-            data[0] = (x1 + x2) * 0.5f32;
-            data[1] = (x1 - x2) * 0.5f32;
+            data[0] = (x1 + x2) * (0.5f32 * self.scale);
+            data[1] = (x1 - x2) * (0.5f32 * self.scale);
         }
 
         let conj_mask: f32x4 = unsafe { mem::transmute(u32x4::new(0, 0x80000000, 0, 0x80000000)) };
@@ -71,14 +74,14 @@ impl AlignReqKernel<f32> for Sse3F32RealFFTPrePostProcessKernel {
             let cur1 = &mut data[i * 2] as *mut f32 as *mut f32x4;
             let cur2 = &mut data[(len_2 - i - 1) * 2] as *mut f32 as *mut f32x4;
 
-            let a_p1 = &table_a[i * 2] as *const f32 as *const f32x4;
-            let a_p2 = &table_a[(len_2 - i - 1) * 2] as *const f32 as *const f32x4;
-            let b_p1 = &table_b[i * 2] as *const f32 as *const f32x4;
-            let b_p2 = &table_b[(len_2 - i - 1) * 2] as *const f32 as *const f32x4;
+            let a_p1 = table_a.ptr_at::<f32x4>(i * 2);
+            let a_p2 = table_a.ptr_at::<f32x4>((len_2 - i - 1) * 2);
+            let b_p1 = table_b.ptr_at::<f32x4>(i * 2);
+            let b_p2 = table_b.ptr_at::<f32x4>((len_2 - i - 1) * 2);
 
             // riri
-            let x1 = unsafe { read_unaligned(cur1) };
-            let x2 = unsafe { I::read(cur2) };
+            let x1 = unsafe { io.read_unaligned(cur1) };
+            let x2 = unsafe { io.read(cur2) };
             let a1 = unsafe { read_unaligned(a_p1) };
             let a2 = unsafe { *a_p2 };
             let b1 = unsafe { read_unaligned(b_p1) };
@@ -93,12 +96,18 @@ impl AlignReqKernel<f32> for Sse3F32RealFFTPrePostProcessKernel {
             let g2 = sse3_f32x4_complex_mul_riri(x2, a2) + sse3_f32x4_complex_mul_riri(x1c, b2);
 
             unsafe {
-                write_unaligned(cur1, g1);
-                I::write(cur2, g2);
+                io.write_unaligned(cur1, g1);
+                io.write(cur2, g2);
             }
         }
     }
     fn alignment_requirement(&self) -> usize {
         16
     }
+    fn backend(&self) -> Backend {
+        Backend::Sse3
+    }
+    fn table_size(&self) -> usize {
+        mem::size_of_val(&self.table[0][..]) + mem::size_of_val(&self.table[1][..])
+    }
 }
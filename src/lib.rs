@@ -1,4 +1,4 @@
-//! Simple FFT library written purely in Rust. Requires a Nightly Rust compiler for x86 intrinsics.
+//! Simple FFT library written purely in Rust. Builds on stable Rust, using the `core::arch` intrinsics on x86.
 //!
 //! ![](docs/benchmark.jpg)
 //!
@@ -9,6 +9,17 @@
 //!  - Supports real-to-complex and complex-to-real transforms.
 //!  - Clients can opt in to a swizzled input/output data order when they don't
 //!    need naturally-ordered data.
+//!  - Can transform a single channel of an interleaved multichannel buffer.
+//!  - Can optionally choose the fastest kernel configuration by measuring the
+//!    alternatives (`Setup::new_measured`).
+//!  - Provides one-shot functions (`fft`, `ifft`, `rfft`, and `irfft`) that
+//!    reuse cached `Setup`s, for tools and prototypes.
+//!  - Can perform FFTs of every power-of-two length up to a maximum using a
+//!    single shared twiddle table (`MultiSizeSetup`).
+//!  - Can perform FFTs of a length fixed at compile time on arrays, with the
+//!    stage sequence derived at compile time (`StaticSetup`).
+//!  - Twiddle and permutation tables are shared between `Setup`s, so creating
+//!    many `Setup`s of the same size is cheap in time and memory.
 //!
 //! # Limitations
 //!
@@ -35,10 +46,161 @@
 //! Note: this causes codegen to generate VEX prefixes to all SSE instructions and makes the binary
 //! incompatible with processors without AVX support.
 //!
+//! # Checked Mode
+//!
+//! The `checked` feature makes the kernels bounds-check every memory access
+//! (even in release builds), including every lane of the SIMD vectors they
+//! load and store, and verify the alignment assumptions of the SIMD
+//! kernels. The `checked-finite` feature additionally asserts that no kernel
+//! produces a non-finite value (NaN or infinity) from a finite input. These
+//! features are intended for testing and come with a considerable performance
+//! penalty.
+//!
+//! # Deterministic Mode
+//!
+//! Setting `Options::deterministic` restricts the kernel selection to the
+//! generic kernels and makes the output bit-identical across machines (given
+//! IEEE 754 conforming arithmetic), which is useful for lockstep simulations.
+//! The SIMD kernels are not used in this mode, so it is considerably slower.
+//! The output may differ between versions of this library and between builds
+//! with different backend features.
+//!
+//! # Static Tables
+//!
+//! Enabling the `static-tables` feature makes the build script precompute the
+//! tables of the generic kernels for the lengths listed in the environment
+//! variable `YFFT_STATIC_TABLE_SIZES` (comma-separated powers of two, e.g.,
+//! `256,1024`). The tables are embedded in the binary as static arrays, and
+//! `Setup`s of those lengths borrow them instead of computing them at runtime.
+//! `YFFT_STATIC_TABLE_TYPES` selects the scalar types (`f32`, `f64`, or
+//! `f32,f64`; the default is `f32`).
+//!
+//! Only the twiddle factors of the radix-2/4 generic kernels, the bit reversal
+//! indices, and the real FFT coefficients for the built-in normalizations are
+//! precomputed. The tables of the SIMD kernels and the mixed-radix generic
+//! kernel are still computed at runtime, and `Setup` itself still allocates
+//! memory. With `Options::max_backend` set to `Backend::Generic2`, no table is
+//! computed at runtime for the listed lengths unless `Normalization::Custom`
+//! is used.
+//!
+//! # Backend Features
+//!
+//! The kernel families (see `Backend`) included in the library are selected by
+//! the cargo features `backend-generic`, `backend-generic2`, `backend-sse`,
+//! `backend-sse3`, and `backend-avx`, all of which are enabled by default.
+//! Excluding the unused ones reduces the binary size, e.g., for WebAssembly or
+//! embedded targets:
+//!
+//! ```toml
+//! [dependencies]
+//! yfft = { version = "...", default-features = false, features = ["std", "backend-generic2", "backend-sse"] }
+//! ```
+//!
+//! At least one of `backend-generic` and `backend-generic2` must be enabled.
+//! Without `backend-generic`, only the transforms whose underlying complex FFT
+//! has a power-of-two length are supported. `Setup::new` returns
+//! `PlanError::UnsupportedRadix` for the others, and when `max_backend` is
+//! `Backend::Generic`.
+//!
+//! # Custom Kernels
+//!
+//! A butterfly pass can be delegated to a custom kernel, e.g., a hand-tuned one
+//! or one calling a vendor library, by implementing `Kernel` and registering a
+//! factory with `register_kernel_factory`. `Setup`s created afterwards offer
+//! each butterfly pass (described by `KernelCreationParams`) to the factories
+//! before the built-in kernels unless `Options::max_backend` is lower than
+//! `Backend::Custom`. Supplying `Setup::with_plan` a `Plan` whose only radix is
+//! the length of the underlying complex FFT makes a single kernel compute the
+//! whole FFT. `validate_kernel` checks a kernel against a reference
+//! implementation:
+//!
+//! ```
+//! use std::f64::consts::PI;
+//! use yfft::*;
+//!
+//! /// Computes the whole FFT (`radix == size`) by the definition.
+//! #[derive(Debug)]
+//! struct MyKernel {
+//!     cparams: KernelCreationParams,
+//! }
+//!
+//! impl Kernel<f32> for MyKernel {
+//!     fn transform(&self, params: &mut KernelParams<f32>) {
+//!         let size = self.cparams.size;
+//!         let sign = if self.cparams.inverse { 1.0 } else { -1.0 };
+//!         let input = &mut params.work_area[0..size * 2];
+//!         input.copy_from_slice(&params.coefs[0..size * 2]);
+//!         for k in 0..size {
+//!             let (mut re, mut im) = (0.0f64, 0.0f64);
+//!             for j in 0..size {
+//!                 let theta = sign * 2.0 * PI * ((j * k) % size) as f64 / size as f64;
+//!                 let (x, y) = (input[j * 2] as f64, input[j * 2 + 1] as f64);
+//!                 re += x * theta.cos() - y * theta.sin();
+//!                 im += x * theta.sin() + y * theta.cos();
+//!             }
+//!             params.coefs[k * 2] = re as f32;
+//!             params.coefs[k * 2 + 1] = im as f32;
+//!         }
+//!     }
+//!
+//!     fn required_work_area_size(&self) -> usize {
+//!         self.cparams.size * 2
+//!     }
+//!
+//!     fn backend(&self) -> Backend {
+//!         Backend::Custom
+//!     }
+//! }
+//!
+//! let cparams = KernelCreationParams {
+//!     size: 64,
+//!     kernel_type: KernelType::Dit,
+//!     radix: 64,
+//!     unit: 1,
+//!     inverse: false,
+//! };
+//! if let Err(e) = validate_kernel(&MyKernel { cparams }, &cparams) {
+//!     panic!("MyKernel is broken: {}", e);
+//! }
+//!
+//! register_kernel_factory::<f32, _>(|cparams| {
+//!     if cparams.size == 64 && cparams.radix == 64 {
+//!         Some(Box::new(MyKernel { cparams: *cparams }))
+//!     } else {
+//!         None
+//!     }
+//! });
+//! ```
+//!
+//! # `no_std` Support
+//!
+//! The crate can be used without the standard library by disabling the default
+//! `std` feature and enabling the `libm` feature, which provides the math
+//! functions otherwise taken from `std`, along with the backends to include
+//! (see above):
+//!
+//! ```toml
+//! [dependencies]
+//! yfft = { version = "...", default-features = false, features = ["libm", "backend-generic2"] }
+//! ```
+//!
+//! The `alloc` crate is still required. The following items are only available
+//! with the `std` feature:
+//!
+//!  - `Setup::cached`, `fft`, `ifft`, `rfft`, `irfft`, and `clear_setup_cache`.
+//!  - `Wisdom`, `Setup::new_measured`, `Plan::measure`, and
+//!    `Setup::transform_concurrent`.
+//!  - `register_kernel_factory` and `clear_kernel_factories`.
+//!  - The `std::error::Error` implementations of the error types.
+//!
+//! Without `std`, `Setup::new` always uses the plan returned by
+//! `Plan::estimate`, and the precomputed tables are not shared between `Setup`s
+//! (see also the `static-tables` feature).
+//!
 //! # Example: Round-trip Conversion
 //!
 //! ```
-//! use yfft::{Setup, Options, DataOrder, DataFormat, Env};
+//! use yfft::{Setup, Options, DataOrder, DataFormat, Env, Normalization};
 //!
 //! let size = 128;
 //!
@@ -49,6 +211,9 @@
 //!     output_data_format: DataFormat::Complex,
 //!     len: size,
 //!     inverse: false,
+//!     normalization: Normalization::None,
+//!     max_backend: None,
+//!     deterministic: false,
 //! })
 //! .unwrap();
 //! let setup2: Setup<f32> = Setup::new(&Options {
@@ -58,6 +223,9 @@
 //!     output_data_format: DataFormat::Complex,
 //!     len: size,
 //!     inverse: true,
+//!     normalization: Normalization::ByLen,
+//!     max_backend: None,
+//!     deterministic: false,
 //! })
 //! .unwrap();
 //!
@@ -73,13 +241,10 @@
 //! let mut result = vec![0.0f32; size * 2];
 //! result.copy_from_slice(&pat);
 //!
-//! // Round-trip transform
-//! env1.transform(&mut result);
-//! env2.transform(&mut result);
-//!
-//! for e in &mut result {
-//!     *e = *e / size as f32;
-//! }
+//! // Round-trip transform. `setup2` scales the output by `1 / size`, so the
+//! // result is identical to the input.
+//! env1.transform(&mut result).unwrap();
+//! env2.transform(&mut result).unwrap();
 //!
 //! assert_num_slice_approx_eq(
 //!     &result,
@@ -113,27 +278,65 @@
 //! # }
 //! ```
 
-#![cfg_attr(test, feature(test))]
-#![feature(platform_intrinsics)]
+#![cfg_attr(not(any(feature = "std", test)), no_std)]
+
+#[cfg(not(any(feature = "std", feature = "libm")))]
+compile_error!("Either the `std` or `libm` feature must be enabled.");
+
+#[cfg(not(any(feature = "backend-generic", feature = "backend-generic2")))]
+compile_error!("Either the `backend-generic` or `backend-generic2` feature must be enabled.");
 
+#[macro_use]
+extern crate alloc;
+#[cfg(any(feature = "std", test))]
+extern crate core;
 extern crate num_complex;
 extern crate num_iter;
 extern crate num_traits;
 
-#[macro_use]
-extern crate packed_simd;
-
-use std::fmt::Debug;
-use std::ops::{AddAssign, DivAssign, MulAssign, SubAssign};
+use core::fmt::Debug;
+use core::ops::{AddAssign, DivAssign, MulAssign, SubAssign};
+use core::slice;
 
 use num_complex::Complex;
 
+// Only used by the x86 SIMD kernels, which are compiled if any of their
+// families is enabled (see `kernel::x86`)
+#[cfg(all(
+    any(target_arch = "x86", target_arch = "x86_64"),
+    target_feature = "sse",
+    any(
+        feature = "backend-sse",
+        all(target_feature = "sse3", feature = "backend-sse3"),
+        all(target_feature = "avx", feature = "backend-avx")
+    )
+))]
 #[macro_use]
 mod simdutils;
+#[cfg(all(
+    any(target_arch = "x86", target_arch = "x86_64"),
+    target_feature = "sse",
+    any(
+        feature = "backend-sse",
+        all(target_feature = "sse3", feature = "backend-sse3"),
+        all(target_feature = "avx", feature = "backend-avx")
+    )
+))]
 mod aligned;
+#[cfg(feature = "std")]
+mod cache;
 mod env;
 mod kernel;
+mod multisize;
+mod plan;
+mod report;
+#[cfg(feature = "std")]
+mod scratch;
 mod setup;
+mod staticsetup;
+mod transforms;
+#[cfg(feature = "std")]
+mod wisdom;
 
 pub trait Num:
     Clone
@@ -168,6 +371,7 @@ impl<T> Num for T where
 {
 }
 
+#[cfg(feature = "backend-generic")]
 #[inline]
 fn complex_from_slice<T: Num>(x: &[T]) -> Complex<T> {
     Complex::new(x[0], x[1])
@@ -178,5 +382,42 @@ fn mul_pos_i<T: Num>(x: Complex<T>) -> Complex<T> {
     Complex::new(-x.im, x.re)
 }
 
-pub use env::Env;
-pub use setup::{DataFormat, DataOrder, Options, PlanError, Setup};
+/// Reinterprets a slice of complex numbers as a slice of interleaved real and
+/// imaginary components.
+#[inline]
+fn complex_slice_as_scalars<T: Num>(x: &[Complex<T>]) -> &[T] {
+    // `Complex` is `#[repr(C)]`
+    unsafe { slice::from_raw_parts(x.as_ptr() as *const T, x.len() * 2) }
+}
+
+/// The mutable version of `complex_slice_as_scalars`.
+#[inline]
+fn complex_slice_as_scalars_mut<T: Num>(x: &mut [Complex<T>]) -> &mut [T] {
+    unsafe { slice::from_raw_parts_mut(x.as_mut_ptr() as *mut T, x.len() * 2) }
+}
+
+/// Reinterprets a slice of interleaved real and imaginary components as a
+/// slice of complex numbers. Panics if `x.len()` is odd.
+#[inline]
+fn scalars_as_complex_slice_mut<T: Num>(x: &mut [T]) -> &mut [Complex<T>] {
+    assert!(x.len() % 2 == 0);
+    unsafe { slice::from_raw_parts_mut(x.as_mut_ptr() as *mut Complex<T>, x.len() / 2) }
+}
+
+#[cfg(feature = "std")]
+pub use cache::{clear_setup_cache, fft, ifft, irfft, rfft};
+pub use env::{Env, StridedLayout, StridedSequence, TransformError};
+#[cfg(feature = "std")]
+pub use kernel::{clear_kernel_factories, register_kernel_factory};
+pub use kernel::{
+    validate_kernel, Backend, Kernel, KernelCreationParams, KernelParams, KernelType,
+    KernelValidationError,
+};
+pub use multisize::MultiSizeSetup;
+pub use plan::Plan;
+pub use report::{Decimation, PlanReport, StageKind, StageReport};
+pub use setup::{DataFormat, DataOrder, Normalization, Options, PlanError, Setup};
+pub use staticsetup::StaticSetup;
+pub use transforms::{ComplexFft, RealForwardFft, RealInverseFft, TransformOptions};
+#[cfg(feature = "std")]
+pub use wisdom::{Wisdom, WisdomError};
@@ -0,0 +1,138 @@
+//! Provides `MultiSizeSetup`, which performs FFTs of every power-of-two length
+//! up to a maximum using a single shared twiddle table.
+use alloc::vec::Vec;
+use core::mem;
+use num_complex::Complex;
+
+use super::kernel::{dif_fft, real_fft_pre_post_process, twiddle};
+use super::{
+    complex_slice_as_scalars_mut, scalars_as_complex_slice_mut, Num, PlanError, TransformError,
+};
+
+/// Performs FFTs of every power-of-two length up to `max_len` using a single
+/// twiddle table.
+///
+/// Unlike `Setup`, this type doesn't have any per-length state. The twiddle
+/// factors for a length `len` are read from the table of the length `max_len`
+/// with the stride `max_len / len`, so switching between the lengths costs
+/// nothing, and the memory consumption is bounded by `max_len`. No scratch
+/// buffer is required, so a `MultiSizeSetup` can be shared by any number of
+/// threads.
+///
+/// The transforms are computed by the portable radix-4 and radix-2
+/// butterflies of `Backend::Generic2`, which are usually slower than the
+/// kernels selected by `Setup`. The results match `Setup` with
+/// `Normalization::None` up to rounding errors; in particular, the backward
+/// real FFT is scaled by `1/2` in the same way.
+#[derive(Debug, Clone)]
+pub struct MultiSizeSetup<T> {
+    max_len: usize,
+    /// `exp(-2πik/max_len)` for `0 <= k < max_len / 2`.
+    twiddles: Vec<Complex<T>>,
+}
+
+impl<T: Num> MultiSizeSetup<T> {
+    /// Constructs a `MultiSizeSetup` supporting the lengths up to `max_len`,
+    /// which must be a power of two.
+    pub fn new(max_len: usize) -> Result<Self, PlanError> {
+        if max_len == 0 {
+            return Err(PlanError::ZeroLength);
+        }
+        if !max_len.is_power_of_two() {
+            return Err(PlanError::NotPowerOfTwo { len: max_len });
+        }
+        Ok(Self {
+            max_len,
+            twiddles: (0..max_len / 2)
+                .map(|k| twiddle(-2 * k as isize, max_len))
+                .collect(),
+        })
+    }
+
+    /// Retrieves the maximum supported length.
+    pub fn max_len(&self) -> usize {
+        self.max_len
+    }
+
+    /// Retrieves the size of the twiddle table in bytes.
+    pub fn table_size(&self) -> usize {
+        mem::size_of_val(&self.twiddles[..])
+    }
+
+    fn check_len(&self, len: usize) -> Result<(), TransformError> {
+        if len.is_power_of_two() && len <= self.max_len {
+            Ok(())
+        } else {
+            Err(TransformError::UnsupportedLength { len })
+        }
+    }
+
+    /// Computes the complex FFT of `data` in place. `data.len()` is the length
+    /// of the transform.
+    ///
+    /// Returns `TransformError::UnsupportedLength` if `data.len()` is not a
+    /// power of two or exceeds `max_len()`.
+    pub fn transform_complex(
+        &self,
+        data: &mut [Complex<T>],
+        inverse: bool,
+    ) -> Result<(), TransformError> {
+        try!(self.check_len(data.len()));
+        self.fft(data, inverse);
+        Ok(())
+    }
+
+    /// Computes the real FFT of `data` in place. `data.len()` is the length
+    /// of the transform.
+    ///
+    /// The forward (`inverse == false`) transform converts the `Real` format
+    /// to the `HalfComplex` format, and the backward transform does the
+    /// opposite.
+    ///
+    /// Returns `TransformError::UnsupportedLength` if `data.len()` is not a
+    /// power of two, is less than `2`, or exceeds `max_len()`.
+    pub fn transform_real(&self, data: &mut [T], inverse: bool) -> Result<(), TransformError> {
+        let len = data.len();
+        try!(self.check_len(len));
+        if len < 2 {
+            return Err(TransformError::UnsupportedLength { len });
+        }
+
+        // The real FFT of the length `len` is computed using the complex FFT
+        // of the length `len / 2`
+        let complex = scalars_as_complex_slice_mut(data);
+        if inverse {
+            real_fft_pre_post_process(complex, true, |k| self.twiddle(k, len, false));
+            self.fft(complex, true);
+        } else {
+            self.fft(complex, false);
+            real_fft_pre_post_process(complex, false, |k| self.twiddle(k, len, false));
+        }
+        Ok(())
+    }
+
+    /// Retrieves `exp(∓2πik/len)` for `0 <= k < len`.
+    #[inline]
+    fn twiddle(&self, k: usize, len: usize, inverse: bool) -> Complex<T> {
+        // The table only covers the first half of the circle
+        let i = k * (self.max_len / len);
+        let half = self.twiddles.len();
+        let w = if i < half {
+            self.twiddles[i]
+        } else {
+            -self.twiddles[i - half]
+        };
+        if inverse {
+            w.conj()
+        } else {
+            w
+        }
+    }
+
+    /// Computes the complex FFT of `data` in place.
+    fn fft(&self, data: &mut [Complex<T>], inverse: bool) {
+        dif_fft(complex_slice_as_scalars_mut(data), inverse, |k, len| {
+            self.twiddle(k, len, inverse)
+        });
+    }
+}
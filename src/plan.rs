@@ -0,0 +1,232 @@
+//! Decides the factorization and the kernel configuration of a `Setup`,
+//! optionally by measuring the alternatives on the current machine.
+use alloc::vec::Vec;
+#[cfg(feature = "std")]
+use core::cmp;
+#[cfg(feature = "std")]
+use std::time::{Duration, Instant};
+
+use super::setup::{check_radixes, factorize, factorize_radix2, fft_len, required_decimation};
+#[cfg(feature = "std")]
+use super::Num;
+use super::{Backend, DataOrder, Decimation, Options, PlanError, Setup};
+
+/// The decisions made while planning a `Setup`.
+///
+/// A `Plan` can be obtained by `Plan::estimate`, `Plan::measure`, or
+/// `Setup::plan`, and passed to `Setup::with_plan` to reproduce the same
+/// configuration later.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Plan {
+    /// The radixes of the butterfly passes in the order they are applied.
+    /// Their product must be the length of the underlying complex FFT, which
+    /// is `Options::len / 2` for the real FFTs of an even length and
+    /// `Options::len` otherwise.
+    pub radixes: Vec<usize>,
+
+    /// The direction of the butterfly passes. Must be `Frequency` if the
+    /// output is not naturally ordered, and `Time` if the input is not
+    /// naturally ordered. If both are naturally ordered, the bit reversal
+    /// stage is performed after the `Frequency` passes or before the `Time`
+    /// passes.
+    pub decimation: Decimation,
+
+    /// Limits the backend used for the bit reversal stage in addition to
+    /// `Options::max_backend`.
+    pub bit_reversal_backend: Backend,
+}
+
+impl Plan {
+    /// Creates the plan used by `Setup::new` using a fixed heuristic (radix 4
+    /// first, then 2, then odd factors). Does not perform any measurement.
+    pub fn estimate(options: &Options) -> Result<Self, PlanError> {
+        try!(Setup::<f32>::check(options));
+
+        let constrain_radix2 = options.input_data_order == DataOrder::BitReversed
+            || options.output_data_order == DataOrder::BitReversed;
+        let decimation = required_decimation(options).unwrap_or(Decimation::Frequency);
+
+        let len = fft_len(options);
+        let mut radixes = if constrain_radix2 {
+            try!(factorize_radix2(len))
+        } else {
+            factorize(len)
+        };
+        if decimation == Decimation::Time {
+            radixes.reverse();
+        }
+
+        Ok(Self {
+            radixes,
+            decimation,
+            bit_reversal_backend: Backend::Avx,
+        })
+    }
+
+    /// Creates a plan by timing the alternative factorizations, radix orders,
+    /// decimations, and bit reversal kernels on the current machine, and
+    /// choosing the fastest one.
+    ///
+    /// The measurement stops when `budget` runs out, in which case the
+    /// remaining candidates are not considered. The plan returned by
+    /// `estimate` is always measured first, so it's the result if the budget
+    /// is too small to measure anything else.
+    ///
+    /// If `Options::deterministic` is set, this function returns the same
+    /// result as `estimate` because the output depends on the plan.
+    ///
+    /// Requires the `std` feature.
+    #[cfg(feature = "std")]
+    pub fn measure<T>(options: &Options, budget: Duration) -> Result<Self, PlanError>
+    where
+        T: Num + 'static,
+    {
+        let estimate = try!(Self::estimate(options));
+        if options.deterministic {
+            return Ok(estimate);
+        }
+
+        let candidates = candidates(options, &estimate);
+        if candidates.len() <= 1 {
+            return Ok(estimate);
+        }
+
+        let start = Instant::now();
+        let slice = budget / candidates.len() as u32;
+        let mut best: Option<(Duration, Self)> = None;
+
+        for plan in candidates {
+            if best.is_some() && start.elapsed() >= budget {
+                break;
+            }
+            let setup = try!(Setup::<T>::with_plan(options, &plan));
+            let time = time_setup(&setup, slice);
+            if best.as_ref().map(|&(t, _)| time < t).unwrap_or(true) {
+                best = Some((time, plan));
+            }
+        }
+
+        Ok(best.unwrap().1)
+    }
+
+    /// Checks whether this plan can be used for `options` and the kernels
+    /// compiled into the library.
+    ///
+    /// `Setup::with_plan` builds the kernels' tables from the radixes right
+    /// after this check, and plans may come from untrusted sources (e.g.,
+    /// `Wisdom::from_str`), so this must not assume anything about them.
+    pub(crate) fn check(&self, options: &Options) -> Result<(), PlanError> {
+        let constrain_radix2 = options.input_data_order == DataOrder::BitReversed
+            || options.output_data_order == DataOrder::BitReversed;
+        let len = fft_len(options);
+        // Each radix is bounded by `len` before multiplying them, and the
+        // product is computed with overflow checks so that a wrapped-around
+        // product can't let through a radix too large to build tables for
+        let valid = self
+            .radixes
+            .iter()
+            .all(|&r| r >= 2 && r <= len && (!constrain_radix2 || r == 2))
+            && self
+                .radixes
+                .iter()
+                .try_fold(1usize, |acc, &r| acc.checked_mul(r))
+                == Some(len)
+            && required_decimation(options)
+                .map(|d| d == self.decimation)
+                .unwrap_or(true);
+        if !valid {
+            return Err(PlanError::InvalidPlan);
+        }
+        check_radixes(&self.radixes, options)
+    }
+}
+
+/// Enumerates the plans applicable to `options`, starting with `estimate`.
+#[cfg(feature = "std")]
+fn candidates(options: &Options, estimate: &Plan) -> Vec<Plan> {
+    let len = fft_len(options);
+    let constrain_radix2 = options.input_data_order == DataOrder::BitReversed
+        || options.output_data_order == DataOrder::BitReversed;
+
+    let num_twos = len.trailing_zeros() as usize;
+    let odd_factors = factorize(len >> num_twos);
+
+    let decimations = match required_decimation(options) {
+        Some(d) => vec![d],
+        None => vec![Decimation::Frequency, Decimation::Time],
+    };
+
+    // The bit reversal kernel only has a choice if a SIMD one is available
+    let max_backend = options.max_backend.unwrap_or(Backend::Custom);
+    let bit_reversal_backends = if required_decimation(options).is_none()
+        && cmp::min(max_backend, estimate.bit_reversal_backend) > Backend::Generic2
+    {
+        vec![estimate.bit_reversal_backend, Backend::Generic2]
+    } else {
+        vec![estimate.bit_reversal_backend]
+    };
+
+    let max_fours = if constrain_radix2 { 0 } else { num_twos / 2 };
+    let mut orders = Vec::new();
+    for num_fours in (0..max_fours + 1).rev() {
+        let mut radixes = vec![4; num_fours];
+        radixes.extend(vec![2; num_twos - num_fours * 2]);
+        radixes.extend(odd_factors.iter().cloned());
+
+        radixes.sort();
+        orders.push(radixes.clone());
+        radixes.reverse();
+        orders.push(radixes);
+    }
+
+    let mut plans = vec![estimate.clone()];
+    for &decimation in &decimations {
+        for radixes in &orders {
+            for &bit_reversal_backend in &bit_reversal_backends {
+                let plan = Plan {
+                    radixes: radixes.clone(),
+                    decimation,
+                    bit_reversal_backend,
+                };
+                if !plans.contains(&plan) {
+                    plans.push(plan);
+                }
+            }
+        }
+    }
+    plans
+}
+
+/// Measures the average time taken by a single transform, spending roughly
+/// `duration` (but at least one transform).
+#[cfg(feature = "std")]
+fn time_setup<T>(setup: &Setup<T>, duration: Duration) -> Duration
+where
+    T: Num + 'static,
+{
+    // Reload the input every time so the values don't blow up
+    let input: Vec<T> = (0..setup.buffer_len())
+        .map(|i| T::from(i % 7).unwrap() - T::from(3).unwrap())
+        .collect();
+    let mut data = input.clone();
+    let mut scratch = vec![T::zero(); setup.scratch_len()];
+
+    // Warm up
+    setup.run(&mut data, &mut scratch);
+
+    let start = Instant::now();
+    // `Duration` can only be divided by a `u32`, which a long measurement of a
+    // small transform could overflow
+    let mut count: u64 = 0;
+    loop {
+        data.copy_from_slice(&input);
+        setup.run(&mut data, &mut scratch);
+        count += 1;
+
+        let elapsed = start.elapsed();
+        if elapsed >= duration {
+            let nanos = elapsed.as_nanos() / u128::from(count);
+            return Duration::from_nanos(nanos as u64);
+        }
+    }
+}
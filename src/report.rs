@@ -0,0 +1,236 @@
+//! Provides a structured description of the kernels constituting a `Setup`.
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::fmt;
+use core::fmt::Write;
+use num_traits::Float;
+
+use super::{Backend, Options};
+
+/// The direction in which the butterfly passes of an FFT are applied.
+#[derive(Debug, Clone, Copy, Ord, PartialOrd, Eq, PartialEq, Hash)]
+pub enum Decimation {
+    /// Decimation-in-time. Used when the input is not naturally ordered, or
+    /// when chosen by the planner.
+    Time,
+
+    /// Decimation-in-frequency. Used when the output is not naturally
+    /// ordered, and by default when neither is.
+    Frequency,
+}
+
+/// The operation performed by a single stage of a `Setup`.
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Hash)]
+pub enum StageKind {
+    /// A butterfly pass of the complex FFT.
+    Butterfly {
+        radix: usize,
+        unit: usize,
+        decimation: Decimation,
+    },
+
+    /// Reorders the output of the decimation-in-frequency passes, or the input
+    /// of the decimation-in-time passes.
+    BitReversal,
+
+    /// Converts the `HalfComplex` input of a backward real FFT to the complex
+    /// sequence of the half length.
+    RealFftPreProcess,
+
+    /// Converts the output of the complex FFT of the half length to the
+    /// `HalfComplex` format.
+    RealFftPostProcess,
+
+    /// Converts the `Real` format to the `Complex` format.
+    RealToComplex,
+
+    /// Converts the `HalfComplex` format to the `Complex` format.
+    HalfComplexToComplex,
+}
+
+impl StageKind {
+    /// Estimates the number of floating-point operations performed by this
+    /// stage. `fft_len` is the length of the underlying complex FFT, and `len`
+    /// is `Options::len`.
+    ///
+    /// The estimate for a radix-`r` butterfly pass is `5 * fft_len * log2(r)`.
+    fn estimate_flops(&self, fft_len: usize, len: usize) -> f64 {
+        match *self {
+            StageKind::Butterfly { radix, .. } => 5.0 * fft_len as f64 * Float::log2(radix as f64),
+            StageKind::RealFftPreProcess | StageKind::RealFftPostProcess => 7.0 * len as f64,
+            StageKind::BitReversal | StageKind::RealToComplex | StageKind::HalfComplexToComplex => {
+                0.0
+            }
+        }
+    }
+}
+
+impl fmt::Display for StageKind {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            StageKind::Butterfly {
+                radix,
+                unit,
+                decimation,
+            } => write!(
+                f,
+                "radix-{} {} (unit = {})",
+                radix,
+                match decimation {
+                    Decimation::Time => "DIT",
+                    Decimation::Frequency => "DIF",
+                },
+                unit
+            ),
+            StageKind::BitReversal => write!(f, "bit reversal"),
+            StageKind::RealFftPreProcess => write!(f, "real FFT pre-processing"),
+            StageKind::RealFftPostProcess => write!(f, "real FFT post-processing"),
+            StageKind::RealToComplex => write!(f, "real to complex"),
+            StageKind::HalfComplexToComplex => write!(f, "half complex to complex"),
+        }
+    }
+}
+
+/// Describes a single stage of a `Setup`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct StageReport {
+    /// The operation performed by this stage.
+    pub kind: StageKind,
+
+    /// The family of the kernel implementing this stage.
+    pub backend: Backend,
+
+    /// The number of scalar values this stage requires in the work area.
+    pub work_area_len: usize,
+
+    /// The number of bytes occupied by the precomputed tables of this stage.
+    pub table_size: usize,
+
+    /// The estimated number of floating-point operations.
+    pub flops: f64,
+}
+
+/// Describes the kernels constituting a `Setup`. Returned by `Setup::report`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PlanReport {
+    /// The options the `Setup` was created with.
+    pub options: Options,
+
+    /// The length of the underlying complex FFT. This is a half of
+    /// `options.len` if the `Setup` uses a real FFT.
+    pub fft_len: usize,
+
+    /// The radixes of the butterfly passes in the order they are applied.
+    pub radixes: Vec<usize>,
+
+    /// The stages in the order they are applied.
+    pub stages: Vec<StageReport>,
+
+    /// Equals to `Setup::scratch_len()`.
+    pub scratch_len: usize,
+
+    /// The total number of bytes occupied by the precomputed tables. Tables
+    /// shared with other `Setup`s are included as well.
+    pub table_size: usize,
+
+    /// The estimated total number of floating-point operations.
+    pub flops: f64,
+}
+
+impl PlanReport {
+    pub(crate) fn new<I>(options: &Options, fft_len: usize, radixes: &[usize], stages: I) -> Self
+    where
+        I: IntoIterator<Item = (StageKind, Backend, usize, usize)>,
+    {
+        let stages: Vec<_> = stages
+            .into_iter()
+            .map(|(kind, backend, work_area_len, table_size)| StageReport {
+                kind,
+                backend,
+                work_area_len,
+                table_size,
+                flops: kind.estimate_flops(fft_len, options.len),
+            })
+            .collect();
+        Self {
+            options: *options,
+            fft_len,
+            radixes: radixes.to_vec(),
+            scratch_len: stages.iter().map(|s| s.work_area_len).max().unwrap_or(0),
+            table_size: stages.iter().map(|s| s.table_size).sum(),
+            flops: stages.iter().map(|s| s.flops).sum(),
+            stages,
+        }
+    }
+
+    /// Renders the stages as a graph in the DOT language.
+    pub fn to_dot(&self) -> String {
+        let mut out = String::new();
+        writeln!(out, "digraph plan {{").unwrap();
+        writeln!(out, "    rankdir=LR;").unwrap();
+        writeln!(
+            out,
+            "    input [shape=box, label=\"input\\n{:?}, {:?}\"];",
+            self.options.input_data_format, self.options.input_data_order
+        )
+        .unwrap();
+        writeln!(
+            out,
+            "    output [shape=box, label=\"output\\n{:?}, {:?}\"];",
+            self.options.output_data_format, self.options.output_data_order
+        )
+        .unwrap();
+        for (i, stage) in self.stages.iter().enumerate() {
+            writeln!(
+                out,
+                "    stage{} [label=\"{}\\n{:?}\\n{} table bytes, {:.0} flops\"];",
+                i, stage.kind, stage.backend, stage.table_size, stage.flops
+            )
+            .unwrap();
+        }
+        let mut prev = String::from("input");
+        for i in 0..self.stages.len() {
+            let cur = format!("stage{}", i);
+            writeln!(out, "    {} -> {};", prev, cur).unwrap();
+            prev = cur;
+        }
+        writeln!(out, "    {} -> output;", prev).unwrap();
+        writeln!(out, "}}").unwrap();
+        out
+    }
+}
+
+/// Renders the report as a human-readable text.
+impl fmt::Display for PlanReport {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let o = &self.options;
+        try!(writeln!(
+            f,
+            "{} FFT of length {}: {:?} ({:?}) -> {:?} ({:?})",
+            if o.inverse { "Backward" } else { "Forward" },
+            o.len,
+            o.input_data_format,
+            o.input_data_order,
+            o.output_data_format,
+            o.output_data_order
+        ));
+        try!(writeln!(
+            f,
+            "Complex FFT length: {}, radixes: {:?}",
+            self.fft_len, self.radixes
+        ));
+        try!(writeln!(
+            f,
+            "Scratch: {} values, tables: {} bytes, estimated flops: {:.0}",
+            self.scratch_len, self.table_size, self.flops
+        ));
+        for (i, stage) in self.stages.iter().enumerate() {
+            try!(writeln!(
+                f,
+                "  #{}: {} [{:?}] work area: {}, tables: {} bytes, flops: {:.0}",
+                i, stage.kind, stage.backend, stage.work_area_len, stage.table_size, stage.flops
+            ));
+        }
+        Ok(())
+    }
+}
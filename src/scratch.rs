@@ -0,0 +1,43 @@
+//! Provides a per-thread pool of scratch buffers used by
+//! `Setup::transform_concurrent`.
+use std::any::{Any, TypeId};
+use std::cell::RefCell;
+use std::collections::HashMap;
+
+use super::Num;
+
+thread_local! {
+    /// Scratch buffers owned by the current thread, keyed by the scalar type
+    /// and length. Each value is a `Vec<T>` of the corresponding type.
+    static POOL: RefCell<HashMap<(TypeId, usize), Box<Any>>> = RefCell::new(HashMap::new());
+}
+
+/// Calls `f` with a scratch buffer of the length `len` owned by the current
+/// thread.
+///
+/// The buffer is allocated on the first use and reused by the subsequent calls
+/// requesting the same length. The buffer is removed from the pool while `f`
+/// is running, so a reentrant call allocates another buffer instead of
+/// aliasing the borrowed one.
+pub(crate) fn with_scratch<T, F, R>(len: usize, f: F) -> R
+where
+    T: Num,
+    F: FnOnce(&mut [T]) -> R,
+{
+    if len == 0 {
+        return f(&mut []);
+    }
+
+    let key = (TypeId::of::<T>(), len);
+    let mut buffer: Box<Any> = POOL
+        .with(|pool| pool.borrow_mut().remove(&key))
+        .unwrap_or_else(|| Box::new(vec![T::zero(); len]));
+
+    let result = f(buffer.downcast_mut::<Vec<T>>().unwrap());
+
+    POOL.with(|pool| {
+        pool.borrow_mut().insert(key, buffer);
+    });
+
+    result
+}
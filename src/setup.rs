@@ -1,15 +1,30 @@
+use super::env::check_buffer_len;
 use super::kernel::{
-    new_bit_reversal_kernel, new_half_complex_to_complex_kernel,
+    is_radix_supported, new_bit_reversal_kernel, new_half_complex_to_complex_kernel,
     new_real_fft_pre_post_process_kernel, new_real_to_complex_kernel, Kernel, KernelCreationParams,
-    KernelType,
+    KernelParams, KernelType,
 };
-use super::Num;
+use super::report::{Decimation, PlanReport, StageKind};
+#[cfg(feature = "std")]
+use super::scratch;
+#[cfg(feature = "std")]
+use super::wisdom;
+use super::{Backend, Num, Plan, StridedSequence, TransformError};
+use alloc::boxed::Box;
+use alloc::vec::Vec;
+use core::cmp;
+use core::fmt;
+use core::hash::{Hash, Hasher};
+use core::mem;
+use core::result::Result;
+#[cfg(feature = "std")]
+use core::time::Duration;
+use num_traits::Float;
+#[cfg(feature = "std")]
 use std::error;
-use std::fmt;
-use std::result::Result;
 
 /// Specifies the data order in which the data is supplied to or returned from the kernel.
-#[derive(Debug, Clone, Copy, Ord, PartialOrd, Eq, PartialEq)]
+#[derive(Debug, Clone, Copy, Ord, PartialOrd, Eq, PartialEq, Hash)]
 pub enum DataOrder {
     /// The data is ordered in a natural order.
     Natural,
@@ -25,7 +40,7 @@ pub enum DataOrder {
 }
 
 /// Specifies the data format.
-#[derive(Debug, Clone, Copy, Ord, PartialOrd, Eq, PartialEq)]
+#[derive(Debug, Clone, Copy, Ord, PartialOrd, Eq, PartialEq, Hash)]
 pub enum DataFormat {
     /// Specifies the interleaved complex format.
     Complex,
@@ -47,8 +62,81 @@ pub enum DataFormat {
     HalfComplex,
 }
 
+/// Specifies the scaling factor applied to the output.
+///
+/// The factors are relative to the unnormalized DFT
+/// `X[k] = sum(x[n] * exp(∓2πikn/N))`.
+///
+/// The factor is folded into the bit reversal, real-to-complex conversion, or
+/// real FFT pre/post-processing stage if the transform has one. Otherwise, it
+/// is folded into the first butterfly pass of a decimation-in-time transform
+/// or the last one of a decimation-in-frequency transform. Either way, no
+/// additional pass is needed.
+#[derive(Debug, Clone, Copy, Default)]
+pub enum Normalization {
+    /// The output is not scaled.
+    ///
+    /// Note that the transforms from the `HalfComplex` format produce an
+    /// output scaled by `1/2` in this mode (for example, a round trip through
+    /// `Real` → `HalfComplex` → `Real` multiplies the signal by `len / 2`).
+    /// Use `Custom(1.0)` to get the unnormalized DFT in this case.
+    #[default]
+    None,
+
+    /// The inverse transform is scaled by `1 / len`, so a round trip through
+    /// a forward and inverse transform is an identity. The forward transform
+    /// is not scaled.
+    ByLen,
+
+    /// Both of the forward and inverse transforms are scaled by
+    /// `1 / sqrt(len)`, making the transform unitary.
+    Unitary,
+
+    /// The output is scaled by the specified value.
+    Custom(f64),
+}
+
+impl Normalization {
+    /// Retrieves the factor by which the unnormalized DFT of the length `len`
+    /// is multiplied. Returns `None` for `Normalization::None`.
+    pub fn factor(self, len: usize, inverse: bool) -> Option<f64> {
+        match self {
+            Normalization::None => None,
+            Normalization::ByLen if inverse => Some(1.0 / len as f64),
+            Normalization::ByLen => Some(1.0),
+            Normalization::Unitary => Some(1.0 / Float::sqrt(len as f64)),
+            Normalization::Custom(x) => Some(x),
+        }
+    }
+
+    fn key(self) -> (u8, u64) {
+        match self {
+            Normalization::None => (0, 0),
+            Normalization::ByLen => (1, 0),
+            Normalization::Unitary => (2, 0),
+            Normalization::Custom(x) => (3, x.to_bits()),
+        }
+    }
+}
+
+// `Custom` values are compared bitwise so `Options` can implement `Eq` and
+// `Hash`
+impl PartialEq for Normalization {
+    fn eq(&self, other: &Self) -> bool {
+        self.key() == other.key()
+    }
+}
+
+impl Eq for Normalization {}
+
+impl Hash for Normalization {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.key().hash(state);
+    }
+}
+
 /// The FFT kernel configuration.
-#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Hash)]
 pub struct Options {
     /// Specifies the input data order.
     ///
@@ -84,27 +172,158 @@ pub struct Options {
 
     /// Specifies whether the inverse (backward) transformation is used.
     pub inverse: bool,
+
+    /// Specifies the scaling factor applied to the output.
+    pub normalization: Normalization,
+
+    /// Limits the kernel selection to the specified backend and the less
+    /// optimized ones (see `Backend` for the order). `None` allows every
+    /// backend.
+    ///
+    /// The kernels of the limited backend are not guaranteed to be used. For
+    /// example, the x86 kernels are only used for `f32` and certain radixes,
+    /// and the others fall back to the generic kernels. The backends excluded
+    /// by the `backend-*` features are never used.
+    pub max_backend: Option<Backend>,
+
+    /// Requests bit-identical results across machines.
+    ///
+    /// If `true`, the kernel selection is limited to `Backend::Generic2` (in
+    /// addition to `max_backend`). The generic kernels evaluate the same
+    /// operations in the same order regardless of the processor, never use
+    /// fused multiply-add, and compute their twiddle factors without relying
+    /// on the platform's math library. As a result, the output only depends on
+    /// the input as long as the platform implements IEEE 754 arithmetic
+    /// faithfully (this excludes the x87 FPU used by some 32-bit x86 targets).
+    /// The results can still differ between builds with different sets of
+    /// `backend-*` features.
+    pub deterministic: bool,
 }
 
 /// The error type which is returned from the `Setup` creation function.
 #[derive(Debug, Clone, Copy, Ord, PartialOrd, Eq, PartialEq)]
 pub enum PlanError {
-    /// A parameter was incorrect.
-    InvalidInput,
+    /// `len` is zero.
+    ZeroLength,
+
+    /// Both of `input_data_order` and `output_data_order` are not `Natural`.
+    BothOrdersSwizzled {
+        input_data_order: DataOrder,
+        output_data_order: DataOrder,
+    },
+
+    /// A data order other than `Natural` was specified for a data format
+    /// other than `Complex`.
+    IncompatibleDataOrder {
+        order: DataOrder,
+        format: DataFormat,
+    },
+
+    /// The combination of the data formats and the transform direction is not
+    /// supported.
+    UnsupportedDataFormat {
+        input_data_format: DataFormat,
+        output_data_format: DataFormat,
+        inverse: bool,
+    },
+
+    /// The `HalfComplex` format was specified with an odd length.
+    OddLength { len: usize },
+
+    /// `BitReversed` was specified with a length that is not a power of two.
+    NotPowerOfTwo { len: usize },
+
+    /// The `Plan` supplied to `Setup::with_plan` is not applicable to the
+    /// options.
+    InvalidPlan,
+
+    /// A butterfly pass of the radix `radix` is required, but no kernel
+    /// supporting it is compiled into the library (see the `backend-*`
+    /// features) or allowed by `max_backend`.
+    UnsupportedRadix { radix: usize },
 }
 
 impl fmt::Display for PlanError {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         match *self {
-            PlanError::InvalidInput => write!(f, "The parameter is invalid."),
+            PlanError::ZeroLength => write!(f, "The length must not be zero."),
+            PlanError::BothOrdersSwizzled {
+                input_data_order,
+                output_data_order,
+            } => write!(
+                f,
+                "The input and output data orders must not be non-natural at the same time \
+                 (input: {:?}, output: {:?}).",
+                input_data_order, output_data_order
+            ),
+            PlanError::IncompatibleDataOrder { order, format } => write!(
+                f,
+                "The data order {:?} cannot be used with the data format {:?}.",
+                order, format
+            ),
+            PlanError::UnsupportedDataFormat {
+                input_data_format,
+                output_data_format,
+                inverse,
+            } => write!(
+                f,
+                "The {} transform from {:?} to {:?} is not supported.",
+                if inverse { "backward" } else { "forward" },
+                input_data_format,
+                output_data_format
+            ),
+            PlanError::OddLength { len } => write!(
+                f,
+                "The HalfComplex format requires an even length, but the length is {}.",
+                len
+            ),
+            PlanError::NotPowerOfTwo { len } => write!(
+                f,
+                "The BitReversed data order requires a power-of-two length, \
+                 but the length is {}.",
+                len
+            ),
+            PlanError::InvalidPlan => write!(f, "The plan is not applicable to the options."),
+            PlanError::UnsupportedRadix { radix } => write!(
+                f,
+                "No kernel supporting the radix {} is available within the backend limit.",
+                radix
+            ),
         }
     }
 }
 
+#[cfg(feature = "std")]
 impl error::Error for PlanError {
     fn description(&self) -> &str {
         match *self {
-            PlanError::InvalidInput => "Invalid input",
+            PlanError::ZeroLength => "Zero length",
+            PlanError::BothOrdersSwizzled { .. } => "Both data orders are swizzled",
+            PlanError::IncompatibleDataOrder { .. } => "Incompatible data order",
+            PlanError::UnsupportedDataFormat { .. } => "Unsupported data format",
+            PlanError::OddLength { .. } => "Odd length",
+            PlanError::NotPowerOfTwo { .. } => "Length not a power of two",
+            PlanError::InvalidPlan => "Invalid plan",
+            PlanError::UnsupportedRadix { .. } => "Unsupported radix",
+        }
+    }
+}
+
+impl DataFormat {
+    /// The number of scalar values that constitute a single element.
+    pub(crate) fn element_len(self) -> usize {
+        match self {
+            DataFormat::Complex | DataFormat::HalfComplex => 2,
+            DataFormat::Real => 1,
+        }
+    }
+
+    /// The number of elements in a sequence of the length `len` represented
+    /// in this format.
+    pub(crate) fn num_elements(self, len: usize) -> usize {
+        match self {
+            DataFormat::Complex | DataFormat::Real => len,
+            DataFormat::HalfComplex => len / 2,
         }
     }
 }
@@ -113,13 +332,126 @@ impl error::Error for PlanError {
 #[derive(Debug)]
 pub struct Setup<T> {
     pub(crate) kernels: Vec<Box<Kernel<T>>>,
+    /// The operation performed by each element of `kernels`.
+    stages: Vec<StageKind>,
+    options: Options,
+    fft_len: usize,
+    plan: Plan,
 }
 
 pub fn factorize_radix2(x: usize) -> Result<Vec<usize>, PlanError> {
     if (x & (x - 1)) == 0 {
         Ok(vec![2; x.trailing_zeros() as usize])
     } else {
-        Err(PlanError::InvalidInput)
+        Err(PlanError::NotPowerOfTwo { len: x })
+    }
+}
+
+/// Retrieves the most optimized backend the kernels of a `Setup` may use.
+pub(crate) fn max_backend(options: &Options) -> Backend {
+    let max_backend = options.max_backend.unwrap_or(Backend::Custom);
+    if options.deterministic {
+        cmp::min(max_backend, Backend::Generic2)
+    } else {
+        max_backend
+    }
+}
+
+/// Checks the constraints on `options` that don't depend on the
+/// factorization of the length, which is expensive to find for a large prime
+/// length.
+pub(crate) fn check_options(options: &Options) -> Result<(), PlanError> {
+    if options.len == 0 {
+        return Err(PlanError::ZeroLength);
+    }
+
+    for &(order, format) in &[
+        (options.input_data_order, options.input_data_format),
+        (options.output_data_order, options.output_data_format),
+    ] {
+        if order != DataOrder::Natural && format != DataFormat::Complex {
+            return Err(PlanError::IncompatibleDataOrder { order, format });
+        }
+    }
+
+    if options.input_data_order != DataOrder::Natural
+        && options.output_data_order != DataOrder::Natural
+    {
+        return Err(PlanError::BothOrdersSwizzled {
+            input_data_order: options.input_data_order,
+            output_data_order: options.output_data_order,
+        });
+    }
+
+    match (
+        options.input_data_format,
+        options.output_data_format,
+        options.inverse,
+    ) {
+        (DataFormat::Complex, DataFormat::Complex, _)
+        | (DataFormat::Real, DataFormat::Complex, false)
+        | (DataFormat::Real, DataFormat::HalfComplex, false)
+        | (DataFormat::HalfComplex, DataFormat::Real, true)
+        | (DataFormat::HalfComplex, DataFormat::Complex, true) => {}
+        _ => {
+            return Err(PlanError::UnsupportedDataFormat {
+                input_data_format: options.input_data_format,
+                output_data_format: options.output_data_format,
+                inverse: options.inverse,
+            });
+        }
+    }
+
+    // note: `HalfComplex` is not defined for odd sizes
+    if (options.input_data_format == DataFormat::HalfComplex
+        || options.output_data_format == DataFormat::HalfComplex)
+        && options.len % 2 != 0
+    {
+        return Err(PlanError::OddLength { len: options.len });
+    }
+
+    if (options.input_data_order == DataOrder::BitReversed
+        || options.output_data_order == DataOrder::BitReversed)
+        && !options.len.is_power_of_two()
+    {
+        return Err(PlanError::NotPowerOfTwo { len: options.len });
+    }
+
+    Ok(())
+}
+
+/// Checks whether a kernel is available for every radix in `radixes`.
+pub(crate) fn check_radixes(radixes: &[usize], options: &Options) -> Result<(), PlanError> {
+    let max_backend = max_backend(options);
+    match radixes
+        .iter()
+        .find(|&&radix| !is_radix_supported(radix, max_backend))
+    {
+        Some(&radix) => Err(PlanError::UnsupportedRadix { radix }),
+        None => Ok(()),
+    }
+}
+
+/// Retrieves the length of the underlying complex FFT.
+pub(crate) fn fft_len(options: &Options) -> usize {
+    let use_realfft = options.len % 2 == 0
+        && (options.input_data_format != DataFormat::Complex
+            || options.output_data_format != DataFormat::Complex);
+    if use_realfft {
+        options.len / 2
+    } else {
+        options.len
+    }
+}
+
+/// Retrieves the decimation dictated by the data orders, or `None` if both of
+/// them are `Natural`.
+pub(crate) fn required_decimation(options: &Options) -> Option<Decimation> {
+    match (options.input_data_order, options.output_data_order) {
+        (DataOrder::Natural, DataOrder::Natural) => None,
+        (DataOrder::Natural, _) => Some(Decimation::Frequency),
+        (_, DataOrder::Natural) => Some(Decimation::Time),
+        _ => unreachable!(),
     }
 }
 
@@ -153,42 +485,70 @@ impl<T> Setup<T>
 where
     T: Num + 'static,
 {
+    /// Checks whether `options` is valid, and returns the error describing the
+    /// first violated constraint if it isn't.
+    ///
+    /// `Setup::new` succeeds if and only if this function returns `Ok(())`.
+    pub fn check(options: &Options) -> Result<(), PlanError> {
+        try!(check_options(options));
+        check_radixes(&factorize(fft_len(options)), options)
+    }
+
+    /// Creates a `Setup` using the plan registered by `Wisdom::register` or
+    /// `new_measured`, or the one returned by `Plan::estimate` if there's
+    /// none. Without the `std` feature, the plan returned by `Plan::estimate`
+    /// is always used.
     pub fn new(options: &Options) -> Result<Self, PlanError> {
-        if options.len == 0 {
-            return Err(PlanError::InvalidInput);
+        #[cfg(feature = "std")]
+        {
+            if let Some(plan) = wisdom::lookup::<T>(options) {
+                return Self::with_plan(options, &plan);
+            }
         }
+        let plan = try!(Plan::estimate(options));
+        Self::with_plan(options, &plan)
+    }
 
-        let constain_radix2 = options.input_data_order == DataOrder::BitReversed
-            || options.output_data_order == DataOrder::BitReversed;
-
-        let is_even_sized = options.len % 2 == 0;
-
-        let input_swizzled = match options.input_data_order {
-            DataOrder::Natural => false,
-            DataOrder::Swizzled => true,
-            DataOrder::BitReversed => true,
-        };
-
-        let output_swizzled = match options.output_data_order {
-            DataOrder::Natural => false,
-            DataOrder::Swizzled => true,
-            DataOrder::BitReversed => true,
-        };
-
-        if input_swizzled && options.input_data_format != DataFormat::Complex {
-            return Err(PlanError::InvalidInput);
+    /// Creates a `Setup` using the plan returned by `Plan::measure`, which
+    /// spends up to `budget` timing the alternative configurations.
+    ///
+    /// The measurement is skipped if a plan for `options` was registered by
+    /// `Wisdom::register` or a previous call to this function. Otherwise, the
+    /// result is registered so `new` and this function can reuse it.
+    ///
+    /// Requires the `std` feature.
+    #[cfg(feature = "std")]
+    pub fn new_measured(options: &Options, budget: Duration) -> Result<Self, PlanError> {
+        if let Some(plan) = wisdom::lookup::<T>(options) {
+            return Self::with_plan(options, &plan);
         }
-
-        if output_swizzled && options.output_data_format != DataFormat::Complex {
-            return Err(PlanError::InvalidInput);
+        let plan = try!(Plan::measure::<T>(options, budget));
+        let setup = try!(Self::with_plan(options, &plan));
+        if !options.deterministic {
+            wisdom::record(&setup);
         }
+        Ok(setup)
+    }
 
-        let (post_bit_reversal, kernel_type) = match (input_swizzled, output_swizzled) {
-            (false, false) => (true, KernelType::Dif),
-            (true, false) => (false, KernelType::Dit),
-            (false, true) => (false, KernelType::Dif),
-            (true, true) => return Err(PlanError::InvalidInput),
+    /// Creates a `Setup` using the specified plan.
+    ///
+    /// Returns `PlanError::InvalidPlan` if `plan` is not applicable to
+    /// `options`.
+    pub fn with_plan(options: &Options, plan: &Plan) -> Result<Self, PlanError> {
+        // `plan.check` checks the radixes actually used, so the default
+        // factorization checked by `Self::check` is irrelevant here
+        try!(check_options(options));
+        try!(plan.check(options));
+
+        let is_even_sized = options.len % 2 == 0;
+
+        let kernel_type = match plan.decimation {
+            Decimation::Time => KernelType::Dit,
+            Decimation::Frequency => KernelType::Dif,
         };
+        let bit_reversal = required_decimation(options).is_none() && plan.radixes.len() > 1;
+        let pre_bit_reversal = bit_reversal && kernel_type == KernelType::Dit;
+        let post_bit_reversal = bit_reversal && kernel_type == KernelType::Dif;
 
         let (pre_r2c, post_hc2c, post_r2c, use_realfft) = match (
             options.input_data_format,
@@ -199,98 +559,414 @@ where
             (DataFormat::Complex, DataFormat::Complex, _, _) => (false, false, false, false),
             (DataFormat::Real, DataFormat::Complex, _, false) => (true, false, false, false),
             (DataFormat::Real, DataFormat::Complex, false, true) => (false, true, false, true),
-
-            // note: `HalfComplex` is not defined for odd sizes
             (DataFormat::Real, DataFormat::HalfComplex, false, true) => (false, false, false, true),
             (DataFormat::HalfComplex, DataFormat::Real, true, true) => (false, false, false, true),
             (DataFormat::HalfComplex, DataFormat::Complex, true, true) => {
                 (false, false, true, true)
             }
-            _ => return Err(PlanError::InvalidInput),
+            _ => unreachable!(),
         };
 
-        let fft_len = if use_realfft {
-            options.len / 2
-        } else {
-            options.len
-        };
+        let fft_len = fft_len(options);
+        let radixes = &plan.radixes;
+
+        let max_backend = max_backend(options);
 
-        let mut radixes = if constain_radix2 {
-            try!(factorize_radix2(fft_len))
+        // The backward real FFT inherently scales the output by `1/2`
+        let inherent_scale = if use_realfft && options.inverse {
+            0.5
         } else {
-            factorize(fft_len)
+            1.0
         };
-        if kernel_type == KernelType::Dit {
-            radixes.reverse();
-        }
+        let mut scale = options
+            .normalization
+            .factor(options.len, options.inverse)
+            .map(|x| x / inherent_scale)
+            .unwrap_or(1.0);
+
+        // `scale` is folded into the first kernel that supports scaling.
+        // `take_scale()` returns the remaining factor and resets it.
+        let mut take_scale = || mem::replace(&mut scale, 1.0);
 
         let mut kernels = Vec::new();
+        let mut stages = Vec::new();
+        let mut push = |kind, kernel| {
+            stages.push(kind);
+            kernels.push(kernel);
+        };
 
         if pre_r2c {
-            kernels.push(new_real_to_complex_kernel(options.len));
+            push(
+                StageKind::RealToComplex,
+                new_real_to_complex_kernel(options.len, take_scale()),
+            );
         }
 
         if use_realfft && options.inverse {
-            kernels.push(new_real_fft_pre_post_process_kernel(options.len, true));
+            push(
+                StageKind::RealFftPreProcess,
+                new_real_fft_pre_post_process_kernel(options.len, true, take_scale(), max_backend),
+            );
+        }
+
+        let bit_reversal_backend = cmp::min(max_backend, plan.bit_reversal_backend);
+        if pre_bit_reversal {
+            push(
+                StageKind::BitReversal,
+                new_bit_reversal_kernel(radixes.as_slice(), take_scale(), bit_reversal_backend),
+            );
         }
 
         match kernel_type {
             KernelType::Dif => {
                 let mut unit = fft_len;
-                for radix_ref in &radixes {
+                for radix_ref in radixes {
                     let radix = *radix_ref;
                     unit /= radix;
-                    kernels.push(Kernel::new(&KernelCreationParams {
-                        size: fft_len,
-                        kernel_type: kernel_type,
-                        radix: radix,
-                        unit: unit,
-                        inverse: options.inverse,
-                    }));
+                    // The last stage has `unit == 1` and applies the factor
+                    // unless the bit reversal stage takes it
+                    let scale = if unit == 1 && !post_bit_reversal {
+                        take_scale()
+                    } else {
+                        1.0
+                    };
+                    push(
+                        StageKind::Butterfly {
+                            radix,
+                            unit,
+                            decimation: Decimation::Frequency,
+                        },
+                        Kernel::new(
+                            &KernelCreationParams {
+                                size: fft_len,
+                                kernel_type: kernel_type,
+                                radix: radix,
+                                unit: unit,
+                                inverse: options.inverse,
+                            },
+                            scale,
+                            max_backend,
+                        ),
+                    );
                 }
             }
             KernelType::Dit => {
                 let mut unit = 1;
-                for radix_ref in &radixes {
+                for radix_ref in radixes {
                     let radix = *radix_ref;
-                    kernels.push(Kernel::new(&KernelCreationParams {
-                        size: fft_len,
-                        kernel_type: kernel_type,
-                        radix: radix,
-                        unit: unit,
-                        inverse: options.inverse,
-                    }));
+                    // The first stage has `unit == 1`. The factor may have
+                    // been taken by a preceding stage already
+                    let scale = if unit == 1 { take_scale() } else { 1.0 };
+                    push(
+                        StageKind::Butterfly {
+                            radix,
+                            unit,
+                            decimation: Decimation::Time,
+                        },
+                        Kernel::new(
+                            &KernelCreationParams {
+                                size: fft_len,
+                                kernel_type: kernel_type,
+                                radix: radix,
+                                unit: unit,
+                                inverse: options.inverse,
+                            },
+                            scale,
+                            max_backend,
+                        ),
+                    );
                     unit *= radix;
                 }
             }
         }
 
-        if post_bit_reversal && radixes.len() > 1 {
-            kernels.push(new_bit_reversal_kernel(radixes.as_slice()));
+        if post_bit_reversal {
+            push(
+                StageKind::BitReversal,
+                new_bit_reversal_kernel(radixes.as_slice(), take_scale(), bit_reversal_backend),
+            );
         }
 
         if use_realfft && !options.inverse {
-            kernels.push(new_real_fft_pre_post_process_kernel(options.len, false));
+            push(
+                StageKind::RealFftPostProcess,
+                new_real_fft_pre_post_process_kernel(options.len, false, take_scale(), max_backend),
+            );
         }
 
         if post_hc2c {
-            kernels.push(new_half_complex_to_complex_kernel(options.len));
+            push(
+                StageKind::HalfComplexToComplex,
+                new_half_complex_to_complex_kernel(options.len),
+            );
         }
 
         if post_r2c {
-            kernels.push(new_real_to_complex_kernel(options.len));
+            push(
+                StageKind::RealToComplex,
+                new_real_to_complex_kernel(options.len, 1.0),
+            );
+        }
+
+        // A complex transform of length 1 has no butterflies. The factor is
+        // applied by a trivial bit reversal stage
+        let scale = take_scale();
+        if scale != 1.0 {
+            push(
+                StageKind::BitReversal,
+                new_bit_reversal_kernel(radixes.as_slice(), scale, bit_reversal_backend),
+            );
+        }
+
+        Ok(Self {
+            kernels: kernels,
+            stages: stages,
+            options: *options,
+            fft_len: fft_len,
+            plan: plan.clone(),
+        })
+    }
+
+    /// Retrieves the `Options` this `Setup` was created with.
+    pub fn options(&self) -> &Options {
+        &self.options
+    }
+
+    /// Retrieves the `Plan` this `Setup` was created with.
+    pub fn plan(&self) -> &Plan {
+        &self.plan
+    }
+
+    /// Describes the kernels constituting this `Setup`.
+    pub fn report(&self) -> PlanReport {
+        PlanReport::new(
+            &self.options,
+            self.fft_len,
+            &self.plan.radixes,
+            self.stages
+                .iter()
+                .zip(self.kernels.iter())
+                .map(|(&kind, kernel)| {
+                    (
+                        kind,
+                        kernel.backend(),
+                        kernel.required_work_area_size(),
+                        kernel.table_size(),
+                    )
+                }),
+        )
+    }
+
+    /// Retrieves the number of scalar values in the input sequence (e.g.,
+    /// `len * 2` for the `Complex` format and `len` for the `Real` format).
+    pub fn input_len(&self) -> usize {
+        let format = self.options.input_data_format;
+        format.num_elements(self.options.len) * format.element_len()
+    }
+
+    /// Retrieves the number of scalar values in the output sequence.
+    pub fn output_len(&self) -> usize {
+        let format = self.options.output_data_format;
+        format.num_elements(self.options.len) * format.element_len()
+    }
+
+    /// Retrieves the minimum length of the buffer supplied to `Env::transform`,
+    /// which is the larger of `input_len()` and `output_len()`.
+    pub fn buffer_len(&self) -> usize {
+        cmp::max(self.input_len(), self.output_len())
+    }
+
+    /// Applies the kernels to `data` in place.
+    ///
+    /// If the `checked` feature is enabled, this function asserts that `data`
+    /// is at least `buffer_len()` long. If the `checked-finite` feature is enabled and the first `buffer_len()`
+    /// elements of `data` are all finite, this function asserts that every
+    /// kernel keeps them finite.
+    pub(crate) fn run(&self, data: &mut [T], work_area: &mut [T]) {
+        self.run_inner(None, data, work_area);
+    }
+
+    /// Applies the kernels to `input` and stores the result to `output`.
+    ///
+    /// The first kernel reads `input` directly (see `Kernel::transform_from`),
+    /// so `input` doesn't have to be copied to `output` beforehand. `input`
+    /// must be `input_len()` long, and `output` must be at least
+    /// `output_len()` long.
+    ///
+    /// If the `checked-finite` feature is enabled and `input` is all finite,
+    /// this function asserts that every kernel keeps the first `input_len()`
+    /// elements of `output` finite.
+    pub(crate) fn run_from(&self, input: &[T], output: &mut [T], work_area: &mut [T]) {
+        debug_assert_eq!(input.len(), self.input_len());
+        self.run_inner(Some(input), output, work_area);
+    }
+
+    fn run_inner(&self, input: Option<&[T]>, data: &mut [T], work_area: &mut [T]) {
+        if cfg!(feature = "checked") {
+            // `Env::transform_unchecked` leaves this to the caller
+            assert!(
+                data.len() >= self.buffer_len(),
+                "the buffer is too short for the setup (len: {}, required: {})",
+                data.len(),
+                self.buffer_len()
+            );
+        }
+
+        let check_len = match input {
+            Some(input) => input.len(),
+            None => self.buffer_len(),
+        };
+        let check_finite = cfg!(feature = "checked-finite")
+            && data.len() >= check_len
+            && input
+                .unwrap_or(&data[0..check_len])
+                .iter()
+                .all(|x| x.is_finite());
+
+        let mut kernel_param = KernelParams {
+            coefs: data,
+            work_area: work_area,
+        };
+        for (i, kernel) in self.kernels.iter().enumerate() {
+            match input {
+                Some(input) if i == 0 => kernel.transform_from(input, &mut kernel_param),
+                _ => kernel.transform(&mut kernel_param),
+            }
+
+            if check_finite {
+                assert!(
+                    kernel_param.coefs[0..check_len]
+                        .iter()
+                        .all(|x| x.is_finite()),
+                    "the kernel #{} ({:?}) produced a non-finite value",
+                    i,
+                    kernel
+                );
+            }
+        }
+
+        if self.kernels.is_empty() {
+            if let Some(input) = input {
+                kernel_param.coefs[0..input.len()].copy_from_slice(input);
+            }
+        }
+    }
+
+    /// Applies the kernels to the strided sequence `input` in `data` and
+    /// stores the result to the strided sequence `output` in `data`.
+    ///
+    /// The first kernel reads `input` directly and the last kernel writes
+    /// `output` directly (see `Kernel::transform_from_strided`). The
+    /// intermediate results are stored in `staging`, which must be at least
+    /// `buffer_len()` long. `input` and `output` must be within `data`.
+    ///
+    /// If the `checked-finite` feature is enabled and `input` is all finite,
+    /// this function asserts that every kernel keeps the intermediate results
+    /// and `output` finite.
+    pub(crate) fn run_strided(
+        &self,
+        data: &mut [T],
+        input: &StridedSequence,
+        output: &StridedSequence,
+        staging: &mut [T],
+        work_area: &mut [T],
+    ) {
+        let check_len = input.num_values();
+        let check_finite = cfg!(feature = "checked-finite")
+            && (0..check_len).all(|j| data[input.index(j)].is_finite());
+
+        let mut kernel_param = KernelParams {
+            coefs: staging,
+            work_area: work_area,
+        };
+        let last = self.kernels.len().wrapping_sub(1);
+        for (i, kernel) in self.kernels.iter().enumerate() {
+            if i == 0 {
+                kernel.transform_from_strided(data, input, &mut kernel_param);
+            } else if i == last {
+                kernel.transform_into_strided(&mut kernel_param, data, output);
+            } else {
+                kernel.transform(&mut kernel_param);
+            }
+
+            if check_finite {
+                let finite = if i != 0 && i == last {
+                    (0..output.num_values()).all(|j| data[output.index(j)].is_finite())
+                } else {
+                    kernel_param.coefs[0..check_len]
+                        .iter()
+                        .all(|x| x.is_finite())
+                };
+                assert!(
+                    finite,
+                    "the kernel #{} ({:?}) produced a non-finite value",
+                    i, kernel
+                );
+            }
         }
 
-        Ok(Self { kernels: kernels })
+        match self.kernels.len() {
+            0 => {
+                input.gather(data, kernel_param.coefs);
+                output.scatter(kernel_param.coefs, data);
+            }
+            // The only kernel has read `input` but not written `output`
+            1 => output.scatter(kernel_param.coefs, data),
+            _ => {}
+        }
     }
 
-    pub(crate) fn required_work_area_size(&self) -> usize {
+    /// Retrieves the minimum length of the scratch buffer supplied to
+    /// `transform_with_scratch`.
+    pub fn scratch_len(&self) -> usize {
         self.kernels
             .iter()
             .map(|k| k.required_work_area_size())
             .max()
             .unwrap_or(0)
     }
+
+    /// Transforms `data` in place using `scratch` as the working area.
+    ///
+    /// This function performs no memory allocation. `data` must be at least
+    /// `buffer_len()` long, and `scratch` must be at least `scratch_len()`
+    /// long. A scratch buffer may be larger than necessary, so a single buffer
+    /// can be shared by multiple `Setup`s by making it as long as the largest
+    /// `scratch_len()` among them. The contents of `scratch` are unspecified on
+    /// return.
+    ///
+    /// Returns `TransformError::BufferTooShort` if either of the buffers is
+    /// too short, in which case `data` is left intact.
+    pub fn transform_with_scratch(
+        &self,
+        data: &mut [T],
+        scratch: &mut [T],
+    ) -> Result<(), TransformError> {
+        try!(check_buffer_len(self.buffer_len(), data.len()));
+        try!(check_buffer_len(self.scratch_len(), scratch.len()));
+        self.run(data, scratch);
+        Ok(())
+    }
+
+    /// Transforms `data` in place using a scratch buffer cached by the calling
+    /// thread.
+    ///
+    /// Unlike `Env`, this function only requires a shared reference to the
+    /// `Setup`, so any number of threads can use the same `Setup` at the same
+    /// time. Each thread allocates a scratch buffer for each distinct
+    /// `scratch_len()` on the first call, and reuses it for the subsequent
+    /// calls. The buffers are kept until the thread exits.
+    ///
+    /// Returns `TransformError::BufferTooShort` if `data` is shorter than
+    /// `buffer_len()`, in which case `data` is left intact.
+    ///
+    /// Requires the `std` feature.
+    #[cfg(feature = "std")]
+    pub fn transform_concurrent(&self, data: &mut [T]) -> Result<(), TransformError> {
+        try!(check_buffer_len(self.buffer_len(), data.len()));
+        scratch::with_scratch(self.scratch_len(), |scratch| self.run(data, scratch));
+        Ok(())
+    }
 }
 
 #[cfg(test)]
@@ -305,6 +981,9 @@ mod tests {
     #[test]
     fn test_factorize_radix2() {
         assert_eq!(factorize_radix2(4), Ok(vec![2, 2]));
-        assert_eq!(factorize_radix2(5), Err(PlanError::InvalidInput));
+        assert_eq!(
+            factorize_radix2(5),
+            Err(PlanError::NotPowerOfTwo { len: 5 })
+        );
     }
 }
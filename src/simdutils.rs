@@ -1,20 +1,146 @@
-pub use packed_simd::{f32x4, f32x8, i32x4, i32x8, u32x4, u32x8, u64x4};
+//! Provides the SIMD vector types used by the x86 kernels as thin wrappers
+//! around the types of `core::arch`, along with the helper functions built on
+//! top of them.
+//!
+//! The wrappers implement the subset of the `packed_simd` interface the
+//! kernels were originally written against. The arithmetic operations map
+//! directly to the corresponding intrinsics, so the results are identical to
+//! those of the equivalent scalar operations.
 #[cfg(target_arch = "x86")]
-use std::arch::x86 as vendor;
+use core::arch::x86 as vendor;
 #[cfg(target_arch = "x86_64")]
-use std::arch::x86_64 as vendor;
-use std::mem;
+use core::arch::x86_64 as vendor;
+use core::mem;
+use core::ops::{Add, Mul, Sub};
 
 #[cfg(test)]
 use num_complex::Complex;
 
+/// Creates a vector whose lanes are taken from the concatenation of `$a` and
+/// `$b` by the constant indices `$i`. The number of the indices determines
+/// the number of the lanes of the result.
+///
+/// The lanes are selected through arrays, which the optimizer folds into a
+/// single shuffle instruction (or a few of them) as the indices are constant.
+macro_rules! shuffle {
+    ($a:expr, $b:expr, [$($i:expr),+ $(,)*]) => {{
+        let a = $crate::simdutils::SimdVector::to_array($a);
+        let b = $crate::simdutils::SimdVector::to_array($b);
+        $crate::simdutils::SimdArray::into_vector(
+            [$($crate::simdutils::select_lane(&a, &b, $i)),+]
+        )
+    }};
+}
+
+/// A SIMD vector type that can be converted from and to an array of its lanes.
+pub trait SimdVector: Copy {
+    type Array: SimdArray<Vector = Self>;
+
+    fn to_array(self) -> Self::Array;
+}
+
+/// An array of lanes that can be converted to a SIMD vector type.
+pub trait SimdArray: Copy {
+    type Vector: SimdVector<Array = Self>;
+
+    fn into_vector(self) -> Self::Vector;
+}
+
+/// Returns the `i`-th lane of the concatenation of `a` and `b`.
+#[inline(always)]
+pub fn select_lane<T: Copy>(a: &[T], b: &[T], i: usize) -> T {
+    if i < a.len() {
+        a[i]
+    } else {
+        b[i - a.len()]
+    }
+}
+
+macro_rules! simd_type {
+    ($name:ident, $raw:ident, $elem:ident, $lanes:expr, ($($lane:ident),*)) => {
+        #[allow(non_camel_case_types)]
+        #[derive(Debug, Clone, Copy)]
+        #[repr(transparent)]
+        pub struct $name(pub vendor::$raw);
+
+        impl $name {
+            #[inline]
+            #[allow(dead_code, clippy::too_many_arguments)]
+            pub fn new($($lane: $elem),*) -> Self {
+                [$($lane),*].into_vector()
+            }
+
+            #[inline]
+            #[allow(dead_code)]
+            pub fn splat(x: $elem) -> Self {
+                [x; $lanes].into_vector()
+            }
+
+            #[inline]
+            #[allow(dead_code)]
+            pub fn extract(self, i: usize) -> $elem {
+                self.to_array()[i]
+            }
+        }
+
+        impl SimdVector for $name {
+            type Array = [$elem; $lanes];
+
+            #[inline(always)]
+            fn to_array(self) -> Self::Array {
+                unsafe { mem::transmute(self) }
+            }
+        }
+
+        impl SimdArray for [$elem; $lanes] {
+            type Vector = $name;
+
+            #[inline(always)]
+            fn into_vector(self) -> Self::Vector {
+                unsafe { mem::transmute(self) }
+            }
+        }
+    };
+}
+
+simd_type!(f32x4, __m128, f32, 4, (x0, x1, x2, x3));
+simd_type!(f32x8, __m256, f32, 8, (x0, x1, x2, x3, x4, x5, x6, x7));
+simd_type!(u32x4, __m128i, u32, 4, (x0, x1, x2, x3));
+simd_type!(u32x8, __m256i, u32, 8, (x0, x1, x2, x3, x4, x5, x6, x7));
+simd_type!(u64x2, __m128i, u64, 2, (x0, x1));
+simd_type!(u64x4, __m256i, u64, 4, (x0, x1, x2, x3));
+
+macro_rules! simd_binary_op {
+    ($name:ident, $trait:ident, $method:ident, $intrinsic:ident) => {
+        impl $trait for $name {
+            type Output = Self;
+
+            #[inline]
+            fn $method(self, rhs: Self) -> Self {
+                $name(unsafe { vendor::$intrinsic(self.0, rhs.0) })
+            }
+        }
+    };
+}
+
+simd_binary_op!(f32x4, Add, add, _mm_add_ps);
+simd_binary_op!(f32x4, Sub, sub, _mm_sub_ps);
+simd_binary_op!(f32x4, Mul, mul, _mm_mul_ps);
+
+#[cfg(target_feature = "sse2")]
+simd_binary_op!(u32x4, Add, add, _mm_add_epi32);
+
+#[cfg(target_feature = "avx")]
+simd_binary_op!(f32x8, Add, add, _mm256_add_ps);
+#[cfg(target_feature = "avx")]
+simd_binary_op!(f32x8, Sub, sub, _mm256_sub_ps);
+#[cfg(target_feature = "avx")]
+simd_binary_op!(f32x8, Mul, mul, _mm256_mul_ps);
+
 #[allow(dead_code)]
 #[inline]
 pub fn f32x4_bitxor(lhs: f32x4, rhs: f32x4) -> f32x4 {
-    let x2: i32x4 = unsafe { mem::transmute(lhs) };
-    let y2: i32x4 = unsafe { mem::transmute(rhs) };
-    let z = x2 ^ y2;
-    unsafe { mem::transmute(z) }
+    f32x4(unsafe { vendor::_mm_xor_ps(lhs.0, rhs.0) })
 }
 
 #[allow(dead_code)]
@@ -23,6 +149,7 @@ pub fn f32x4_to_array(x: f32x4) -> [f32; 4] {
 }
 
 /// `neg_mask` must be `[0x80000000, 0x80000000, 0, 0]`
+#[cfg(feature = "backend-sse")]
 #[inline]
 pub fn f32x4_complex_mul_rrii(x: f32x4, y: f32x4, neg_mask: f32x4) -> f32x4 {
     let y_iirr = shuffle!(y, y, [2, 3, 4, 5]);
@@ -48,6 +175,7 @@ pub fn f32x4_complex_mul_rrii(x: f32x4, y: f32x4, neg_mask: f32x4) -> f32x4 {
     t4 + t6
 }
 
+#[cfg(feature = "backend-sse")]
 #[test]
 fn test_f32x4_complex_mul_rrii() {
     let neg_mask = unsafe { mem::transmute(u32x4::new(0x80000000, 0x80000000, 0, 0)) };
@@ -70,20 +198,13 @@ fn test_f32x4_complex_mul_rrii() {
 #[cfg(all(target_feature = "sse3", not(target_feature = "fma")))]
 #[allow(dead_code)]
 pub fn sse3_fma_f32x4_fmaddsub(x: f32x4, y: f32x4, z: f32x4) -> f32x4 {
-    let xy = unsafe { mem::transmute(x * y) };
-    let z = unsafe { mem::transmute(z) };
-    let w = unsafe { vendor::_mm_addsub_ps(xy, z) };
-    unsafe { mem::transmute(w) }
+    f32x4(unsafe { vendor::_mm_addsub_ps((x * y).0, z.0) })
 }
 
 #[cfg(all(target_feature = "sse3", target_feature = "fma"))]
 #[allow(dead_code)]
 pub fn sse3_fma_f32x4_fmaddsub(x: f32x4, y: f32x4, z: f32x4) -> f32x4 {
-    let x = unsafe { mem::transmute(x) };
-    let y = unsafe { mem::transmute(y) };
-    let z = unsafe { mem::transmute(z) };
-    let w = unsafe { vendor::_mm_fmaddsub_ps(x, y, z) };
-    unsafe { mem::transmute(w) }
+    f32x4(unsafe { vendor::_mm_fmaddsub_ps(x.0, y.0, z.0) })
 }
 
 #[cfg(target_feature = "sse3")]
@@ -128,10 +249,7 @@ pub fn avx_fma_f32x8_fmadd(x: f32x8, y: f32x8, z: f32x8) -> f32x8 {
 #[cfg(all(target_feature = "avx", target_feature = "fma"))]
 #[allow(dead_code)]
 pub fn avx_fma_f32x8_fmadd(x: f32x8, y: f32x8, z: f32x8) -> f32x8 {
-    let x: vendor::__m256 = unsafe { mem::transmute(x) };
-    let y: vendor::__m256 = unsafe { mem::transmute(y) };
-    let z: vendor::__m256 = unsafe { mem::transmute(z) };
-    unsafe { mem::transmute(vendor::_mm256_fmadd_ps(x, y, z)) }
+    f32x8(unsafe { vendor::_mm256_fmadd_ps(x.0, y.0, z.0) })
 }
 
 #[cfg(all(target_feature = "avx", not(target_feature = "fma")))]
@@ -143,27 +261,19 @@ pub fn avx_fma_f32x8_fmsub(x: f32x8, y: f32x8, z: f32x8) -> f32x8 {
 #[cfg(all(target_feature = "avx", target_feature = "fma"))]
 #[allow(dead_code)]
 pub fn avx_fma_f32x8_fmsub(x: f32x8, y: f32x8, z: f32x8) -> f32x8 {
-    let x: vendor::__m256 = unsafe { mem::transmute(x) };
-    let y: vendor::__m256 = unsafe { mem::transmute(y) };
-    let z: vendor::__m256 = unsafe { mem::transmute(z) };
-    unsafe { mem::transmute(vendor::_mm256_fmsub_ps(x, y, z)) }
+    f32x8(unsafe { vendor::_mm256_fmsub_ps(x.0, y.0, z.0) })
 }
 
 #[cfg(all(target_feature = "avx", not(target_feature = "fma")))]
 #[allow(dead_code)]
 pub fn avx_fma_f32x8_fmaddsub(x: f32x8, y: f32x8, z: f32x8) -> f32x8 {
-    let xy: vendor::__m256 = unsafe { mem::transmute(x * y) };
-    let z: vendor::__m256 = unsafe { mem::transmute(z) };
-    unsafe { mem::transmute(vendor::_mm256_addsub_ps(xy, z)) }
+    f32x8(unsafe { vendor::_mm256_addsub_ps((x * y).0, z.0) })
 }
 
 #[cfg(all(target_feature = "avx", target_feature = "fma"))]
 #[allow(dead_code)]
 pub fn avx_fma_f32x8_fmaddsub(x: f32x8, y: f32x8, z: f32x8) -> f32x8 {
-    let x: vendor::__m256 = unsafe { mem::transmute(x) };
-    let y: vendor::__m256 = unsafe { mem::transmute(y) };
-    let z: vendor::__m256 = unsafe { mem::transmute(z) };
-    unsafe { mem::transmute(vendor::_mm256_fmaddsub_ps(x, y, z)) }
+    f32x8(unsafe { vendor::_mm256_fmaddsub_ps(x.0, y.0, z.0) })
 }
 
 #[cfg(target_feature = "avx")]
@@ -212,10 +322,7 @@ fn test_avx_f32x8_complex_mul_riri() {
 #[allow(dead_code)]
 #[inline]
 pub fn avx_f32x8_bitxor(lhs: f32x8, rhs: f32x8) -> f32x8 {
-    let x2: i32x8 = unsafe { mem::transmute(lhs) };
-    let y2: i32x8 = unsafe { mem::transmute(rhs) };
-    let z = x2 ^ y2;
-    unsafe { mem::transmute(z) }
+    f32x8(unsafe { vendor::_mm256_xor_ps(lhs.0, rhs.0) })
 }
 
 #[cfg(target_feature = "avx")]
@@ -0,0 +1,205 @@
+//! Provides `StaticSetup`, which performs FFTs of a length fixed at compile
+//! time.
+use alloc::sync::Arc;
+use alloc::vec::Vec;
+use num_complex::Complex;
+
+use super::kernel::{
+    digit_reverse_in_place, new_real_fft_pre_post_process_kernel, stage_bits, Backend, Kernel,
+    KernelCreationParams, KernelParams, KernelType,
+};
+use super::{complex_slice_as_scalars_mut, Num};
+
+/// The size of the work area allocated on the stack. This is enough for any
+/// built-in radix-2 or radix-4 butterfly kernel.
+const WORK_AREA_LEN: usize = 8;
+
+/// Performs FFTs of the length `N`, which is fixed at compile time.
+///
+/// The transforms operate on arrays, so the length is checked by the type
+/// system. No scratch buffer is required, so a `StaticSetup` can be shared by
+/// any number of threads.
+///
+/// `N` must be a power of two; other values are rejected at compile time.
+///
+/// The sequence of the butterfly stages (radix 4 where possible) is derived
+/// from `N` at compile time. `new` creates a kernel for each stage in the
+/// same way as `Setup` does, so `f32` transforms use the SSE and AVX kernels
+/// where available. The custom kernels registered by
+/// `register_kernel_factory` are not used. The results match `Setup` with
+/// `Normalization::None` up to rounding errors; in particular, the backward
+/// real FFT is scaled by `1/2` in the same way.
+///
+/// The twiddle factors are stored on the heap, in the tables shared with
+/// `Setup`, so a `StaticSetup` is small regardless of `N`. `new` allocates
+/// the kernels, but the transforms don't allocate any memory.
+///
+/// # Examples
+///
+/// ```
+/// use yfft::StaticSetup;
+///
+/// let setup = StaticSetup::<f32, 8>::new();
+/// let mut data = [0.0f32, 1.0, 0.0, 0.0, 0.0, 0.0, 0.0, 0.0];
+/// setup.transform_real(&mut data, false);
+/// ```
+#[derive(Debug, Clone)]
+pub struct StaticSetup<T, const N: usize> {
+    /// The forward and backward complex FFTs.
+    complex: [Fft<T>; 2],
+    /// `None` if `N < 2`.
+    real: Option<RealFft<T>>,
+}
+
+/// The kernels of the forward and backward real FFTs of the length `N`, which
+/// are computed using the complex FFT of the length `N / 2`.
+#[derive(Debug, Clone)]
+struct RealFft<T> {
+    /// The forward and backward complex FFTs of the length `N / 2`.
+    half: [Fft<T>; 2],
+    post_process: Arc<Kernel<T>>,
+    pre_process: Arc<Kernel<T>>,
+}
+
+/// The butterfly kernels of a complex FFT in one direction.
+#[derive(Debug, Clone)]
+struct Fft<T> {
+    kernels: Vec<Arc<Kernel<T>>>,
+}
+
+impl<T: Num + 'static> Fft<T> {
+    /// Creates the decimation-in-frequency kernels of the stages given by
+    /// `stage_bits` (see `StaticSetup::STAGES`).
+    fn new(len: usize, stage_bits: &[u32], inverse: bool) -> Self {
+        let mut unit = len;
+        let kernels = stage_bits
+            .iter()
+            .map(|&bits| {
+                let radix = 1 << bits;
+                unit /= radix;
+                let kernel: Arc<Kernel<T>> = Arc::from(Kernel::new(
+                    &KernelCreationParams {
+                        size: len,
+                        kernel_type: KernelType::Dif,
+                        radix,
+                        unit,
+                        inverse,
+                    },
+                    1.0,
+                    Backend::Avx,
+                ));
+                assert!(kernel.required_work_area_size() <= WORK_AREA_LEN);
+                kernel
+            })
+            .collect();
+        Self { kernels }
+    }
+
+    /// Applies the butterfly stages to `data`. The output is in the digit
+    /// reversed order.
+    fn transform(&self, data: &mut [T]) {
+        let mut work_area = [T::zero(); WORK_AREA_LEN];
+        for kernel in &self.kernels {
+            kernel.transform(&mut KernelParams {
+                coefs: data,
+                work_area: &mut work_area,
+            });
+        }
+    }
+}
+
+impl<T: Num + 'static, const N: usize> StaticSetup<T, N> {
+    /// Fails to compile if `N` is not a power of two.
+    const VALID_LEN: () = assert!(N.is_power_of_two(), "`N` must be a power of two");
+
+    /// Fails to compile if `N` is less than `2`.
+    const VALID_REAL_LEN: () = assert!(N >= 2, "`N` must be at least 2 for real FFTs");
+
+    /// The base-2 logarithms of the radixes of the butterfly stages of the
+    /// complex FFT of the length `N`, and the number of the stages.
+    const STAGES: ([u32; 64], usize) = stage_bits(N);
+
+    /// `STAGES` for the complex FFT of the length `N / 2`, which computes the
+    /// real FFT of the length `N`. Real FFTs require `N >= 2`.
+    const HALF_STAGES: ([u32; 64], usize) = stage_bits(if N >= 2 { N / 2 } else { 1 });
+
+    /// Constructs a `StaticSetup`.
+    pub fn new() -> Self {
+        let () = Self::VALID_LEN;
+
+        let complex_stages = &Self::STAGES.0[..Self::STAGES.1];
+        let complex = [
+            Fft::new(N, complex_stages, false),
+            Fft::new(N, complex_stages, true),
+        ];
+
+        let real = if N >= 2 {
+            let half_stages = &Self::HALF_STAGES.0[..Self::HALF_STAGES.1];
+            Some(RealFft {
+                half: [
+                    Fft::new(N / 2, half_stages, false),
+                    Fft::new(N / 2, half_stages, true),
+                ],
+                post_process: Arc::from(new_real_fft_pre_post_process_kernel(
+                    N,
+                    false,
+                    1.0,
+                    Backend::Avx,
+                )),
+                pre_process: Arc::from(new_real_fft_pre_post_process_kernel(
+                    N,
+                    true,
+                    1.0,
+                    Backend::Avx,
+                )),
+            })
+        } else {
+            None
+        };
+
+        Self { complex, real }
+    }
+
+    /// Computes the complex FFT of `data` in place.
+    pub fn transform_complex(&self, data: &mut [Complex<T>; N], inverse: bool) {
+        let data = complex_slice_as_scalars_mut(data);
+        self.complex[inverse as usize].transform(data);
+        digit_reverse_in_place(data, &Self::STAGES.0[..Self::STAGES.1]);
+    }
+
+    /// Computes the real FFT of `data` in place.
+    ///
+    /// The forward (`inverse == false`) transform converts the `Real` format
+    /// to the `HalfComplex` format, and the backward transform does the
+    /// opposite. Fails to compile if `N` is less than `2`.
+    pub fn transform_real(&self, data: &mut [T; N], inverse: bool) {
+        let () = Self::VALID_REAL_LEN;
+
+        // The real FFT of the length `N` is computed using the complex FFT
+        // of the length `N / 2`
+        let real = self.real.as_ref().unwrap();
+        let half_stages = &Self::HALF_STAGES.0[..Self::HALF_STAGES.1];
+        let mut work_area = [];
+        if inverse {
+            real.pre_process.transform(&mut KernelParams {
+                coefs: data,
+                work_area: &mut work_area,
+            });
+            real.half[1].transform(data);
+            digit_reverse_in_place(data, half_stages);
+        } else {
+            real.half[0].transform(data);
+            digit_reverse_in_place(data, half_stages);
+            real.post_process.transform(&mut KernelParams {
+                coefs: data,
+                work_area: &mut work_area,
+            });
+        }
+    }
+}
+
+impl<T: Num + 'static, const N: usize> Default for StaticSetup<T, N> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -0,0 +1,289 @@
+//! Provides a dedicated type for each supported combination of data formats.
+//!
+//! Each type wraps a `Setup` and an `Env`, and only exposes the entry points
+//! that accept buffers matching its data formats. The `new` constructors use
+//! the default `TransformOptions` (e.g., no normalization); use
+//! `with_options` to specify them.
+use super::{
+    Backend, DataFormat, DataOrder, Env, Normalization, Num, Options, PlanError, Setup,
+    TransformError,
+};
+use num_complex::Complex;
+
+/// The options of `ComplexFft`, `RealForwardFft`, and `RealInverseFft`.
+///
+/// These are the fields of `Options` that don't affect the data formats or
+/// the direction, which are determined by the type.
+#[derive(Debug, Clone, Copy, Default, Eq, PartialEq, Hash)]
+pub struct TransformOptions {
+    /// See `Options::normalization`.
+    pub normalization: Normalization,
+
+    /// See `Options::max_backend`.
+    pub max_backend: Option<Backend>,
+
+    /// See `Options::deterministic`.
+    pub deterministic: bool,
+}
+
+fn new_env<T: Num>(
+    len: usize,
+    inverse: bool,
+    (input_data_format, output_data_format): (DataFormat, DataFormat),
+    (input_data_order, output_data_order): (DataOrder, DataOrder),
+    options: &TransformOptions,
+) -> Result<Env<T, Setup<T>>, PlanError> {
+    Setup::new(&Options {
+        input_data_order,
+        output_data_order,
+        input_data_format,
+        output_data_format,
+        len,
+        inverse,
+        normalization: options.normalization,
+        max_backend: options.max_backend,
+        deterministic: options.deterministic,
+    })
+    .map(Env::new)
+}
+
+/// A complex-to-complex FFT.
+///
+/// Both of the input and output are arrays of `len` complex numbers.
+#[derive(Debug)]
+pub struct ComplexFft<T> {
+    env: Env<T, Setup<T>>,
+}
+
+impl<T: Num> ComplexFft<T> {
+    /// Constructs a forward (`inverse == false`) or backward
+    /// (`inverse == true`) complex FFT of the length `len`. The input and
+    /// output are naturally ordered.
+    pub fn new(len: usize, inverse: bool) -> Result<Self, PlanError> {
+        Self::with_data_order(len, inverse, DataOrder::Natural, DataOrder::Natural)
+    }
+
+    /// Constructs a complex FFT with the specified input and output data
+    /// orders. See `Options` for the restrictions on the data orders.
+    pub fn with_data_order(
+        len: usize,
+        inverse: bool,
+        input_data_order: DataOrder,
+        output_data_order: DataOrder,
+    ) -> Result<Self, PlanError> {
+        Self::with_data_order_and_options(
+            len,
+            inverse,
+            input_data_order,
+            output_data_order,
+            &TransformOptions::default(),
+        )
+    }
+
+    /// Constructs a naturally ordered complex FFT with the specified
+    /// `TransformOptions`, e.g., to specify `normalization` or `max_backend`.
+    pub fn with_options(
+        len: usize,
+        inverse: bool,
+        options: &TransformOptions,
+    ) -> Result<Self, PlanError> {
+        Self::with_data_order_and_options(
+            len,
+            inverse,
+            DataOrder::Natural,
+            DataOrder::Natural,
+            options,
+        )
+    }
+
+    /// Constructs a complex FFT with the specified data orders and
+    /// `TransformOptions`.
+    pub fn with_data_order_and_options(
+        len: usize,
+        inverse: bool,
+        input_data_order: DataOrder,
+        output_data_order: DataOrder,
+        options: &TransformOptions,
+    ) -> Result<Self, PlanError> {
+        Ok(Self {
+            env: try!(new_env(
+                len,
+                inverse,
+                (DataFormat::Complex, DataFormat::Complex),
+                (input_data_order, output_data_order),
+                options
+            )),
+        })
+    }
+
+    /// Retrieves the number of complex numbers in the input and output, which
+    /// is never zero.
+    #[allow(clippy::len_without_is_empty)]
+    pub fn len(&self) -> usize {
+        self.setup().options().len
+    }
+
+    /// Retrieves the underlying `Setup`.
+    pub fn setup(&self) -> &Setup<T> {
+        self.env.setup()
+    }
+
+    /// Transforms `data` in place.
+    ///
+    /// Returns `TransformError::LengthMismatch` if `data.len() != self.len()`.
+    pub fn transform(&mut self, data: &mut [Complex<T>]) -> Result<(), TransformError> {
+        self.env.transform_complex(data)
+    }
+
+    /// Transforms `input` and writes the result to `output`.
+    ///
+    /// Returns `TransformError::LengthMismatch` if `input.len() != self.len()`
+    /// or `output.len() != self.len()`.
+    pub fn transform_into(
+        &mut self,
+        input: &[Complex<T>],
+        output: &mut [Complex<T>],
+    ) -> Result<(), TransformError> {
+        self.env.transform_complex_into(input, output)
+    }
+}
+
+/// A forward real-to-complex FFT producing the `HalfComplex` format.
+///
+/// The input is an array of `len` real numbers `x`. The output is an array of
+/// `len / 2` complex numbers `G` representing the first half of the spectrum
+/// `X`:
+///
+///  - `G[0].re == X[0].re` (note that `X[0]` is a real number)
+///  - `G[0].im == X[len / 2].re` (ditto)
+///  - `G[k] == X[k]` for `1 <= k < len / 2`
+///
+/// The remaining part of the spectrum is implied by `X[len - k] == conj(X[k])`.
+#[derive(Debug)]
+pub struct RealForwardFft<T> {
+    env: Env<T, Setup<T>>,
+}
+
+impl<T: Num> RealForwardFft<T> {
+    /// Constructs a real FFT of the length `len`, which must be an even
+    /// number.
+    pub fn new(len: usize) -> Result<Self, PlanError> {
+        Self::with_options(len, &TransformOptions::default())
+    }
+
+    /// Constructs a real FFT with the specified `TransformOptions`, e.g., to
+    /// specify `normalization` or `max_backend`.
+    pub fn with_options(len: usize, options: &TransformOptions) -> Result<Self, PlanError> {
+        Ok(Self {
+            env: try!(new_env(
+                len,
+                false,
+                (DataFormat::Real, DataFormat::HalfComplex),
+                (DataOrder::Natural, DataOrder::Natural),
+                options
+            )),
+        })
+    }
+
+    /// Retrieves the number of real numbers in the input, which is never
+    /// zero.
+    #[allow(clippy::len_without_is_empty)]
+    pub fn len(&self) -> usize {
+        self.setup().options().len
+    }
+
+    /// Retrieves the underlying `Setup`.
+    pub fn setup(&self) -> &Setup<T> {
+        self.env.setup()
+    }
+
+    /// Transforms `input` and writes the result to `output`.
+    ///
+    /// Returns `TransformError::LengthMismatch` if `input.len() != self.len()`
+    /// or `output.len() != self.len() / 2`.
+    pub fn transform(
+        &mut self,
+        input: &[T],
+        output: &mut [Complex<T>],
+    ) -> Result<(), TransformError> {
+        self.env.transform_real_into(input, output)
+    }
+
+    /// Transforms `data` in place. On return, `data[2 * k]` and
+    /// `data[2 * k + 1]` contain `G[k].re` and `G[k].im`, respectively.
+    ///
+    /// Returns `TransformError::LengthMismatch` if `data.len() != self.len()`.
+    pub fn transform_in_place(&mut self, data: &mut [T]) -> Result<(), TransformError> {
+        try!(self.env.check_input_len(data.len()));
+        self.env.transform(data)
+    }
+}
+
+/// A backward complex-to-real FFT consuming the `HalfComplex` format.
+///
+/// This is the inverse of `RealForwardFft` up to a scaling factor: applying
+/// `RealForwardFft` and then `RealInverseFft` created by `new` multiplies the
+/// signal by `len / 2`. To get the exact inverse, create it by `with_options`
+/// with `Normalization::ByLen`.
+///
+/// The input is an array of `len / 2` complex numbers in the layout described
+/// in `RealForwardFft`. The output is an array of `len` real numbers.
+#[derive(Debug)]
+pub struct RealInverseFft<T> {
+    env: Env<T, Setup<T>>,
+}
+
+impl<T: Num> RealInverseFft<T> {
+    /// Constructs a backward real FFT of the length `len`, which must be an
+    /// even number.
+    pub fn new(len: usize) -> Result<Self, PlanError> {
+        Self::with_options(len, &TransformOptions::default())
+    }
+
+    /// Constructs a backward real FFT with the specified `TransformOptions`,
+    /// e.g., to specify `normalization` or `max_backend`.
+    pub fn with_options(len: usize, options: &TransformOptions) -> Result<Self, PlanError> {
+        Ok(Self {
+            env: try!(new_env(
+                len,
+                true,
+                (DataFormat::HalfComplex, DataFormat::Real),
+                (DataOrder::Natural, DataOrder::Natural),
+                options
+            )),
+        })
+    }
+
+    /// Retrieves the number of real numbers in the output, which is never
+    /// zero.
+    #[allow(clippy::len_without_is_empty)]
+    pub fn len(&self) -> usize {
+        self.setup().options().len
+    }
+
+    /// Retrieves the underlying `Setup`.
+    pub fn setup(&self) -> &Setup<T> {
+        self.env.setup()
+    }
+
+    /// Transforms `input` and writes the result to `output`.
+    ///
+    /// Returns `TransformError::LengthMismatch` if
+    /// `input.len() != self.len() / 2` or `output.len() != self.len()`.
+    pub fn transform(
+        &mut self,
+        input: &[Complex<T>],
+        output: &mut [T],
+    ) -> Result<(), TransformError> {
+        self.env.transform_to_real_into(input, output)
+    }
+
+    /// Transforms `data` in place. `data[2 * k]` and `data[2 * k + 1]` must
+    /// contain `G[k].re` and `G[k].im`, respectively.
+    ///
+    /// Returns `TransformError::LengthMismatch` if `data.len() != self.len()`.
+    pub fn transform_in_place(&mut self, data: &mut [T]) -> Result<(), TransformError> {
+        try!(self.env.check_output_len(data.len()));
+        self.env.transform(data)
+    }
+}
@@ -0,0 +1,481 @@
+//! Provides a way to save the planning decisions and reuse them later.
+use std::any::TypeId;
+use std::collections::BTreeMap;
+use std::error;
+use std::fmt;
+use std::str::FromStr;
+use std::sync::RwLock;
+
+use super::setup::check_options;
+use super::{Backend, DataFormat, DataOrder, Decimation, Normalization, Num, Options, Plan, Setup};
+
+macro_rules! try_opt {
+    ($e:expr) => {
+        match $e {
+            Some(x) => x,
+            None => return None,
+        }
+    };
+}
+
+/// The version of the text format written by `Wisdom`'s `Display`
+/// implementation.
+const VERSION: u32 = 1;
+
+/// The first token of the text format.
+const MAGIC: &str = "yfft-wisdom";
+
+/// The maximum length accepted by the parser. The largest buffer of a
+/// `Setup<f64>` with this length (`len * 2` elements) spans no more than
+/// `isize::MAX` bytes.
+const MAX_LEN: usize = isize::MAX as usize / 16;
+
+/// The wisdom used by `Setup::new` and `Setup::new_measured`.
+static GLOBAL: RwLock<BTreeMap<Key, Plan>> = RwLock::new(BTreeMap::new());
+
+/// A collection of planning decisions.
+///
+/// A `Wisdom` can be converted to a stable, versioned text format using its
+/// `Display` implementation and loaded back using its `FromStr`
+/// implementation. Each decision is keyed by the precision, the length, the
+/// data formats and orders, the direction, and the CPU feature set the crate
+/// was compiled for, so a `Wisdom` created on a different machine or build is
+/// simply ignored. Only the decisions for `f32` and `f64` are recorded.
+///
+/// The decisions are used by `Setup::new` and `Setup::new_measured` after
+/// they are registered by `Wisdom::register`. `Setup::new_measured` also
+/// registers the result of the measurement automatically. Registered
+/// decisions are not used if `Options::deterministic` is set.
+///
+/// ```
+/// use yfft::*;
+/// use std::time::Duration;
+///
+/// let options = Options {
+///     input_data_order: DataOrder::Natural,
+///     output_data_order: DataOrder::Natural,
+///     input_data_format: DataFormat::Complex,
+///     output_data_format: DataFormat::Complex,
+///     len: 64,
+///     inverse: false,
+///     normalization: Normalization::None,
+///     max_backend: None,
+///     deterministic: false,
+/// };
+/// let setup: Setup<f32> = Setup::new_measured(&options, Duration::from_millis(10)).unwrap();
+///
+/// // Save the result...
+/// let mut wisdom = Wisdom::new();
+/// wisdom.insert(&setup);
+/// let text = wisdom.to_string();
+///
+/// // ... and load it on the next run
+/// let wisdom: Wisdom = text.parse().unwrap();
+/// wisdom.register();
+/// let setup2: Setup<f32> = Setup::new(&options).unwrap();
+/// assert_eq!(setup.plan(), setup2.plan());
+/// ```
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Wisdom {
+    entries: BTreeMap<Key, Plan>,
+}
+
+#[derive(Debug, Clone, Ord, PartialOrd, Eq, PartialEq)]
+struct Key {
+    precision: &'static str,
+    features: String,
+    len: usize,
+    input_data_order: DataOrder,
+    output_data_order: DataOrder,
+    input_data_format: DataFormat,
+    output_data_format: DataFormat,
+    inverse: bool,
+}
+
+impl Key {
+    /// Returns `None` if the precision of `T` can't be recorded (see
+    /// `precision`).
+    fn new<T: Num>(options: &Options) -> Option<Self> {
+        Some(Self {
+            precision: try_opt!(precision::<T>()),
+            features: cpu_features(),
+            len: options.len,
+            input_data_order: options.input_data_order,
+            output_data_order: options.output_data_order,
+            input_data_format: options.input_data_format,
+            output_data_format: options.output_data_format,
+            inverse: options.inverse,
+        })
+    }
+
+    fn options(&self) -> Options {
+        Options {
+            input_data_order: self.input_data_order,
+            output_data_order: self.output_data_order,
+            input_data_format: self.input_data_format,
+            output_data_format: self.output_data_format,
+            len: self.len,
+            inverse: self.inverse,
+            normalization: Normalization::None,
+            max_backend: None,
+            deterministic: false,
+        }
+    }
+}
+
+/// Names the scalar type in the text format. Only `f32` and `f64` are
+/// supported; the decisions for other `Num` types are not recorded.
+fn precision<T: Num>() -> Option<&'static str> {
+    let id = TypeId::of::<T>();
+    if id == TypeId::of::<f32>() {
+        Some("f32")
+    } else if id == TypeId::of::<f64>() {
+        Some("f64")
+    } else {
+        None
+    }
+}
+
+/// Describes the target architecture and features the kernels were compiled
+/// for. The kernels are selected at compile time, so this is what determines
+/// their performance characteristics.
+fn cpu_features() -> String {
+    let mut features = String::from(::std::env::consts::ARCH);
+    for &(name, enabled) in &[
+        ("sse", cfg!(target_feature = "sse")),
+        ("sse2", cfg!(target_feature = "sse2")),
+        ("sse3", cfg!(target_feature = "sse3")),
+        ("avx", cfg!(target_feature = "avx")),
+        ("fma", cfg!(target_feature = "fma")),
+    ] {
+        if enabled {
+            features.push('+');
+            features.push_str(name);
+        }
+    }
+    features
+}
+
+impl Wisdom {
+    /// Constructs an empty `Wisdom`.
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    /// Retrieves the number of decisions.
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// Returns `true` if there are no decisions.
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Records the plan of `setup`, replacing the existing one for the same
+    /// key. Does nothing unless `T` is `f32` or `f64`.
+    pub fn insert<T: Num + 'static>(&mut self, setup: &Setup<T>) {
+        if let Some(key) = Key::new::<T>(setup.options()) {
+            self.entries.insert(key, setup.plan().clone());
+        }
+    }
+
+    /// Retrieves the plan recorded for `options` on the current CPU feature
+    /// set.
+    pub fn get<T: Num>(&self, options: &Options) -> Option<&Plan> {
+        self.entries.get(&try_opt!(Key::new::<T>(options)))
+    }
+
+    /// Copies the decisions of `other` to `self`, replacing the existing ones
+    /// for the same keys.
+    pub fn merge(&mut self, other: &Wisdom) {
+        for (key, plan) in &other.entries {
+            self.entries.insert(key.clone(), plan.clone());
+        }
+    }
+
+    /// Makes the decisions available to `Setup::new` and
+    /// `Setup::new_measured`. They are merged with the previously registered
+    /// ones.
+    pub fn register(&self) {
+        let mut global = GLOBAL.write().unwrap();
+        for (key, plan) in &self.entries {
+            global.insert(key.clone(), plan.clone());
+        }
+    }
+
+    /// Retrieves a copy of the registered decisions, including the ones
+    /// registered by `Setup::new_measured`.
+    pub fn global() -> Self {
+        Self {
+            entries: GLOBAL.read().unwrap().clone(),
+        }
+    }
+
+    /// Removes all registered decisions.
+    pub fn forget_global() {
+        GLOBAL.write().unwrap().clear();
+    }
+}
+
+/// Retrieves the registered plan for `options`.
+pub(crate) fn lookup<T: Num>(options: &Options) -> Option<Plan> {
+    if options.deterministic {
+        return None;
+    }
+    let key = try_opt!(Key::new::<T>(options));
+    GLOBAL.read().unwrap().get(&key).cloned()
+}
+
+/// Registers the plan of `setup`.
+pub(crate) fn record<T: Num + 'static>(setup: &Setup<T>) {
+    if let Some(key) = Key::new::<T>(setup.options()) {
+        GLOBAL.write().unwrap().insert(key, setup.plan().clone());
+    }
+}
+
+/// The error type which is returned when parsing a `Wisdom` fails.
+#[derive(Debug, Clone, Copy, Ord, PartialOrd, Eq, PartialEq)]
+pub enum WisdomError {
+    /// The text doesn't start with the `yfft-wisdom` header.
+    MissingHeader,
+
+    /// The text was written in a format version this version of the library
+    /// doesn't understand.
+    UnsupportedVersion { version: u32 },
+
+    /// The line (counted from 1) is malformed or describes an invalid plan.
+    Malformed { line: usize },
+}
+
+impl fmt::Display for WisdomError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            WisdomError::MissingHeader => write!(f, "The wisdom header is missing."),
+            WisdomError::UnsupportedVersion { version } => write!(
+                f,
+                "The wisdom format version {} is not supported (expected {}).",
+                version, VERSION
+            ),
+            WisdomError::Malformed { line } => write!(f, "The wisdom line {} is malformed.", line),
+        }
+    }
+}
+
+impl error::Error for WisdomError {
+    fn description(&self) -> &str {
+        match *self {
+            WisdomError::MissingHeader => "Missing wisdom header",
+            WisdomError::UnsupportedVersion { .. } => "Unsupported wisdom version",
+            WisdomError::Malformed { .. } => "Malformed wisdom",
+        }
+    }
+}
+
+fn order_name(x: DataOrder) -> &'static str {
+    match x {
+        DataOrder::Natural => "natural",
+        DataOrder::Swizzled => "swizzled",
+        DataOrder::BitReversed => "bit-reversed",
+    }
+}
+
+fn format_name(x: DataFormat) -> &'static str {
+    match x {
+        DataFormat::Complex => "complex",
+        DataFormat::Real => "real",
+        DataFormat::HalfComplex => "half-complex",
+    }
+}
+
+fn decimation_name(x: Decimation) -> &'static str {
+    match x {
+        Decimation::Time => "time",
+        Decimation::Frequency => "frequency",
+    }
+}
+
+fn backend_name(x: Backend) -> &'static str {
+    match x {
+        Backend::Generic => "generic",
+        Backend::Generic2 => "generic2",
+        Backend::Sse => "sse",
+        Backend::Sse3 => "sse3",
+        Backend::Avx => "avx",
+        Backend::Custom => "custom",
+    }
+}
+
+/// Finds the value whose name is `name`.
+fn parse_name<T: Copy>(values: &[T], to_name: fn(T) -> &'static str, name: &str) -> Option<T> {
+    values.iter().cloned().find(|&x| to_name(x) == name)
+}
+
+/// Writes the text format. Each line other than the header describes a single
+/// decision as a list of `key=value` pairs:
+///
+/// ```text
+/// yfft-wisdom 1
+/// plan precision=f32 features=x86_64+sse+sse2 len=64 input=natural/complex output=natural/complex inverse=false decimation=frequency radixes=4,4,4 bit-reversal=avx
+/// ```
+impl fmt::Display for Wisdom {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        try!(writeln!(f, "{} {}", MAGIC, VERSION));
+        for (key, plan) in &self.entries {
+            let radixes: Vec<String> = plan.radixes.iter().map(|r| r.to_string()).collect();
+            try!(writeln!(
+                f,
+                "plan precision={} features={} len={} input={}/{} output={}/{} inverse={} \
+                 decimation={} radixes={} bit-reversal={}",
+                key.precision,
+                key.features,
+                key.len,
+                order_name(key.input_data_order),
+                format_name(key.input_data_format),
+                order_name(key.output_data_order),
+                format_name(key.output_data_format),
+                key.inverse,
+                decimation_name(plan.decimation),
+                radixes.join(","),
+                backend_name(plan.bit_reversal_backend),
+            ));
+        }
+        Ok(())
+    }
+}
+
+impl FromStr for Wisdom {
+    type Err = WisdomError;
+
+    /// Parses the text format. Empty lines and lines starting with `#` are
+    /// ignored.
+    ///
+    /// Never panics. A line describing a plan `Setup::with_plan` would reject
+    /// (including a length or radixes too large to represent) results in
+    /// `WisdomError::Malformed`.
+    fn from_str(s: &str) -> Result<Self, WisdomError> {
+        let mut lines = s
+            .lines()
+            .enumerate()
+            .map(|(i, line)| (i + 1, line.trim()))
+            .filter(|&(_, line)| !line.is_empty() && !line.starts_with('#'));
+
+        let version = match lines.next().map(|(_, line)| line.split_whitespace()) {
+            Some(mut tokens) => {
+                if tokens.next() != Some(MAGIC) {
+                    return Err(WisdomError::MissingHeader);
+                }
+                tokens.next().and_then(|x| x.parse().ok())
+            }
+            None => return Err(WisdomError::MissingHeader),
+        };
+        match version {
+            Some(VERSION) => {}
+            Some(version) => return Err(WisdomError::UnsupportedVersion { version }),
+            None => return Err(WisdomError::MissingHeader),
+        }
+
+        let mut wisdom = Wisdom::new();
+        for (line_number, line) in lines {
+            let (key, plan) =
+                try!(parse_line(line).ok_or(WisdomError::Malformed { line: line_number }));
+            wisdom.entries.insert(key, plan);
+        }
+        Ok(wisdom)
+    }
+}
+
+fn parse_line(line: &str) -> Option<(Key, Plan)> {
+    let mut tokens = line.split_whitespace();
+    if tokens.next() != Some("plan") {
+        return None;
+    }
+
+    let mut fields = BTreeMap::new();
+    for token in tokens {
+        let mut parts = token.splitn(2, '=');
+        let name = parts.next().unwrap();
+        let value = try_opt!(parts.next());
+        if fields.insert(name, value).is_some() {
+            return None;
+        }
+    }
+    let field = |name: &str| fields.get(name).cloned();
+
+    const ORDERS: &[DataOrder] = &[
+        DataOrder::Natural,
+        DataOrder::Swizzled,
+        DataOrder::BitReversed,
+    ];
+    const FORMATS: &[DataFormat] = &[
+        DataFormat::Complex,
+        DataFormat::Real,
+        DataFormat::HalfComplex,
+    ];
+    let parse_layout = |value: &str| -> Option<(DataOrder, DataFormat)> {
+        let mut parts = value.splitn(2, '/');
+        let order = try_opt!(parse_name(ORDERS, order_name, parts.next().unwrap()));
+        let format = try_opt!(parse_name(FORMATS, format_name, try_opt!(parts.next())));
+        Some((order, format))
+    };
+
+    let precision = match try_opt!(field("precision")) {
+        "f32" => "f32",
+        "f64" => "f64",
+        _ => return None,
+    };
+    let (input_data_order, input_data_format) = try_opt!(parse_layout(try_opt!(field("input"))));
+    let (output_data_order, output_data_format) = try_opt!(parse_layout(try_opt!(field("output"))));
+
+    let key = Key {
+        precision,
+        features: try_opt!(field("features")).to_owned(),
+        len: try_opt!(try_opt!(field("len")).parse().ok()),
+        input_data_order,
+        output_data_order,
+        input_data_format,
+        output_data_format,
+        inverse: try_opt!(try_opt!(field("inverse")).parse().ok()),
+    };
+
+    let radixes = try_opt!(field("radixes"));
+    let plan = Plan {
+        radixes: if radixes.is_empty() {
+            Vec::new()
+        } else {
+            try_opt!(radixes.split(',').map(|x| x.parse().ok()).collect())
+        },
+        decimation: try_opt!(parse_name(
+            &[Decimation::Time, Decimation::Frequency],
+            decimation_name,
+            try_opt!(field("decimation")),
+        )),
+        bit_reversal_backend: try_opt!(parse_name(
+            &[
+                Backend::Generic,
+                Backend::Generic2,
+                Backend::Sse,
+                Backend::Sse3,
+                Backend::Avx,
+                Backend::Custom,
+            ],
+            backend_name,
+            try_opt!(field("bit-reversal")),
+        )),
+    };
+
+    // Reject the plans `Setup::with_plan` would reject. The text may come
+    // from anywhere, so the checks must not panic or take long for any
+    // input: the length is bounded, and the radixes are validated by
+    // `Plan::check` (which is overflow-safe) instead of factorizing the
+    // length.
+    if key.len > MAX_LEN {
+        return None;
+    }
+    let options = key.options();
+    if check_options(&options).is_err() || plan.check(&options).is_err() {
+        return None;
+    }
+
+    Some((key, plan))
+}
@@ -0,0 +1,193 @@
+//! Measures the accuracy of the transforms against a high-precision
+//! reference. Run with `--nocapture` to see the RMS and maximum errors.
+extern crate num_complex;
+extern crate yfft;
+
+use num_complex::Complex;
+use std::f64::consts::PI;
+use yfft::*;
+
+type C64 = Complex<f64>;
+
+/// Computes `exp(-2πik/len)` without accumulating the rounding error of `k`.
+fn exp_neg_2pi_i(k: usize, len: usize) -> C64 {
+    let angle = -2.0 * PI * (k % len) as f64 / len as f64;
+    Complex::new(angle.cos(), angle.sin())
+}
+
+/// A straightforward mixed-radix FFT evaluated in double precision. Each
+/// twiddle factor is computed directly, so the error only grows with the
+/// number of passes.
+fn reference_fft(input: &[C64]) -> Vec<C64> {
+    let len = input.len();
+    let twiddles: Vec<C64> = (0..len).map(|k| exp_neg_2pi_i(k, len)).collect();
+    reference_fft_inner(input, &twiddles)
+}
+
+/// `twiddles` contains `exp(-2πik/n)` for `0 <= k < n`, where `n` is a
+/// multiple of `input.len()`.
+fn reference_fft_inner(input: &[C64], twiddles: &[C64]) -> Vec<C64> {
+    let len = input.len();
+    if len <= 1 {
+        return input.to_vec();
+    }
+    let radix = (2..len + 1).find(|r| len % r == 0).unwrap();
+    let sub_len = len / radix;
+    let stride = twiddles.len() / len;
+
+    let subs: Vec<Vec<C64>> = (0..radix)
+        .map(|r| {
+            let sub: Vec<C64> = (0..sub_len).map(|i| input[i * radix + r]).collect();
+            reference_fft_inner(&sub, twiddles)
+        })
+        .collect();
+
+    (0..len)
+        .map(|k| {
+            subs.iter()
+                .enumerate()
+                .map(|(r, sub)| sub[k % sub_len] * twiddles[r * k % len * stride])
+                .fold(Complex::new(0.0, 0.0), |x, y| x + y)
+        })
+        .collect()
+}
+
+/// A deterministic pseudo-random sequence in `[-1, 1)`.
+fn random_input(len: usize) -> Vec<C64> {
+    let mut state = 0x2545f4914f6cdd1du64;
+    let mut next = || {
+        state ^= state << 13;
+        state ^= state >> 7;
+        state ^= state << 17;
+        (state >> 11) as f64 / (1u64 << 52) as f64 - 1.0
+    };
+    (0..len).map(|_| Complex::new(next(), next())).collect()
+}
+
+#[derive(Debug)]
+struct ErrorStats {
+    /// The RMS error relative to the RMS of the expected values.
+    rms: f64,
+    /// The maximum absolute error relative to the maximum magnitude of the
+    /// expected values.
+    max: f64,
+}
+
+fn error_stats(got: &[C64], expected: &[C64]) -> ErrorStats {
+    assert_eq!(got.len(), expected.len());
+    let len = expected.len() as f64;
+    let rms = (expected.iter().map(|x| x.norm_sqr()).sum::<f64>() / len).sqrt();
+    let peak = expected.iter().map(|x| x.norm()).fold(0.0, f64::max);
+    let sq_err: f64 = got
+        .iter()
+        .zip(expected.iter())
+        .map(|(a, b)| (a - b).norm_sqr())
+        .sum();
+    let max_err = got
+        .iter()
+        .zip(expected.iter())
+        .map(|(a, b)| (a - b).norm())
+        .fold(0.0, f64::max);
+    ErrorStats {
+        rms: (sq_err / len).sqrt() / rms,
+        max: max_err / peak,
+    }
+}
+
+fn transform<T: Num>(input: &[C64], inverse: bool, max_backend: Option<Backend>) -> Vec<C64> {
+    let setup = Setup::<T>::new(&Options {
+        input_data_order: DataOrder::Natural,
+        output_data_order: DataOrder::Natural,
+        input_data_format: DataFormat::Complex,
+        output_data_format: DataFormat::Complex,
+        len: input.len(),
+        inverse,
+        normalization: Normalization::None,
+        max_backend,
+        deterministic: false,
+    })
+    .unwrap();
+    let mut data: Vec<T> = input
+        .iter()
+        .flat_map(|x| vec![T::from(x.re).unwrap(), T::from(x.im).unwrap()])
+        .collect();
+    Env::new(&setup).transform(&mut data).unwrap();
+    data.chunks(2)
+        .map(|x| Complex::new(x[0].to_f64().unwrap(), x[1].to_f64().unwrap()))
+        .collect()
+}
+
+const BACKENDS: &[Option<Backend>] = &[Some(Backend::Generic), Some(Backend::Generic2), None];
+
+const SIZES: &[usize] = &[
+    1 << 4,
+    1 << 8,
+    1 << 12,
+    1 << 16,
+    1 << 20,
+    3 << 10,
+    5 * 7 * 9 * 11,
+];
+
+/// Compares single precision transforms against the double precision
+/// reference, which is accurate enough to be considered exact here.
+#[test]
+fn accuracy_f32() {
+    for &len in SIZES {
+        let input = random_input(len);
+        let expected = reference_fft(&input);
+
+        // The backward transform of `conj(x)` is `conj(fft(x))`
+        let input_conj: Vec<C64> = input.iter().map(|x| x.conj()).collect();
+        let expected_conj: Vec<C64> = expected.iter().map(|x| x.conj()).collect();
+
+        for &max_backend in BACKENDS {
+            let stats = error_stats(&transform::<f32>(&input, false, max_backend), &expected);
+            println!(
+                "f32 forward  len = {:>7}, {:?}: {:?}",
+                len, max_backend, stats
+            );
+            assert!(stats.rms < 5.0e-7 && stats.max < 2.0e-6, "{:?}", stats);
+
+            let stats = error_stats(
+                &transform::<f32>(&input_conj, true, max_backend),
+                &expected_conj,
+            );
+            println!(
+                "f32 backward len = {:>7}, {:?}: {:?}",
+                len, max_backend, stats
+            );
+            assert!(stats.rms < 5.0e-7 && stats.max < 2.0e-6, "{:?}", stats);
+        }
+    }
+}
+
+/// The reference is not more precise than double precision transforms, so
+/// they are checked using an input whose spectrum is known exactly: a sum of
+/// complex sinusoids with integral frequencies.
+#[test]
+fn accuracy_f64() {
+    for &len in SIZES {
+        let tones = [(1, 1.0), (len / 3, -0.5), (len - 5, 0.25)];
+        let input: Vec<C64> = (0..len)
+            .map(|i| {
+                tones
+                    .iter()
+                    .map(|&(freq, amp)| exp_neg_2pi_i(len - freq * i % len, len) * amp)
+                    .fold(Complex::new(0.0, 0.0), |x, y| x + y)
+            })
+            .collect();
+        let mut expected = vec![Complex::new(0.0, 0.0); len];
+        for &(freq, amp) in &tones {
+            expected[freq].re += amp * len as f64;
+        }
+        for &max_backend in BACKENDS {
+            let stats = error_stats(&transform::<f64>(&input, false, max_backend), &expected);
+            println!(
+                "f64 forward  len = {:>7}, {:?}: {:?}",
+                len, max_backend, stats
+            );
+            assert!(stats.rms < 1.0e-15 && stats.max < 1.0e-14, "{:?}", stats);
+        }
+    }
+}
@@ -0,0 +1,259 @@
+extern crate yfft;
+
+use yfft::*;
+
+fn assert_num_slice_approx_eq<T: yfft::Num>(got: &[T], expected: &[T], releps: T) {
+    assert_eq!(got.len(), expected.len());
+    // We can't use `Iterator::max()` because T doesn't implement Ord
+    let maxabs = expected
+        .iter()
+        .map(|x| x.abs())
+        .fold(T::zero() / T::zero(), |x, y| x.max(y))
+        + T::from(0.01).unwrap();
+    let eps = maxabs * releps;
+    for i in 0..got.len() {
+        let a = got[i];
+        let b = expected[i];
+        if (a - b).abs() > eps {
+            assert!(
+                (a - b).abs() < eps,
+                "assertion failed: `got almost equal to expected` \
+                 (got: `{:?}`, expected: `{:?}`, diff=`{:?}`)",
+                got,
+                expected,
+                (a - b).abs()
+            );
+        }
+    }
+}
+
+fn test_pattern<T: yfft::Num>(size: usize) -> Vec<T> {
+    (0..size)
+        .map(|x| -> T { T::from((x * 3 + 7) ^ (x * 7 + 3) ^ (x >> 1)).unwrap() })
+        .collect()
+}
+
+const BACKENDS: &[Backend] = &[
+    Backend::Generic,
+    Backend::Generic2,
+    Backend::Sse,
+    Backend::Sse3,
+    Backend::Avx,
+];
+
+/// The most basic backend that can compute every butterfly pass in this
+/// build of the library.
+fn reference_backend() -> Backend {
+    if cfg!(feature = "backend-generic") {
+        Backend::Generic
+    } else {
+        Backend::Generic2
+    }
+}
+
+/// Returns whether the butterfly kernels of `backend` are compiled in.
+fn is_backend_compiled(backend: Backend) -> bool {
+    match backend {
+        Backend::Generic => cfg!(feature = "backend-generic"),
+        Backend::Generic2 => cfg!(feature = "backend-generic2"),
+        Backend::Sse => cfg!(feature = "backend-sse"),
+        Backend::Sse3 => cfg!(feature = "backend-sse3"),
+        Backend::Avx => cfg!(feature = "backend-avx"),
+        // No kernel factories are registered by these tests
+        Backend::Custom => false,
+    }
+}
+
+fn compare_backends(input_data_format: DataFormat, output_data_format: DataFormat, inverse: bool) {
+    for size_ref in &[2, 4, 8, 16, 32, 64, 128, 256, 1024] {
+        let size = *size_ref;
+        let new_setup = |max_backend| {
+            Setup::<f32>::new(&Options {
+                input_data_order: DataOrder::Natural,
+                output_data_order: DataOrder::Natural,
+                input_data_format,
+                output_data_format,
+                len: size,
+                inverse,
+                normalization: Normalization::None,
+                max_backend,
+                deterministic: false,
+            })
+            .unwrap()
+        };
+
+        let reference = new_setup(Some(reference_backend()));
+        let pat = test_pattern::<f32>(reference.input_len());
+        let mut expected = vec![0.0; reference.output_len()];
+        Env::new(&reference)
+            .transform_into(&pat, &mut expected)
+            .unwrap();
+
+        for &backend in BACKENDS {
+            if backend < reference_backend() {
+                continue;
+            }
+            let setup = new_setup(Some(backend));
+            let report = setup.report();
+            for stage in &report.stages {
+                assert!(
+                    stage.backend <= backend,
+                    "{:?} was used although the limit is {:?}",
+                    stage.backend,
+                    backend
+                );
+            }
+
+            let mut got = vec![0.0; setup.output_len()];
+            Env::new(&setup).transform_into(&pat, &mut got).unwrap();
+            assert_num_slice_approx_eq(&got, &expected, 1.0e-3);
+        }
+    }
+}
+
+#[test]
+fn backend_complex_forward() {
+    compare_backends(DataFormat::Complex, DataFormat::Complex, false);
+}
+
+#[test]
+fn backend_complex_backward() {
+    compare_backends(DataFormat::Complex, DataFormat::Complex, true);
+}
+
+#[test]
+fn backend_real_forward() {
+    compare_backends(DataFormat::Real, DataFormat::HalfComplex, false);
+}
+
+#[test]
+fn backend_real_backward() {
+    compare_backends(DataFormat::HalfComplex, DataFormat::Real, true);
+}
+
+#[test]
+#[cfg(feature = "backend-generic")]
+fn backend_generic_only() {
+    let setup = Setup::<f32>::new(&Options {
+        input_data_order: DataOrder::Natural,
+        output_data_order: DataOrder::Natural,
+        input_data_format: DataFormat::Complex,
+        output_data_format: DataFormat::Complex,
+        len: 64,
+        inverse: false,
+        normalization: Normalization::None,
+        max_backend: Some(Backend::Generic),
+        deterministic: false,
+    })
+    .unwrap();
+    assert!(setup
+        .report()
+        .stages
+        .iter()
+        .all(|s| s.backend == Backend::Generic));
+}
+
+#[test]
+fn backend_compiled_only() {
+    // Covers every kind of stage, so a built-in kernel that doesn't override
+    // `Kernel::backend` is reported as `Backend::Custom` and caught here
+    let cases = [
+        (
+            DataFormat::Complex,
+            DataFormat::Complex,
+            false,
+            Normalization::None,
+        ),
+        (
+            DataFormat::Complex,
+            DataFormat::Complex,
+            true,
+            Normalization::ByLen,
+        ),
+        (
+            DataFormat::Real,
+            DataFormat::HalfComplex,
+            false,
+            Normalization::None,
+        ),
+        (
+            DataFormat::HalfComplex,
+            DataFormat::Real,
+            true,
+            Normalization::ByLen,
+        ),
+        (
+            DataFormat::Real,
+            DataFormat::Complex,
+            false,
+            Normalization::None,
+        ),
+        (
+            DataFormat::HalfComplex,
+            DataFormat::Complex,
+            true,
+            Normalization::None,
+        ),
+    ];
+    for &(input_data_format, output_data_format, inverse, normalization) in &cases {
+        for &len in &[2, 4, 8, 64, 1024] {
+            let setup = Setup::<f32>::new(&Options {
+                input_data_order: DataOrder::Natural,
+                output_data_order: DataOrder::Natural,
+                input_data_format,
+                output_data_format,
+                len,
+                inverse,
+                normalization,
+                max_backend: None,
+                deterministic: false,
+            })
+            .unwrap();
+            for stage in &setup.report().stages {
+                // The generic non-butterfly kernels are always available
+                let is_butterfly = matches!(stage.kind, StageKind::Butterfly { .. });
+                if is_butterfly || stage.backend != Backend::Generic {
+                    assert!(
+                        is_backend_compiled(stage.backend),
+                        "{:?} was used by {:?} although it is not compiled in",
+                        stage.backend,
+                        stage.kind
+                    );
+                }
+            }
+        }
+    }
+}
+
+#[test]
+fn backend_unsupported_radix() {
+    let options = Options {
+        input_data_order: DataOrder::Natural,
+        output_data_order: DataOrder::Natural,
+        input_data_format: DataFormat::Complex,
+        output_data_format: DataFormat::Complex,
+        len: 3,
+        inverse: false,
+        normalization: Normalization::None,
+        max_backend: None,
+        deterministic: false,
+    };
+    let generic_only = Options {
+        len: 8,
+        max_backend: Some(Backend::Generic),
+        ..options
+    };
+    if cfg!(feature = "backend-generic") {
+        assert!(Setup::<f32>::new(&options).is_ok());
+        assert!(Setup::<f32>::new(&generic_only).is_ok());
+    } else {
+        assert_eq!(
+            Setup::<f32>::new(&options).err(),
+            Some(PlanError::UnsupportedRadix { radix: 3 })
+        );
+        assert_eq!(
+            Setup::<f32>::check(&generic_only),
+            Err(PlanError::UnsupportedRadix { radix: 2 })
+        );
+    }
+}
@@ -0,0 +1,85 @@
+#![cfg(feature = "checked")]
+extern crate yfft;
+
+use yfft::*;
+
+fn options(len: usize, input_data_format: DataFormat, output_data_format: DataFormat) -> Options {
+    Options {
+        input_data_order: DataOrder::Natural,
+        output_data_order: DataOrder::Natural,
+        input_data_format,
+        output_data_format,
+        len,
+        inverse: false,
+        normalization: Normalization::None,
+        max_backend: None,
+        deterministic: false,
+    }
+}
+
+#[test]
+#[should_panic(expected = "the buffer is too short for the setup (len: 120, required: 128)")]
+fn short_buffer_detected() {
+    let setup: Setup<f32> =
+        Setup::new(&options(64, DataFormat::Complex, DataFormat::Complex)).unwrap();
+    let mut env = Env::new(&setup);
+
+    let mut data = vec![0.0f32; 120];
+    unsafe { env.transform_unchecked(&mut data) };
+}
+
+#[test]
+#[should_panic(expected = "the buffer is too short for the setup (len: 60, required: 64)")]
+fn short_real_buffer_detected() {
+    let setup: Setup<f32> =
+        Setup::new(&options(64, DataFormat::Real, DataFormat::HalfComplex)).unwrap();
+    let mut env = Env::new(&setup);
+
+    let mut data = vec![0.0f32; 60];
+    unsafe { env.transform_unchecked(&mut data) };
+}
+
+#[test]
+#[cfg(feature = "checked-finite")]
+#[should_panic(expected = "non-finite")]
+fn overflow_detected() {
+    let setup: Setup<f32> = Setup::new(&Options {
+        input_data_order: DataOrder::Natural,
+        output_data_order: DataOrder::Natural,
+        input_data_format: DataFormat::Complex,
+        output_data_format: DataFormat::Complex,
+        len: 64,
+        inverse: false,
+        normalization: Normalization::None,
+        max_backend: None,
+        deterministic: false,
+    })
+    .unwrap();
+    let mut env = Env::new(&setup);
+
+    // The DC component overflows
+    let mut data = vec![std::f32::MAX; 128];
+    env.transform(&mut data).unwrap();
+}
+
+#[test]
+#[cfg(feature = "checked-finite")]
+fn non_finite_input_ignored() {
+    let setup: Setup<f32> = Setup::new(&Options {
+        input_data_order: DataOrder::Natural,
+        output_data_order: DataOrder::Natural,
+        input_data_format: DataFormat::Complex,
+        output_data_format: DataFormat::Complex,
+        len: 64,
+        inverse: false,
+        normalization: Normalization::None,
+        max_backend: None,
+        deterministic: false,
+    })
+    .unwrap();
+    let mut env = Env::new(&setup);
+
+    let mut data = vec![0.0f32; 128];
+    data[4] = std::f32::NAN;
+    env.transform(&mut data).unwrap();
+}
@@ -0,0 +1,229 @@
+extern crate num_complex;
+extern crate yfft;
+
+use num_complex::Complex;
+use std::f64::consts::PI;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use yfft::*;
+
+/// Evaluates the definition of the butterfly pass directly.
+#[derive(Debug)]
+struct NaiveKernel {
+    cparams: KernelCreationParams,
+}
+
+impl Kernel<f64> for NaiveKernel {
+    fn transform(&self, params: &mut KernelParams<f64>) {
+        let cparams = &self.cparams;
+        let (radix, unit) = (cparams.radix, cparams.unit);
+        let len = radix * unit;
+        let sign = if cparams.inverse { 1.0 } else { -1.0 };
+        let data = &mut params.coefs[0..cparams.size * 2];
+        let wa = &mut params.work_area[0..radix * 2];
+
+        for x in (0..cparams.size).step_by(len) {
+            for y in 0..unit {
+                for w in 0..radix {
+                    let i = x + y + w * unit;
+                    wa[w * 2] = data[i * 2];
+                    wa[w * 2 + 1] = data[i * 2 + 1];
+                }
+                for z in 0..radix {
+                    let mut c = Complex::new(0.0, 0.0);
+                    for w in 0..radix {
+                        let k = match cparams.kernel_type {
+                            KernelType::Dit => (y + z * unit) * w,
+                            KernelType::Dif => z * (y + w * unit),
+                        } % len;
+                        let theta = sign * 2.0 * PI * k as f64 / len as f64;
+                        let t = Complex::new(theta.cos(), theta.sin());
+                        c += t * Complex::new(wa[w * 2], wa[w * 2 + 1]);
+                    }
+                    let i = x + y + z * unit;
+                    data[i * 2] = c.re;
+                    data[i * 2 + 1] = c.im;
+                }
+            }
+        }
+    }
+
+    fn required_work_area_size(&self) -> usize {
+        self.cparams.radix * 2
+    }
+
+    fn backend(&self) -> Backend {
+        Backend::Custom
+    }
+}
+
+/// Leaves the sequence unchanged.
+#[derive(Debug)]
+struct IdentityKernel;
+
+impl Kernel<f64> for IdentityKernel {
+    fn transform(&self, _: &mut KernelParams<f64>) {}
+}
+
+/// Writes past the end of the sequence.
+#[derive(Debug)]
+struct OverrunKernel {
+    size: usize,
+}
+
+impl Kernel<f64> for OverrunKernel {
+    fn transform(&self, params: &mut KernelParams<f64>) {
+        params.coefs[self.size * 2] = 0.0;
+    }
+}
+
+fn cparams(
+    size: usize,
+    kernel_type: KernelType,
+    radix: usize,
+    unit: usize,
+    inverse: bool,
+) -> KernelCreationParams {
+    KernelCreationParams {
+        size,
+        kernel_type,
+        radix,
+        unit,
+        inverse,
+    }
+}
+
+#[test]
+fn validate_naive_kernel() {
+    for &kernel_type in &[KernelType::Dit, KernelType::Dif] {
+        for &inverse in &[false, true] {
+            for &(size, radix, unit) in &[(2, 2, 1), (16, 4, 1), (16, 4, 4), (60, 5, 3), (64, 2, 8)]
+            {
+                let cparams = cparams(size, kernel_type, radix, unit, inverse);
+                let kernel = NaiveKernel { cparams };
+                assert_eq!(validate_kernel(&kernel, &cparams), Ok(()), "{:?}", cparams);
+            }
+        }
+    }
+}
+
+#[test]
+fn validate_broken_kernels() {
+    let params = cparams(16, KernelType::Dit, 4, 1, false);
+    match validate_kernel(&IdentityKernel, &params) {
+        Err(KernelValidationError::Inaccurate { .. }) => {}
+        x => panic!("{:?}", x),
+    }
+    assert_eq!(
+        validate_kernel(&OverrunKernel { size: 16 }, &params),
+        Err(KernelValidationError::OutOfBounds { index: 32 })
+    );
+
+    // The definition of `Dif` differs from that of `Dit` unless `unit = 1`
+    let params = cparams(16, KernelType::Dit, 4, 2, false);
+    let kernel = NaiveKernel {
+        cparams: cparams(16, KernelType::Dif, 4, 2, false),
+    };
+    match validate_kernel(&kernel, &params) {
+        Err(KernelValidationError::Inaccurate { .. }) => {}
+        x => panic!("{:?}", x),
+    }
+
+    for &(size, radix, unit) in &[(0, 2, 1), (16, 1, 1), (16, 4, 0), (12, 4, 2)] {
+        let params = cparams(size, KernelType::Dit, radix, unit, false);
+        assert_eq!(
+            validate_kernel(&IdentityKernel, &params),
+            Err(KernelValidationError::InvalidParams)
+        );
+    }
+}
+
+fn options(len: usize, max_backend: Option<Backend>, deterministic: bool) -> Options {
+    Options {
+        input_data_order: DataOrder::Natural,
+        output_data_order: DataOrder::Natural,
+        input_data_format: DataFormat::Complex,
+        output_data_format: DataFormat::Complex,
+        len,
+        inverse: false,
+        normalization: Normalization::None,
+        max_backend,
+        deterministic,
+    }
+}
+
+fn uses_custom_kernels<T: Num>(options: &Options) -> bool {
+    Setup::<T>::new(options)
+        .unwrap()
+        .report()
+        .stages
+        .iter()
+        .any(|s| s.backend == Backend::Custom)
+}
+
+// The only test touching the registry, which is shared by the tests in this
+// file
+#[test]
+fn registered_factory() {
+    static NUM_CALLS: AtomicUsize = AtomicUsize::new(0);
+    register_kernel_factory::<f64, _>(|cparams| {
+        NUM_CALLS.fetch_add(1, Ordering::Relaxed);
+        if cparams.radix == 4 || cparams.radix == cparams.size {
+            Some(Box::new(NaiveKernel { cparams: *cparams }))
+        } else {
+            None
+        }
+    });
+
+    let setup = Setup::<f64>::new(&options(32, None, false)).unwrap();
+    let backends: Vec<_> = setup
+        .report()
+        .stages
+        .iter()
+        .map(|s| match s.kind {
+            StageKind::Butterfly { radix, .. } => Some((radix, s.backend == Backend::Custom)),
+            _ => None,
+        })
+        .collect();
+    assert!(backends.contains(&Some((4, true))), "{:?}", backends);
+    assert!(backends.contains(&Some((2, false))), "{:?}", backends);
+    assert!(NUM_CALLS.load(Ordering::Relaxed) >= 3);
+
+    // The output must match that of the built-in kernels
+    let reference = Setup::<f64>::new(&options(32, Some(Backend::Avx), false)).unwrap();
+    let pat: Vec<f64> = (0..64).map(|x| ((x * 7) % 13) as f64 - 6.0).collect();
+    let mut got = vec![0.0; 64];
+    let mut expected = vec![0.0; 64];
+    Env::new(&setup).transform_into(&pat, &mut got).unwrap();
+    Env::new(&reference)
+        .transform_into(&pat, &mut expected)
+        .unwrap();
+    for (x, y) in got.iter().zip(expected.iter()) {
+        assert!((x - y).abs() < 1.0e-9, "{:?} != {:?}", got, expected);
+    }
+
+    // A single kernel can compute the whole FFT
+    let plan = Plan {
+        radixes: vec![32],
+        decimation: Decimation::Time,
+        bit_reversal_backend: Backend::Avx,
+    };
+    let setup = Setup::<f64>::with_plan(&options(32, None, false), &plan).unwrap();
+    let stages = setup.report().stages;
+    assert_eq!(stages.len(), 1);
+    assert_eq!(stages[0].backend, Backend::Custom);
+    Env::new(&setup).transform_into(&pat, &mut got).unwrap();
+    for (x, y) in got.iter().zip(expected.iter()) {
+        assert!((x - y).abs() < 1.0e-9, "{:?} != {:?}", got, expected);
+    }
+
+    assert!(!uses_custom_kernels::<f64>(&options(
+        32,
+        Some(Backend::Avx),
+        false
+    )));
+    assert!(!uses_custom_kernels::<f64>(&options(32, None, true)));
+    assert!(!uses_custom_kernels::<f32>(&options(32, None, false)));
+
+    clear_kernel_factories();
+    assert!(!uses_custom_kernels::<f64>(&options(32, None, false)));
+}
@@ -0,0 +1,190 @@
+extern crate yfft;
+
+use std::thread;
+use yfft::*;
+
+fn test_pattern<T: yfft::Num>(size: usize) -> Vec<T> {
+    (0..size)
+        .map(|x| -> T { T::from((x * 3 + 7) ^ (x * 7 + 3) ^ (x >> 1)).unwrap() })
+        .collect()
+}
+
+fn new_setup(
+    input_data_format: DataFormat,
+    output_data_format: DataFormat,
+    inverse: bool,
+    len: usize,
+    max_backend: Option<Backend>,
+) -> Setup<f32> {
+    Setup::new(&Options {
+        input_data_order: DataOrder::Natural,
+        output_data_order: DataOrder::Natural,
+        input_data_format,
+        output_data_format,
+        len,
+        inverse,
+        normalization: Normalization::ByLen,
+        max_backend,
+        deterministic: true,
+    })
+    .unwrap()
+}
+
+fn transform(setup: &Setup<f32>) -> Vec<u32> {
+    let pat = test_pattern::<f32>(setup.input_len());
+    let mut out = vec![0.0; setup.output_len()];
+    Env::new(setup).transform_into(&pat, &mut out).unwrap();
+    out.iter().map(|x| x.to_bits()).collect()
+}
+
+const FORMATS: &[(DataFormat, DataFormat, bool)] = &[
+    (DataFormat::Complex, DataFormat::Complex, false),
+    (DataFormat::Complex, DataFormat::Complex, true),
+    (DataFormat::Real, DataFormat::HalfComplex, false),
+    (DataFormat::HalfComplex, DataFormat::Real, true),
+];
+
+#[test]
+fn deterministic_backend() {
+    for &(input_data_format, output_data_format, inverse) in FORMATS {
+        for &max_backend in &[None, Some(Backend::Avx), Some(Backend::Generic)] {
+            let setup = new_setup(
+                input_data_format,
+                output_data_format,
+                inverse,
+                256,
+                max_backend,
+            );
+            for stage in &setup.report().stages {
+                assert!(stage.backend <= Backend::Generic2, "{:?}", stage);
+            }
+        }
+    }
+}
+
+/// Transforms the test pattern with the input and output buffers placed
+/// `skew` elements after a 32-byte boundary, on a separate thread.
+fn transform_skewed(options: Options, skew: usize) -> Vec<u32> {
+    thread::spawn(move || {
+        let setup = Setup::<f32>::new(&options).unwrap();
+        let pat = test_pattern::<f32>(setup.input_len());
+
+        let mut input = vec![0.0; pat.len() + 16];
+        let in_start = aligned_offset(&input) + skew;
+        let in_range = in_start..in_start + pat.len();
+        input[in_range.clone()].copy_from_slice(&pat);
+
+        let mut output = vec![0.0; setup.output_len() + 16];
+        let out_start = aligned_offset(&output) + skew;
+        let out_range = out_start..out_start + setup.output_len();
+        Env::new(&setup)
+            .transform_into(&input[in_range], &mut output[out_range.clone()])
+            .unwrap();
+        output[out_range].iter().map(|x| x.to_bits()).collect()
+    })
+    .join()
+    .unwrap()
+}
+
+/// Retrieves the number of elements from the start of `buffer` to the first
+/// 32-byte boundary.
+fn aligned_offset(buffer: &[f32]) -> usize {
+    (32 - buffer.as_ptr() as usize % 32) % 32 / 4
+}
+
+/// Returns whether a `Setup` for `options` uses a kernel that is excluded by
+/// the deterministic mode.
+fn uses_simd(options: &Options) -> bool {
+    Setup::<f32>::new(options)
+        .unwrap()
+        .report()
+        .stages
+        .iter()
+        .any(|s| s.backend > Backend::Generic2)
+}
+
+/// Compares the deterministic output computed with the generic kernels
+/// against the ones computed with different backend limits, buffer
+/// alignments, and threads. As a control, the same comparison must detect a
+/// difference without `Options::deterministic` on a machine where the SIMD
+/// kernels are used, which shows the test would fail if the flag was
+/// ignored.
+#[test]
+fn deterministic_bit_identical() {
+    let mut num_simd_cases = 0;
+    let mut num_simd_mismatches = 0;
+
+    for &(input_data_format, output_data_format, inverse) in FORMATS {
+        for size_ref in &[2, 16, 40, 256, 1024] {
+            let size = *size_ref;
+            let options = |max_backend, deterministic| Options {
+                input_data_order: DataOrder::Natural,
+                output_data_order: DataOrder::Natural,
+                input_data_format,
+                output_data_format,
+                len: size,
+                inverse,
+                normalization: Normalization::ByLen,
+                max_backend,
+                deterministic,
+            };
+            let expected = transform(&new_setup(
+                input_data_format,
+                output_data_format,
+                inverse,
+                size,
+                Some(Backend::Generic2),
+            ));
+
+            for &max_backend in &[None, Some(Backend::Sse), Some(Backend::Avx)] {
+                for &skew in &[0, 1] {
+                    assert_eq!(
+                        transform_skewed(options(max_backend, true), skew),
+                        expected,
+                        "{:?} → {:?}, len = {}, max_backend = {:?}, skew = {}",
+                        input_data_format,
+                        output_data_format,
+                        size,
+                        max_backend,
+                        skew
+                    );
+
+                    let nondeterministic = options(max_backend, false);
+                    if uses_simd(&nondeterministic) {
+                        num_simd_cases += 1;
+                        if transform_skewed(nondeterministic, skew) != expected {
+                            num_simd_mismatches += 1;
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    if num_simd_cases > 0 {
+        assert!(
+            num_simd_mismatches > 0,
+            "the SIMD kernels produced the same output as the generic ones \
+             in all {} cases, so this test can't detect an ignored \
+             `deterministic` flag",
+            num_simd_cases
+        );
+    }
+}
+
+/// The output of the deterministic mode must not depend on the machine, so
+/// it can be compared against a value recorded once.
+#[test]
+fn deterministic_golden() {
+    let setup = new_setup(DataFormat::Complex, DataFormat::Complex, false, 1024, None);
+    // FNV-1a
+    let hash = transform(&setup)
+        .iter()
+        .fold(0xcbf29ce484222325u64, |h, &x| {
+            (h ^ x as u64).wrapping_mul(0x100000001b3)
+        });
+    assert_eq!(hash, GOLDEN);
+}
+
+// Changes whenever the arithmetic of the generic kernels changes
+const GOLDEN: u64 = 0xc4320b43575f3759;
@@ -0,0 +1,162 @@
+extern crate num_complex;
+extern crate yfft;
+
+use num_complex::Complex;
+use std::sync::Arc;
+use std::thread;
+use yfft::*;
+
+fn assert_num_slice_approx_eq<T: yfft::Num>(got: &[T], expected: &[T], releps: T) {
+    assert_eq!(got.len(), expected.len());
+    // We can't use `Iterator::max()` because T doesn't implement Ord
+    let maxabs = expected
+        .iter()
+        .map(|x| x.abs())
+        .fold(T::zero() / T::zero(), |x, y| x.max(y))
+        + T::from(0.01).unwrap();
+    let eps = maxabs * releps;
+    for i in 0..got.len() {
+        let a = got[i];
+        let b = expected[i];
+        if (a - b).abs() > eps {
+            assert!(
+                (a - b).abs() < eps,
+                "assertion failed: `got almost equal to expected` \
+                 (got: `{:?}`, expected: `{:?}`, diff=`{:?}`)",
+                got,
+                expected,
+                (a - b).abs()
+            );
+        }
+    }
+}
+
+fn test_pattern<T: yfft::Num>(size: usize) -> Vec<T> {
+    (0..size)
+        .map(|x| -> T { T::from((x * 3 + 7) ^ (x * 7 + 3) ^ (x >> 1)).unwrap() })
+        .collect()
+}
+
+fn reference<T: Num>(
+    input_data_format: DataFormat,
+    output_data_format: DataFormat,
+    len: usize,
+    inverse: bool,
+    input: &[T],
+) -> Vec<T> {
+    let setup = Setup::new(&Options {
+        input_data_order: DataOrder::Natural,
+        output_data_order: DataOrder::Natural,
+        input_data_format,
+        output_data_format,
+        len,
+        inverse,
+        normalization: Normalization::None,
+        max_backend: None,
+        deterministic: false,
+    })
+    .unwrap();
+    let mut output = vec![T::zero(); setup.output_len()];
+    Env::new(&setup).transform_into(input, &mut output).unwrap();
+    output
+}
+
+fn multisize<T: Num>() {
+    let setup = MultiSizeSetup::<T>::new(2048).unwrap();
+    assert_eq!(setup.max_len(), 2048);
+
+    for &len in &[1, 2, 4, 8, 64, 256, 512, 1024, 2048] {
+        for &inverse in &[false, true] {
+            let pat = test_pattern::<T>(len * 2);
+            let mut data: Vec<_> = (0..len)
+                .map(|i| Complex::new(pat[i * 2], pat[i * 2 + 1]))
+                .collect();
+            setup.transform_complex(&mut data, inverse).unwrap();
+            let got: Vec<T> = data.iter().flat_map(|c| vec![c.re, c.im]).collect();
+            let expected = reference(DataFormat::Complex, DataFormat::Complex, len, inverse, &pat);
+            assert_num_slice_approx_eq(&got, &expected, T::from(1.0e-3).unwrap());
+        }
+
+        if len >= 2 {
+            let pat = test_pattern::<T>(len);
+            let mut data = pat.clone();
+            setup.transform_real(&mut data, false).unwrap();
+            let expected = reference(DataFormat::Real, DataFormat::HalfComplex, len, false, &pat);
+            assert_num_slice_approx_eq(&data, &expected, T::from(1.0e-3).unwrap());
+
+            let mut data = pat.clone();
+            setup.transform_real(&mut data, true).unwrap();
+            let expected = reference(DataFormat::HalfComplex, DataFormat::Real, len, true, &pat);
+            assert_num_slice_approx_eq(&data, &expected, T::from(1.0e-3).unwrap());
+        }
+    }
+}
+
+#[test]
+fn multisize_f32() {
+    multisize::<f32>();
+}
+
+#[test]
+fn multisize_f64() {
+    multisize::<f64>();
+}
+
+#[test]
+fn multisize_shared() {
+    let setup = Arc::new(MultiSizeSetup::<f32>::new(1024).unwrap());
+    let threads: Vec<_> = [256, 512, 1024]
+        .iter()
+        .map(|&len| {
+            let setup = Arc::clone(&setup);
+            thread::spawn(move || {
+                let pat = test_pattern::<f32>(len);
+                let mut data = pat.clone();
+                for _ in 0..4 {
+                    setup.transform_real(&mut data, false).unwrap();
+                    setup.transform_real(&mut data, true).unwrap();
+                    for x in data.iter_mut() {
+                        *x *= 2.0 / len as f32;
+                    }
+                }
+                assert_num_slice_approx_eq(&data, &pat, 1.0e-3);
+            })
+        })
+        .collect();
+    for thread in threads {
+        thread.join().unwrap();
+    }
+}
+
+#[test]
+fn multisize_errors() {
+    assert_eq!(
+        MultiSizeSetup::<f32>::new(0).err(),
+        Some(PlanError::ZeroLength)
+    );
+    assert_eq!(
+        MultiSizeSetup::<f32>::new(48).err(),
+        Some(PlanError::NotPowerOfTwo { len: 48 })
+    );
+
+    let setup = MultiSizeSetup::<f32>::new(256).unwrap();
+    assert_eq!(setup.table_size(), 128 * 8);
+    let mut data = vec![Complex::new(0.0, 0.0); 512];
+    assert_eq!(
+        setup.transform_complex(&mut data, false),
+        Err(TransformError::UnsupportedLength { len: 512 })
+    );
+    assert_eq!(
+        setup.transform_complex(&mut data[0..24], false),
+        Err(TransformError::UnsupportedLength { len: 24 })
+    );
+    assert_eq!(
+        setup.transform_complex(&mut data[0..0], false),
+        Err(TransformError::UnsupportedLength { len: 0 })
+    );
+    let mut data = vec![0.0; 1];
+    assert_eq!(
+        setup.transform_real(&mut data, false),
+        Err(TransformError::UnsupportedLength { len: 1 })
+    );
+}
@@ -0,0 +1,341 @@
+extern crate num_complex;
+extern crate num_traits;
+extern crate yfft;
+
+use yfft::*;
+
+fn assert_num_slice_approx_eq<T: yfft::Num>(got: &[T], expected: &[T], releps: T) {
+    assert_eq!(got.len(), expected.len());
+    // We can't use `Iterator::max()` because T doesn't implement Ord
+    let maxabs = expected
+        .iter()
+        .map(|x| x.abs())
+        .fold(T::zero() / T::zero(), |x, y| x.max(y))
+        + T::from(0.01).unwrap();
+    let eps = maxabs * releps;
+    for i in 0..got.len() {
+        let a = got[i];
+        let b = expected[i];
+        if (a - b).abs() > eps {
+            assert!(
+                (a - b).abs() < eps,
+                "assertion failed: `got almost equal to expected` \
+                 (got: `{:?}`, expected: `{:?}`, diff=`{:?}`)",
+                got,
+                expected,
+                (a - b).abs()
+            );
+        }
+    }
+}
+
+fn test_pattern<T: yfft::Num>(size: usize) -> Vec<T> {
+    (0..size)
+        .map(|x| -> T { T::from((x * 3 + 7) ^ (x * 7 + 3) ^ (x >> 1)).unwrap() })
+        .collect()
+}
+
+const FORMATS: &[(DataOrder, DataOrder, DataFormat, DataFormat, bool)] = &[
+    (
+        DataOrder::Natural,
+        DataOrder::Natural,
+        DataFormat::Complex,
+        DataFormat::Complex,
+        false,
+    ),
+    (
+        DataOrder::Natural,
+        DataOrder::Natural,
+        DataFormat::Complex,
+        DataFormat::Complex,
+        true,
+    ),
+    (
+        DataOrder::Natural,
+        DataOrder::Swizzled,
+        DataFormat::Complex,
+        DataFormat::Complex,
+        false,
+    ),
+    (
+        DataOrder::Swizzled,
+        DataOrder::Natural,
+        DataFormat::Complex,
+        DataFormat::Complex,
+        true,
+    ),
+    (
+        DataOrder::Natural,
+        DataOrder::Natural,
+        DataFormat::Real,
+        DataFormat::Complex,
+        false,
+    ),
+    (
+        DataOrder::Natural,
+        DataOrder::Natural,
+        DataFormat::Real,
+        DataFormat::HalfComplex,
+        false,
+    ),
+    (
+        DataOrder::Natural,
+        DataOrder::Natural,
+        DataFormat::HalfComplex,
+        DataFormat::Real,
+        true,
+    ),
+    (
+        DataOrder::Natural,
+        DataOrder::Natural,
+        DataFormat::HalfComplex,
+        DataFormat::Complex,
+        true,
+    ),
+];
+
+/// Compares the output of each normalization mode against the unnormalized
+/// output scaled by the expected factor.
+fn normalization_factor<T: Num>() {
+    for &(input_data_order, output_data_order, input_data_format, output_data_format, inverse) in
+        FORMATS
+    {
+        for size_ref in &[1, 2, 3, 4, 6, 15, 16, 40, 64, 256] {
+            let size = *size_ref;
+            // The factor is applied by a different kernel for each backend
+            for &max_backend in &[
+                None,
+                Some(Backend::Sse),
+                Some(Backend::Generic2),
+                Some(Backend::Generic),
+            ] {
+                let new_setup = |normalization| {
+                    Setup::<T>::new(&Options {
+                        input_data_order,
+                        output_data_order,
+                        input_data_format,
+                        output_data_format,
+                        len: size,
+                        inverse,
+                        normalization,
+                        max_backend,
+                        deterministic: false,
+                    })
+                };
+                let reference = match new_setup(Normalization::None) {
+                    Ok(setup) => setup,
+                    Err(_) => continue,
+                };
+
+                let pat = test_pattern::<T>(reference.input_len());
+                let mut expected = vec![T::zero(); reference.output_len()];
+                Env::new(&reference)
+                    .transform_into(&pat, &mut expected)
+                    .unwrap();
+
+                // The unnormalized backward real FFT is scaled by `1/2`
+                let inherent = if input_data_format == DataFormat::HalfComplex {
+                    0.5
+                } else {
+                    1.0
+                };
+
+                for &(normalization, factor) in &[
+                    (
+                        Normalization::ByLen,
+                        if inverse { 1.0 / size as f64 } else { 1.0 },
+                    ),
+                    (Normalization::Unitary, 1.0 / (size as f64).sqrt()),
+                    (Normalization::Custom(-3.0), -3.0),
+                ] {
+                    let setup = new_setup(normalization).unwrap();
+                    let mut got = vec![T::zero(); setup.output_len()];
+                    Env::new(&setup).transform_into(&pat, &mut got).unwrap();
+
+                    let scale = T::from(factor / inherent).unwrap();
+                    let expected: Vec<T> = expected.iter().map(|&x| x * scale).collect();
+                    assert_num_slice_approx_eq(&got, &expected, T::from(1.0e-3).unwrap());
+                }
+            }
+        }
+    }
+}
+
+#[test]
+fn normalization_factor_f32() {
+    normalization_factor::<f32>();
+}
+
+#[test]
+fn normalization_factor_f64() {
+    normalization_factor::<f64>();
+}
+
+fn round_trip<T: Num>(
+    forward_format: DataFormat,
+    spectrum_format: DataFormat,
+    normalization: Normalization,
+) {
+    for size_ref in &[2, 4, 16, 40, 64, 256] {
+        let size = *size_ref;
+        let new_setup = |input_data_format, output_data_format, inverse| {
+            Setup::<T>::new(&Options {
+                input_data_order: DataOrder::Natural,
+                output_data_order: DataOrder::Natural,
+                input_data_format,
+                output_data_format,
+                len: size,
+                inverse,
+                normalization,
+                max_backend: None,
+                deterministic: false,
+            })
+            .unwrap()
+        };
+        let setup1 = new_setup(forward_format, spectrum_format, false);
+        let setup2 = new_setup(spectrum_format, forward_format, true);
+
+        let pat = test_pattern::<T>(setup1.input_len());
+        let mut buf = vec![T::zero(); setup1.buffer_len()];
+        buf[0..pat.len()].copy_from_slice(&pat);
+        Env::new(&setup1).transform(&mut buf).unwrap();
+        Env::new(&setup2).transform(&mut buf).unwrap();
+
+        assert_num_slice_approx_eq(&buf[0..pat.len()], &pat, T::from(1.0e-3).unwrap());
+    }
+}
+
+#[test]
+fn round_trip_complex_by_len() {
+    round_trip::<f32>(
+        DataFormat::Complex,
+        DataFormat::Complex,
+        Normalization::ByLen,
+    );
+}
+
+#[test]
+fn round_trip_complex_unitary() {
+    round_trip::<f64>(
+        DataFormat::Complex,
+        DataFormat::Complex,
+        Normalization::Unitary,
+    );
+}
+
+#[test]
+fn round_trip_real_by_len() {
+    round_trip::<f32>(
+        DataFormat::Real,
+        DataFormat::HalfComplex,
+        Normalization::ByLen,
+    );
+}
+
+#[test]
+fn round_trip_real_unitary() {
+    round_trip::<f64>(
+        DataFormat::Real,
+        DataFormat::HalfComplex,
+        Normalization::Unitary,
+    );
+}
+
+/// Checks that the normalization factor never requires an additional pass,
+/// as documented by `Normalization`.
+#[test]
+fn no_scale_pass() {
+    for &(len, output_data_order, input_data_format, output_data_format) in &[
+        // Folded into the bit reversal or the real FFT post-processing
+        (
+            64,
+            DataOrder::Natural,
+            DataFormat::Complex,
+            DataFormat::Complex,
+        ),
+        (
+            15,
+            DataOrder::Natural,
+            DataFormat::Complex,
+            DataFormat::Complex,
+        ),
+        (
+            64,
+            DataOrder::Natural,
+            DataFormat::Real,
+            DataFormat::HalfComplex,
+        ),
+        (
+            8,
+            DataOrder::Natural,
+            DataFormat::Real,
+            DataFormat::HalfComplex,
+        ),
+        // Folded into a butterfly pass
+        (
+            64,
+            DataOrder::Swizzled,
+            DataFormat::Complex,
+            DataFormat::Complex,
+        ),
+        (
+            4,
+            DataOrder::Natural,
+            DataFormat::Complex,
+            DataFormat::Complex,
+        ),
+        (
+            5,
+            DataOrder::Natural,
+            DataFormat::Complex,
+            DataFormat::Complex,
+        ),
+        // Folded into a trivial bit reversal, which is the only stage
+        (
+            1,
+            DataOrder::Natural,
+            DataFormat::Complex,
+            DataFormat::Complex,
+        ),
+    ] {
+        let stages = |normalization| -> Vec<StageKind> {
+            Setup::<f32>::new(&Options {
+                input_data_order: DataOrder::Natural,
+                output_data_order,
+                input_data_format,
+                output_data_format,
+                len,
+                inverse: false,
+                normalization,
+                max_backend: None,
+                deterministic: false,
+            })
+            .unwrap()
+            .report()
+            .stages
+            .iter()
+            .map(|s| s.kind)
+            .collect()
+        };
+        let mut expected = stages(Normalization::None);
+        if len == 1 {
+            expected.push(StageKind::BitReversal);
+        }
+        assert_eq!(
+            stages(Normalization::Custom(2.0)),
+            expected,
+            "len = {}, output order = {:?}, input format = {:?}",
+            len,
+            output_data_order,
+            input_data_format
+        );
+    }
+}
+
+#[test]
+fn normalization_eq() {
+    assert_eq!(Normalization::Custom(0.5), Normalization::Custom(0.5));
+    assert_ne!(Normalization::Custom(0.5), Normalization::Custom(0.25));
+    assert_ne!(Normalization::None, Normalization::Custom(1.0));
+    assert_eq!(Normalization::default(), Normalization::None);
+}
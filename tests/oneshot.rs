@@ -0,0 +1,158 @@
+extern crate num_complex;
+extern crate yfft;
+
+use num_complex::Complex;
+use std::sync::Arc;
+use yfft::*;
+
+fn assert_complex_approx_eq(got: &[Complex<f64>], expected: &[Complex<f64>]) {
+    assert_eq!(got.len(), expected.len());
+    for (a, b) in got.iter().zip(expected.iter()) {
+        assert!(
+            (a - b).norm() < 1.0e-9,
+            "got: {:?}, expected: {:?}",
+            got,
+            expected
+        );
+    }
+}
+
+fn naive_dft(input: &[Complex<f64>]) -> Vec<Complex<f64>> {
+    let len = input.len();
+    (0..len)
+        .map(|k| {
+            input
+                .iter()
+                .enumerate()
+                .map(|(n, x)| {
+                    let angle = -2.0 * std::f64::consts::PI * (k * n % len) as f64 / len as f64;
+                    x * Complex::new(angle.cos(), angle.sin())
+                })
+                .fold(Complex::new(0.0, 0.0), |x, y| x + y)
+        })
+        .collect()
+}
+
+fn test_pattern(len: usize) -> Vec<f64> {
+    (0..len)
+        .map(|x| ((x * 3 + 7) ^ (x * 7 + 3) ^ (x >> 1)) as f64)
+        .collect()
+}
+
+#[test]
+fn oneshot_fft() {
+    for &len in &[0, 1, 2, 5, 16, 24, 128] {
+        let pat = test_pattern(len * 2);
+        let input: Vec<_> = (0..len)
+            .map(|i| Complex::new(pat[i * 2], pat[i * 2 + 1]))
+            .collect();
+
+        let mut data = input.clone();
+        fft(&mut data).unwrap();
+        assert_complex_approx_eq(&data, &naive_dft(&input));
+
+        ifft(&mut data).unwrap();
+        assert_complex_approx_eq(&data, &input);
+    }
+}
+
+#[test]
+fn oneshot_rfft() {
+    for &len in &[0, 1, 2, 5, 16, 24, 128] {
+        let input = test_pattern(len);
+        let spectrum = rfft(&input).unwrap();
+
+        let complex_input: Vec<_> = input.iter().map(|&x| Complex::new(x, 0.0)).collect();
+        let expected = naive_dft(&complex_input);
+        if len == 0 {
+            assert!(spectrum.is_empty());
+        } else {
+            assert_complex_approx_eq(&spectrum, &expected[0..len / 2 + 1]);
+        }
+
+        if len % 2 == 0 {
+            let output = irfft(&spectrum, len).unwrap();
+            assert_eq!(output.len(), len);
+            for (a, b) in output.iter().zip(input.iter()) {
+                assert!(
+                    (a - b).abs() < 1.0e-9,
+                    "got: {:?}, expected: {:?}",
+                    output,
+                    input
+                );
+            }
+        }
+    }
+}
+
+#[test]
+fn oneshot_errors() {
+    let spectrum = vec![Complex::new(1.0, 0.0); 3];
+    assert_eq!(
+        irfft(&spectrum, 5),
+        Err(TransformError::Plan(PlanError::OddLength { len: 5 }))
+    );
+
+    // Odd factors require `backend-generic`
+    let mut data = vec![Complex::new(1.0, 0.0); 6];
+    let result = fft(&mut data);
+    if cfg!(feature = "backend-generic") {
+        assert_eq!(result, Ok(()));
+    } else {
+        assert_eq!(
+            result,
+            Err(TransformError::Plan(PlanError::UnsupportedRadix {
+                radix: 3
+            }))
+        );
+    }
+}
+
+#[test]
+fn oneshot_irfft_spectrum_len() {
+    assert_eq!(
+        irfft(&[Complex::new(1.0f32, 0.0); 4], 8),
+        Err(TransformError::LengthMismatch {
+            expected: 5,
+            actual: 4
+        })
+    );
+}
+
+#[test]
+fn cached_setup() {
+    let options = Options {
+        input_data_order: DataOrder::Natural,
+        output_data_order: DataOrder::Natural,
+        input_data_format: DataFormat::Complex,
+        output_data_format: DataFormat::Complex,
+        len: 77,
+        inverse: false,
+        normalization: Normalization::Custom(0.5),
+        max_backend: None,
+        deterministic: false,
+    };
+    let setup1 = Setup::<f32>::cached(&options).unwrap();
+    let setup2 = Setup::<f32>::cached(&options).unwrap();
+    assert!(Arc::ptr_eq(&setup1, &setup2));
+
+    // Keyed by the scalar type and every field of `Options`
+    let setup3 = Setup::<f64>::cached(&options).unwrap();
+    assert_eq!(setup3.options(), &options);
+    let mut options2 = options;
+    options2.normalization = Normalization::Custom(0.25);
+    let setup4 = Setup::<f32>::cached(&options2).unwrap();
+    assert!(!Arc::ptr_eq(&setup1, &setup4));
+    assert_eq!(setup4.options(), &options2);
+
+    clear_setup_cache();
+    let setup5 = Setup::<f32>::cached(&options).unwrap();
+    assert!(!Arc::ptr_eq(&setup1, &setup5));
+
+    let mut invalid = options;
+    invalid.len = 0;
+    assert_eq!(
+        Setup::<f32>::cached(&invalid).err(),
+        Some(PlanError::ZeroLength)
+    );
+}
@@ -0,0 +1,106 @@
+extern crate yfft;
+
+use yfft::*;
+
+fn options(
+    input_data_order: DataOrder,
+    output_data_order: DataOrder,
+    input_data_format: DataFormat,
+    output_data_format: DataFormat,
+    len: usize,
+    inverse: bool,
+) -> Options {
+    Options {
+        input_data_order,
+        output_data_order,
+        input_data_format,
+        output_data_format,
+        len,
+        inverse,
+        normalization: Normalization::None,
+        max_backend: None,
+        deterministic: false,
+    }
+}
+
+fn check(options: &Options) -> Result<(), PlanError> {
+    let result = Setup::<f32>::check(options);
+    assert_eq!(Setup::<f32>::new(options).err(), result.err());
+    result
+}
+
+#[test]
+fn plan_ok() {
+    use DataFormat::*;
+    use DataOrder::*;
+    assert_eq!(
+        check(&options(Natural, Natural, Complex, Complex, 15, false)),
+        Ok(())
+    );
+    assert_eq!(
+        check(&options(BitReversed, Natural, Complex, Complex, 16, true)),
+        Ok(())
+    );
+    assert_eq!(
+        check(&options(Natural, Natural, Real, Complex, 15, false)),
+        Ok(())
+    );
+    assert_eq!(
+        check(&options(Natural, Natural, HalfComplex, Real, 16, true)),
+        Ok(())
+    );
+}
+
+#[test]
+fn plan_errors() {
+    use DataFormat::*;
+    use DataOrder::*;
+    assert_eq!(
+        check(&options(Natural, Natural, Complex, Complex, 0, false)),
+        Err(PlanError::ZeroLength)
+    );
+    assert_eq!(
+        check(&options(Swizzled, BitReversed, Complex, Complex, 16, false)),
+        Err(PlanError::BothOrdersSwizzled {
+            input_data_order: Swizzled,
+            output_data_order: BitReversed,
+        })
+    );
+    assert_eq!(
+        check(&options(Natural, Swizzled, Real, HalfComplex, 16, false)),
+        Err(PlanError::IncompatibleDataOrder {
+            order: Swizzled,
+            format: HalfComplex,
+        })
+    );
+    assert_eq!(
+        check(&options(Natural, Natural, Real, HalfComplex, 15, false)),
+        Err(PlanError::OddLength { len: 15 })
+    );
+    assert_eq!(
+        check(&options(BitReversed, Natural, Complex, Complex, 12, false)),
+        Err(PlanError::NotPowerOfTwo { len: 12 })
+    );
+    assert_eq!(
+        check(&options(Natural, Natural, Complex, Real, 16, false)),
+        Err(PlanError::UnsupportedDataFormat {
+            input_data_format: Complex,
+            output_data_format: Real,
+            inverse: false,
+        })
+    );
+    assert_eq!(
+        check(&options(Natural, Natural, HalfComplex, Real, 16, false)),
+        Err(PlanError::UnsupportedDataFormat {
+            input_data_format: HalfComplex,
+            output_data_format: Real,
+            inverse: false,
+        })
+    );
+}
+
+#[test]
+fn plan_error_display() {
+    let e = PlanError::OddLength { len: 15 };
+    assert!(e.to_string().contains("15"));
+}
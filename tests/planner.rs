@@ -0,0 +1,212 @@
+extern crate yfft;
+
+use std::time::Duration;
+use yfft::*;
+
+fn assert_num_slice_approx_eq<T: yfft::Num>(got: &[T], expected: &[T], releps: T) {
+    assert_eq!(got.len(), expected.len());
+    // We can't use `Iterator::max()` because T doesn't implement Ord
+    let maxabs = expected
+        .iter()
+        .map(|x| x.abs())
+        .fold(T::zero() / T::zero(), |x, y| x.max(y))
+        + T::from(0.01).unwrap();
+    let eps = maxabs * releps;
+    for i in 0..got.len() {
+        let a = got[i];
+        let b = expected[i];
+        if (a - b).abs() > eps {
+            assert!(
+                (a - b).abs() < eps,
+                "assertion failed: `got almost equal to expected` \
+                 (got: `{:?}`, expected: `{:?}`, diff=`{:?}`)",
+                got,
+                expected,
+                (a - b).abs()
+            );
+        }
+    }
+}
+
+fn test_pattern<T: yfft::Num>(size: usize) -> Vec<T> {
+    (0..size)
+        .map(|x| -> T { T::from((x * 3 + 7) ^ (x * 7 + 3) ^ (x >> 1)).unwrap() })
+        .collect()
+}
+
+fn new_options(
+    input_data_format: DataFormat,
+    output_data_format: DataFormat,
+    inverse: bool,
+    len: usize,
+) -> Options {
+    Options {
+        input_data_order: DataOrder::Natural,
+        output_data_order: DataOrder::Natural,
+        input_data_format,
+        output_data_format,
+        len,
+        inverse,
+        normalization: Normalization::None,
+        max_backend: None,
+        deterministic: false,
+    }
+}
+
+fn transform<T: Num>(setup: &Setup<T>) -> Vec<T> {
+    let pat = test_pattern::<T>(setup.input_len());
+    let mut out = vec![T::zero(); setup.output_len()];
+    Env::new(setup).transform_into(&pat, &mut out).unwrap();
+    out
+}
+
+const FORMATS: &[(DataFormat, DataFormat, bool)] = &[
+    (DataFormat::Complex, DataFormat::Complex, false),
+    (DataFormat::Complex, DataFormat::Complex, true),
+    (DataFormat::Real, DataFormat::HalfComplex, false),
+    (DataFormat::HalfComplex, DataFormat::Real, true),
+];
+
+/// Every permutation of `radixes`.
+fn permutations(radixes: &[usize]) -> Vec<Vec<usize>> {
+    if radixes.len() <= 1 {
+        return vec![radixes.to_vec()];
+    }
+    let mut result = Vec::new();
+    for i in 0..radixes.len() {
+        let mut rest = radixes.to_vec();
+        let first = rest.remove(i);
+        for mut perm in permutations(&rest) {
+            perm.insert(0, first);
+            result.push(perm);
+        }
+    }
+    result
+}
+
+fn explicit_plans<T: Num>() {
+    for &(input_data_format, output_data_format, inverse) in FORMATS {
+        for &(size, factors) in &[
+            (24, &[4, 3, 2][..]),
+            (48, &[2, 2, 3, 4][..]),
+            (60, &[5, 3, 4][..]),
+            (64, &[2, 4, 8][..]),
+        ] {
+            let options = new_options(input_data_format, output_data_format, inverse, size);
+            let expected = transform(&Setup::<T>::new(&options).unwrap());
+
+            // The real FFTs are computed using a complex FFT of the half length
+            let factors = if input_data_format == DataFormat::Complex {
+                factors.to_vec()
+            } else {
+                let mut factors = factors.to_vec();
+                let i = factors.iter().position(|&x| x % 2 == 0).unwrap();
+                factors[i] /= 2;
+                factors.retain(|&x| x != 1);
+                factors
+            };
+
+            for radixes in permutations(&factors) {
+                for &decimation in &[Decimation::Time, Decimation::Frequency] {
+                    for &bit_reversal_backend in &[Backend::Generic, Backend::Avx] {
+                        let plan = Plan {
+                            radixes: radixes.clone(),
+                            decimation,
+                            bit_reversal_backend,
+                        };
+                        let setup = Setup::<T>::with_plan(&options, &plan).unwrap();
+                        assert_eq!(setup.plan(), &plan);
+                        assert_num_slice_approx_eq(
+                            &transform(&setup),
+                            &expected,
+                            T::from(1.0e-3).unwrap(),
+                        );
+                    }
+                }
+            }
+        }
+    }
+}
+
+#[test]
+fn explicit_plans_f32() {
+    explicit_plans::<f32>();
+}
+
+#[test]
+fn explicit_plans_f64() {
+    explicit_plans::<f64>();
+}
+
+#[test]
+fn measured() {
+    for &(input_data_format, output_data_format, inverse) in FORMATS {
+        for &size in &[1, 2, 16, 40, 256, 1024] {
+            let options = new_options(input_data_format, output_data_format, inverse, size);
+            let setup = match Setup::<f32>::new_measured(&options, Duration::from_millis(20)) {
+                Ok(setup) => setup,
+                Err(PlanError::OddLength { .. }) => continue,
+                Err(e) => panic!("{:?}", e),
+            };
+            let expected = transform(&Setup::<f32>::new(&options).unwrap());
+            assert_num_slice_approx_eq(&transform(&setup), &expected, 1.0e-3);
+        }
+    }
+}
+
+#[test]
+fn measured_zero_budget() {
+    let options = new_options(DataFormat::Complex, DataFormat::Complex, false, 256);
+    let plan = Plan::measure::<f32>(&options, Duration::from_millis(0)).unwrap();
+    assert_eq!(plan, Plan::estimate(&options).unwrap());
+}
+
+#[test]
+fn measured_deterministic() {
+    let mut options = new_options(DataFormat::Complex, DataFormat::Complex, false, 256);
+    options.deterministic = true;
+    let setup = Setup::<f32>::new_measured(&options, Duration::from_millis(20)).unwrap();
+    assert_eq!(setup.plan(), &Plan::estimate(&options).unwrap());
+}
+
+#[test]
+fn estimate() {
+    let options = new_options(DataFormat::Complex, DataFormat::Complex, false, 48);
+    assert_eq!(
+        Setup::<f32>::new(&options).unwrap().plan(),
+        &Plan::estimate(&options).unwrap()
+    );
+}
+
+#[test]
+fn invalid_plans() {
+    let options = new_options(DataFormat::Complex, DataFormat::Complex, false, 48);
+    let mut swizzled = options;
+    swizzled.output_data_order = DataOrder::Swizzled;
+    let mut bit_reversed = new_options(DataFormat::Complex, DataFormat::Complex, false, 16);
+    bit_reversed.input_data_order = DataOrder::BitReversed;
+    let len16 = new_options(DataFormat::Complex, DataFormat::Complex, false, 16);
+    // `16 * huge` overflows to `16`
+    let huge = (1usize << (usize::BITS - 4)) + 1;
+
+    for &(ref options, ref radixes, decimation) in &[
+        (options, vec![4, 4, 4], Decimation::Frequency),
+        (options, vec![48, 1], Decimation::Frequency),
+        (swizzled, vec![4, 4, 3], Decimation::Time),
+        (bit_reversed, vec![4, 4], Decimation::Time),
+        // The products wrap around to the length
+        (options, vec![48, huge], Decimation::Frequency),
+        (options, vec![3, 16, huge], Decimation::Frequency),
+        (len16, vec![16, huge], Decimation::Frequency),
+    ] {
+        let plan = Plan {
+            radixes: radixes.clone(),
+            decimation,
+            bit_reversal_backend: Backend::Avx,
+        };
+        assert_eq!(
+            Setup::<f32>::with_plan(options, &plan).err(),
+            Some(PlanError::InvalidPlan)
+        );
+    }
+}
@@ -85,6 +85,9 @@ fn fft_real_forward<T: Num>() {
             output_data_format: DataFormat::Complex,
             len: size * 2,
             inverse: false,
+            normalization: Normalization::None,
+            max_backend: None,
+            deterministic: false,
         })
         .unwrap();
 
@@ -96,6 +99,9 @@ fn fft_real_forward<T: Num>() {
             output_data_format: DataFormat::Complex,
             len: size * 2,
             inverse: false,
+            normalization: Normalization::None,
+            max_backend: None,
+            deterministic: false,
         })
         .unwrap();
 
@@ -110,14 +116,14 @@ fn fft_real_forward<T: Num>() {
             for i in size * 2..size * 4 {
                 result1[i] = T::zero();
             }
-            env1.transform(result1.as_mut_slice());
+            env1.transform(result1.as_mut_slice()).unwrap();
 
             // complex FFT
             for i in 0..size * 2 {
                 result2[i * 2] = pat[i];
                 result2[i * 2 + 1] = T::zero();
             }
-            env2.transform(result2.as_mut_slice());
+            env2.transform(result2.as_mut_slice()).unwrap();
 
             assert_num_slice_approx_eq(
                 result1.as_slice(),
@@ -151,6 +157,9 @@ fn fft_real_backward<T: Num>() {
             output_data_format: DataFormat::Complex,
             len: size * 2,
             inverse: true,
+            normalization: Normalization::None,
+            max_backend: None,
+            deterministic: false,
         })
         .unwrap();
 
@@ -162,6 +171,9 @@ fn fft_real_backward<T: Num>() {
             output_data_format: DataFormat::Complex,
             len: size * 2,
             inverse: true,
+            normalization: Normalization::None,
+            max_backend: None,
+            deterministic: false,
         })
         .unwrap();
 
@@ -176,7 +188,7 @@ fn fft_real_backward<T: Num>() {
             for i in size * 2..size * 4 {
                 result1[i] = T::zero();
             }
-            env1.transform(result1.as_mut_slice());
+            env1.transform(result1.as_mut_slice()).unwrap();
 
             // complex FFT
             result2[0..size * 2].copy_from_slice(pat.as_slice());
@@ -190,7 +202,7 @@ fn fft_real_backward<T: Num>() {
             for e in result2.iter_mut() {
                 *e = *e * T::from(0.5f32).unwrap();
             }
-            env2.transform(result2.as_mut_slice());
+            env2.transform(result2.as_mut_slice()).unwrap();
 
             assert_num_slice_approx_eq(
                 result1.as_slice(),
@@ -222,6 +234,9 @@ fn fft_roundtrip_real<T: Num>() {
             output_data_format: DataFormat::HalfComplex,
             len: size * 2,
             inverse: false,
+            normalization: Normalization::None,
+            max_backend: None,
+            deterministic: false,
         })
         .unwrap();
         let setup2: Setup<T> = Setup::new(&Options {
@@ -231,6 +246,9 @@ fn fft_roundtrip_real<T: Num>() {
             output_data_format: DataFormat::Real,
             len: size * 2,
             inverse: true,
+            normalization: Normalization::None,
+            max_backend: None,
+            deterministic: false,
         })
         .unwrap();
 
@@ -242,8 +260,8 @@ fn fft_roundtrip_real<T: Num>() {
         let mut result = vec![T::zero(); size * 2];
         for pat in test_patterns::<T>(size * 2) {
             result.copy_from_slice(pat.as_slice());
-            env1.transform(result.as_mut_slice());
-            env2.transform(result.as_mut_slice());
+            env1.transform(result.as_mut_slice()).unwrap();
+            env2.transform(result.as_mut_slice()).unwrap();
 
             for e in &mut result {
                 *e = *e * factor;
@@ -263,3 +281,50 @@ fn fft_roundtrip_real_f32() {
 fn fft_roundtrip_real_f64() {
     fft_roundtrip_real::<f64>();
 }
+
+fn fft_real_out_of_place<T: Num>() {
+    for size_ref in &[1, 2, 3, 4, 5, 6, 7, 8, 16, 32, 40, 49, 64, 128] {
+        let size = *size_ref;
+
+        let setup: Setup<T> = Setup::new(&Options {
+            input_data_order: DataOrder::Natural,
+            output_data_order: DataOrder::Natural,
+            input_data_format: DataFormat::Real,
+            output_data_format: DataFormat::Complex,
+            len: size * 2,
+            inverse: false,
+            normalization: Normalization::None,
+            max_backend: None,
+            deterministic: false,
+        })
+        .unwrap();
+        let mut env = Env::new(&setup);
+
+        let mut result1 = vec![T::zero(); size * 4];
+        let mut result2 = vec![T::zero(); size * 4];
+        for pat in test_patterns::<T>(size * 2) {
+            // out-of-place; the input buffer only has to hold the real input
+            env.transform_into(pat.as_slice(), result1.as_mut_slice())
+                .unwrap();
+
+            // in-place
+            result2[0..size * 2].copy_from_slice(pat.as_slice());
+            for i in size * 2..size * 4 {
+                result2[i] = T::zero();
+            }
+            env.transform(result2.as_mut_slice()).unwrap();
+
+            assert_eq!(result1, result2);
+        }
+    }
+}
+
+#[test]
+fn fft_real_out_of_place_f32() {
+    fft_real_out_of_place::<f32>();
+}
+
+#[test]
+fn fft_real_out_of_place_f64() {
+    fft_real_out_of_place::<f64>();
+}
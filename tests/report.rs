@@ -0,0 +1,75 @@
+extern crate yfft;
+
+use yfft::*;
+
+fn new_setup(
+    input_data_format: DataFormat,
+    output_data_format: DataFormat,
+    len: usize,
+    inverse: bool,
+) -> Setup<f64> {
+    Setup::new(&Options {
+        input_data_order: DataOrder::Natural,
+        output_data_order: DataOrder::Natural,
+        input_data_format,
+        output_data_format,
+        len,
+        inverse,
+        normalization: Normalization::None,
+        max_backend: None,
+        deterministic: false,
+    })
+    .unwrap()
+}
+
+#[test]
+fn report_complex() {
+    let setup = new_setup(DataFormat::Complex, DataFormat::Complex, 64, false);
+    let report = setup.report();
+
+    assert_eq!(report.fft_len, 64);
+    assert_eq!(report.radixes.iter().product::<usize>(), 64);
+    assert_eq!(report.scratch_len, setup.scratch_len());
+
+    let butterflies: Vec<_> = report
+        .stages
+        .iter()
+        .filter_map(|s| match s.kind {
+            StageKind::Butterfly {
+                radix, decimation, ..
+            } => Some((radix, decimation)),
+            _ => None,
+        })
+        .collect();
+    assert_eq!(
+        butterflies,
+        report
+            .radixes
+            .iter()
+            .map(|&r| (r, Decimation::Frequency))
+            .collect::<Vec<_>>()
+    );
+    assert_eq!(report.stages.last().unwrap().kind, StageKind::BitReversal);
+    assert!(report.flops > 0.0);
+
+    let text = report.to_string();
+    assert!(text.contains("bit reversal"));
+    let dot = report.to_dot();
+    assert!(dot.starts_with("digraph"));
+    assert!(dot.contains("input -> stage0"));
+}
+
+#[test]
+fn report_real() {
+    let setup = new_setup(DataFormat::Real, DataFormat::HalfComplex, 64, false);
+    let report = setup.report();
+
+    assert_eq!(report.fft_len, 32);
+    assert_eq!(
+        report.stages.last().unwrap().kind,
+        StageKind::RealFftPostProcess
+    );
+    // `f64` never uses the x86 kernels
+    assert!(report.stages.iter().all(|s| s.backend <= Backend::Generic2));
+    assert!(report.table_size > 0);
+}
@@ -0,0 +1,132 @@
+extern crate num_complex;
+extern crate yfft;
+
+use num_complex::Complex;
+use yfft::*;
+
+fn assert_num_slice_approx_eq<T: yfft::Num>(got: &[T], expected: &[T], releps: T) {
+    assert_eq!(got.len(), expected.len());
+    // We can't use `Iterator::max()` because T doesn't implement Ord
+    let maxabs = expected
+        .iter()
+        .map(|x| x.abs())
+        .fold(T::zero() / T::zero(), |x, y| x.max(y))
+        + T::from(0.01).unwrap();
+    let eps = maxabs * releps;
+    for i in 0..got.len() {
+        let a = got[i];
+        let b = expected[i];
+        if (a - b).abs() > eps {
+            assert!(
+                (a - b).abs() < eps,
+                "assertion failed: `got almost equal to expected` \
+                 (got: `{:?}`, expected: `{:?}`, diff=`{:?}`)",
+                got,
+                expected,
+                (a - b).abs()
+            );
+        }
+    }
+}
+
+fn test_pattern<T: yfft::Num>(size: usize) -> Vec<T> {
+    (0..size)
+        .map(|x| -> T { T::from((x * 3 + 7) ^ (x * 7 + 3) ^ (x >> 1)).unwrap() })
+        .collect()
+}
+
+fn reference<T: Num>(
+    input_data_format: DataFormat,
+    output_data_format: DataFormat,
+    len: usize,
+    inverse: bool,
+    input: &[T],
+) -> Vec<T> {
+    let setup = Setup::new(&Options {
+        input_data_order: DataOrder::Natural,
+        output_data_order: DataOrder::Natural,
+        input_data_format,
+        output_data_format,
+        len,
+        inverse,
+        normalization: Normalization::None,
+        max_backend: None,
+        deterministic: false,
+    })
+    .unwrap();
+    let mut output = vec![T::zero(); setup.output_len()];
+    Env::new(&setup).transform_into(input, &mut output).unwrap();
+    output
+}
+
+fn check_complex<T: Num, const N: usize>() {
+    let setup = StaticSetup::<T, N>::new();
+    for &inverse in &[false, true] {
+        let pat = test_pattern::<T>(N * 2);
+        let mut data = [Complex::new(T::zero(), T::zero()); N];
+        for (i, x) in data.iter_mut().enumerate() {
+            *x = Complex::new(pat[i * 2], pat[i * 2 + 1]);
+        }
+        setup.transform_complex(&mut data, inverse);
+        let got: Vec<T> = data.iter().flat_map(|c| vec![c.re, c.im]).collect();
+        let expected = reference(DataFormat::Complex, DataFormat::Complex, N, inverse, &pat);
+        assert_num_slice_approx_eq(&got, &expected, T::from(1.0e-3).unwrap());
+    }
+}
+
+fn check_real<T: Num, const N: usize>() {
+    let setup = StaticSetup::<T, N>::new();
+    let pat = test_pattern::<T>(N);
+
+    let mut data = [T::zero(); N];
+    data.copy_from_slice(&pat);
+    setup.transform_real(&mut data, false);
+    let expected = reference(DataFormat::Real, DataFormat::HalfComplex, N, false, &pat);
+    assert_num_slice_approx_eq(&data, &expected, T::from(1.0e-3).unwrap());
+
+    data.copy_from_slice(&pat);
+    setup.transform_real(&mut data, true);
+    let expected = reference(DataFormat::HalfComplex, DataFormat::Real, N, true, &pat);
+    assert_num_slice_approx_eq(&data, &expected, T::from(1.0e-3).unwrap());
+}
+
+fn check<T: Num>() {
+    check_complex::<T, 1>();
+    check_complex::<T, 2>();
+    check_complex::<T, 4>();
+    check_complex::<T, 8>();
+    check_complex::<T, 32>();
+    check_complex::<T, 128>();
+    check_complex::<T, 1024>();
+    check_real::<T, 2>();
+    check_real::<T, 4>();
+    check_real::<T, 8>();
+    check_real::<T, 16>();
+    check_real::<T, 64>();
+    check_real::<T, 512>();
+    check_real::<T, 2048>();
+}
+
+#[test]
+fn static_setup_f32() {
+    check::<f32>();
+}
+
+#[test]
+fn static_setup_f64() {
+    check::<f64>();
+}
+
+#[test]
+fn static_setup_round_trip() {
+    let setup = StaticSetup::<f32, 16>::default();
+    let pat = test_pattern::<f32>(16);
+    let mut data = [0.0; 16];
+    data.copy_from_slice(&pat);
+    setup.transform_real(&mut data, false);
+    setup.transform_real(&mut data, true);
+    for x in data.iter_mut() {
+        *x *= 2.0 / 16.0;
+    }
+    assert_num_slice_approx_eq(&data, &pat, 1.0e-4);
+}
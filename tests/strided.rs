@@ -0,0 +1,308 @@
+extern crate num_complex;
+extern crate num_traits;
+extern crate yfft;
+
+use yfft::*;
+
+fn assert_num_slice_approx_eq<T: yfft::Num>(got: &[T], expected: &[T], releps: T) {
+    assert_eq!(got.len(), expected.len());
+    // We can't use `Iterator::max()` because T doesn't implement Ord
+    let maxabs = expected
+        .iter()
+        .map(|x| x.abs())
+        .fold(T::zero() / T::zero(), |x, y| x.max(y))
+        + T::from(0.01).unwrap();
+    let eps = maxabs * releps;
+    for i in 0..got.len() {
+        let a = got[i];
+        let b = expected[i];
+        if (a - b).abs() > eps {
+            assert!(
+                (a - b).abs() < eps,
+                "assertion failed: `got almost equal to expected` \
+                 (got: `{:?}`, expected: `{:?}`, diff=`{:?}`)",
+                got,
+                expected,
+                (a - b).abs()
+            );
+        }
+    }
+}
+
+fn test_pattern<T: yfft::Num>(size: usize, seed: usize) -> Vec<T> {
+    (0..size)
+        .map(|x| -> T { T::from((x * 3 + seed) ^ (x * 7 + 3) ^ (x >> 1)).unwrap() })
+        .collect()
+}
+
+fn strided_channels<T: Num>(
+    input_data_format: DataFormat,
+    output_data_format: DataFormat,
+    inverse: bool,
+) {
+    // The first and last stages access the channel directly, so test each
+    // family of kernels
+    let backends = [None, Some(Backend::Generic2), Some(Backend::Generic)];
+    for max_backend in &backends {
+        for size_ref in &[2, 4, 6, 8, 16, 40, 64] {
+            let size = *size_ref;
+            let setup: Setup<T> = Setup::new(&Options {
+                input_data_order: DataOrder::Natural,
+                output_data_order: DataOrder::Natural,
+                input_data_format,
+                output_data_format,
+                len: size,
+                inverse,
+                normalization: Normalization::None,
+                max_backend: *max_backend,
+                deterministic: false,
+            })
+            .unwrap();
+            let mut env = Env::new(&setup);
+
+            let in_len = size
+                * if input_data_format == DataFormat::Complex {
+                    2
+                } else {
+                    1
+                };
+            let out_len = size
+                * if output_data_format == DataFormat::Complex {
+                    2
+                } else {
+                    1
+                };
+
+            for num_channels in 1..4 {
+                let channels: Vec<Vec<T>> = (0..num_channels)
+                    .map(|c| test_pattern(in_len, c * 5 + 1))
+                    .collect();
+
+                // Compute the expected result by transforming each channel separately
+                let expected: Vec<Vec<T>> = channels
+                    .iter()
+                    .map(|pat| {
+                        let mut buf = vec![T::zero(); std::cmp::max(in_len, out_len)];
+                        buf[0..in_len].copy_from_slice(pat);
+                        env.transform(&mut buf).unwrap();
+                        buf.truncate(out_len);
+                        buf
+                    })
+                    .collect();
+
+                let mut data = vec![T::zero(); std::cmp::max(in_len, out_len) * num_channels];
+                for (c, pat) in channels.iter().enumerate() {
+                    for j in 0..in_len {
+                        data[j * num_channels + c] = pat[j];
+                    }
+                }
+
+                for c in 0..num_channels {
+                    env.transform_channel(&mut data, c, num_channels).unwrap();
+                }
+
+                for (c, expected) in expected.iter().enumerate() {
+                    let got: Vec<T> = (0..out_len).map(|j| data[j * num_channels + c]).collect();
+                    assert_num_slice_approx_eq(&got, expected, T::from(1.0e-3).unwrap());
+                }
+            }
+        }
+    }
+}
+
+#[test]
+fn strided_complex_f32() {
+    strided_channels::<f32>(DataFormat::Complex, DataFormat::Complex, false);
+}
+
+#[test]
+fn strided_complex_inverse_f64() {
+    strided_channels::<f64>(DataFormat::Complex, DataFormat::Complex, true);
+}
+
+#[test]
+fn strided_real_to_half_complex_f32() {
+    strided_channels::<f32>(DataFormat::Real, DataFormat::HalfComplex, false);
+}
+
+#[test]
+fn strided_half_complex_to_real_f64() {
+    strided_channels::<f64>(DataFormat::HalfComplex, DataFormat::Real, true);
+}
+
+#[test]
+fn strided_real_to_complex_f64() {
+    strided_channels::<f64>(DataFormat::Real, DataFormat::Complex, false);
+}
+
+#[test]
+fn strided_layout_offset() {
+    let size = 16;
+    let setup: Setup<f32> = Setup::new(&Options {
+        input_data_order: DataOrder::Natural,
+        output_data_order: DataOrder::Natural,
+        input_data_format: DataFormat::Complex,
+        output_data_format: DataFormat::Complex,
+        len: size,
+        inverse: false,
+        normalization: Normalization::None,
+        max_backend: None,
+        deterministic: false,
+    })
+    .unwrap();
+    let mut env = Env::new(&setup);
+
+    let pat = test_pattern::<f32>(size * 2, 3);
+    let mut expected = pat.clone();
+    env.transform(&mut expected).unwrap();
+
+    // Read from the first half, write to the second half
+    let mut data = vec![0.0f32; size * 4];
+    data[0..size * 2].copy_from_slice(&pat);
+    env.transform_strided(
+        &mut data,
+        StridedLayout::contiguous(DataFormat::Complex),
+        StridedLayout {
+            offset: size * 2,
+            stride: 2,
+            component_stride: 1,
+        },
+    )
+    .unwrap();
+
+    assert_eq!(&data[0..size * 2], &pat[..]);
+    assert_num_slice_approx_eq(&data[size * 2..], &expected, 1.0e-3);
+}
+
+#[test]
+fn strided_interleaved_elements() {
+    let size = 8;
+    let num_channels = 3;
+    let setup: Setup<f64> = Setup::new(&Options {
+        input_data_order: DataOrder::Natural,
+        output_data_order: DataOrder::Natural,
+        input_data_format: DataFormat::Complex,
+        output_data_format: DataFormat::Complex,
+        len: size,
+        inverse: true,
+        normalization: Normalization::None,
+        max_backend: None,
+        deterministic: false,
+    })
+    .unwrap();
+    let mut env = Env::new(&setup);
+
+    let pat = test_pattern::<f64>(size * 2 * num_channels, 1);
+    let mut data = pat.clone();
+    for c in 0..num_channels {
+        let layout =
+            StridedLayout::interleaved_elements(c, num_channels, DataFormat::Complex).unwrap();
+        env.transform_strided(&mut data, layout, layout).unwrap();
+    }
+
+    for c in 0..num_channels {
+        let mut expected: Vec<f64> = (0..size)
+            .flat_map(|i| {
+                let start = (i * num_channels + c) * 2;
+                pat[start..start + 2].to_vec()
+            })
+            .collect();
+        env.transform(&mut expected).unwrap();
+
+        let got: Vec<f64> = (0..size)
+            .flat_map(|i| {
+                let start = (i * num_channels + c) * 2;
+                data[start..start + 2].to_vec()
+            })
+            .collect();
+        assert_num_slice_approx_eq(&got, &expected, 1.0e-3);
+    }
+}
+
+#[test]
+fn strided_too_short() {
+    let setup: Setup<f32> = Setup::new(&Options {
+        input_data_order: DataOrder::Natural,
+        output_data_order: DataOrder::Natural,
+        input_data_format: DataFormat::Real,
+        output_data_format: DataFormat::HalfComplex,
+        len: 16,
+        inverse: false,
+        normalization: Normalization::None,
+        max_backend: None,
+        deterministic: false,
+    })
+    .unwrap();
+    let mut env = Env::new(&setup);
+    let mut data = vec![0.0f32; 31];
+    assert_eq!(
+        env.transform_channel(&mut data, 1, 2),
+        Err(TransformError::BufferTooShort {
+            required: 32,
+            actual: 31
+        })
+    );
+}
+
+#[test]
+fn strided_channel_out_of_range() {
+    let setup: Setup<f32> = Setup::new(&Options {
+        input_data_order: DataOrder::Natural,
+        output_data_order: DataOrder::Natural,
+        input_data_format: DataFormat::Complex,
+        output_data_format: DataFormat::Complex,
+        len: 4,
+        inverse: false,
+        normalization: Normalization::None,
+        max_backend: None,
+        deterministic: false,
+    })
+    .unwrap();
+    let mut env = Env::new(&setup);
+    let mut data = vec![0.0f32; 16];
+    assert_eq!(
+        env.transform_channel(&mut data, 2, 2),
+        Err(TransformError::ChannelOutOfRange {
+            channel: 2,
+            num_channels: 2
+        })
+    );
+    assert_eq!(
+        StridedLayout::interleaved_elements(0, 0, DataFormat::Real),
+        Err(TransformError::ChannelOutOfRange {
+            channel: 0,
+            num_channels: 0
+        })
+    );
+}
+
+#[test]
+fn strided_layout_overflow() {
+    let setup: Setup<f32> = Setup::new(&Options {
+        input_data_order: DataOrder::Natural,
+        output_data_order: DataOrder::Natural,
+        input_data_format: DataFormat::Complex,
+        output_data_format: DataFormat::Complex,
+        len: 4,
+        inverse: false,
+        normalization: Normalization::None,
+        max_backend: None,
+        deterministic: false,
+    })
+    .unwrap();
+    let mut env = Env::new(&setup);
+    let mut data = vec![0.0f32; 8];
+    let layout = StridedLayout {
+        offset: 0,
+        stride: usize::MAX / 2,
+        component_stride: 1,
+    };
+    assert_eq!(
+        env.transform_strided(&mut data, layout, layout),
+        Err(TransformError::LayoutOverflow)
+    );
+    assert_eq!(
+        StridedLayout::interleaved_scalars(0, usize::MAX, DataFormat::Complex),
+        Err(TransformError::LayoutOverflow)
+    );
+}
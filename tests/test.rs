@@ -12,6 +12,9 @@ fn test() {
         output_data_format: DataFormat::Complex,
         len: 1024,
         inverse: false,
+        normalization: Normalization::None,
+        max_backend: None,
+        deterministic: false,
     })
     .unwrap();
     let setup_rc = Rc::new(setup);
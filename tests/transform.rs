@@ -113,6 +113,9 @@ fn simple_fft<T: Num>(inverse: bool) {
             output_data_format: DataFormat::Complex,
             len: size,
             inverse: inverse,
+            normalization: Normalization::None,
+            max_backend: None,
+            deterministic: false,
         })
         .unwrap();
         let mut se = Env::new(&setup);
@@ -120,7 +123,7 @@ fn simple_fft<T: Num>(inverse: bool) {
         let mut result_2 = vec![T::zero(); size * 2];
         for pat in test_patterns::<T>(size) {
             result_1.copy_from_slice(pat.as_slice());
-            se.transform(result_1.as_mut_slice());
+            se.transform(result_1.as_mut_slice()).unwrap();
 
             naive_dft(pat.as_slice(), result_2.as_mut_slice(), inverse);
 
@@ -164,6 +167,9 @@ fn fft_roundtrip_shortcut<T: Num>() {
             output_data_format: DataFormat::Complex,
             len: size,
             inverse: false,
+            normalization: Normalization::None,
+            max_backend: None,
+            deterministic: false,
         })
         .unwrap();
         let setup2: Setup<T> = Setup::new(&Options {
@@ -173,6 +179,9 @@ fn fft_roundtrip_shortcut<T: Num>() {
             output_data_format: DataFormat::Complex,
             len: size,
             inverse: true,
+            normalization: Normalization::None,
+            max_backend: None,
+            deterministic: false,
         })
         .unwrap();
 
@@ -184,8 +193,8 @@ fn fft_roundtrip_shortcut<T: Num>() {
         let mut result = vec![T::zero(); size * 2];
         for pat in test_patterns::<T>(size) {
             result.copy_from_slice(pat.as_slice());
-            env1.transform(result.as_mut_slice());
-            env2.transform(result.as_mut_slice());
+            env1.transform(result.as_mut_slice()).unwrap();
+            env2.transform(result.as_mut_slice()).unwrap();
 
             for e in &mut result {
                 *e = *e * factor;
@@ -205,3 +214,305 @@ fn fft_roundtrip_shortcut_f32() {
 fn fft_roundtrip_shortcut_f64() {
     fft_roundtrip_shortcut::<f64>();
 }
+
+fn fft_out_of_place<T: Num>(inverse: bool) {
+    for size_ref in &[1, 2, 3, 4, 5, 6, 7, 8, 16, 32, 40, 49, 64, 128] {
+        let size = *size_ref;
+        let setup: Setup<T> = Setup::new(&Options {
+            input_data_order: DataOrder::Natural,
+            output_data_order: DataOrder::Natural,
+            input_data_format: DataFormat::Complex,
+            output_data_format: DataFormat::Complex,
+            len: size,
+            inverse: inverse,
+            normalization: Normalization::None,
+            max_backend: None,
+            deterministic: false,
+        })
+        .unwrap();
+        let mut se = Env::new(&setup);
+        let mut result_1 = vec![T::zero(); size * 2];
+        let mut result_2 = vec![T::zero(); size * 2];
+        for pat in test_patterns::<T>(size) {
+            let input = pat.clone();
+            se.transform_into(input.as_slice(), result_1.as_mut_slice())
+                .unwrap();
+            assert_eq!(input, pat);
+
+            naive_dft(pat.as_slice(), result_2.as_mut_slice(), inverse);
+
+            assert_num_slice_approx_eq(
+                result_1.as_slice(),
+                result_2.as_slice(),
+                T::from(1.0e-3).unwrap(),
+            );
+        }
+    }
+}
+
+#[test]
+fn fft_out_of_place_forward_f32() {
+    fft_out_of_place::<f32>(false);
+}
+
+#[test]
+fn fft_out_of_place_backward_f64() {
+    fft_out_of_place::<f64>(true);
+}
+
+/// Returns the index of the first element of `buffer` aligned to 32 bytes.
+fn aligned_offset(buffer: &[f32]) -> usize {
+    (32 - buffer.as_ptr() as usize % 32) % 32 / 4
+}
+
+/// Checks that `transform_into`, whose first stage reads the input buffer
+/// directly, produces exactly the same result as `transform` for every
+/// backend, decimation, and alignment of the buffers.
+#[test]
+fn out_of_place_matches_in_place() {
+    let formats = [
+        (DataFormat::Complex, DataFormat::Complex, false),
+        (DataFormat::Complex, DataFormat::Complex, true),
+        (DataFormat::Real, DataFormat::Complex, false),
+        (DataFormat::Real, DataFormat::HalfComplex, false),
+        (DataFormat::HalfComplex, DataFormat::Real, true),
+        (DataFormat::HalfComplex, DataFormat::Complex, true),
+    ];
+    let backends = [
+        None,
+        Some(Backend::Generic),
+        Some(Backend::Generic2),
+        Some(Backend::Sse),
+        Some(Backend::Sse3),
+        Some(Backend::Avx),
+    ];
+    for &(input_data_format, output_data_format, inverse) in &formats {
+        for &len in &[1, 2, 3, 4, 6, 8, 16, 32, 64, 128, 256, 1024] {
+            for &max_backend in &backends {
+                let options = Options {
+                    input_data_order: DataOrder::Natural,
+                    output_data_order: DataOrder::Natural,
+                    input_data_format,
+                    output_data_format,
+                    len,
+                    inverse,
+                    normalization: Normalization::ByLen,
+                    max_backend,
+                    deterministic: false,
+                };
+                let estimate = match Plan::estimate(&options) {
+                    Ok(plan) => plan,
+                    // e.g., a real FFT of an odd length
+                    Err(_) => continue,
+                };
+                let mut dit = estimate.clone();
+                dit.decimation = Decimation::Time;
+                dit.radixes.reverse();
+
+                for plan in &[estimate, dit] {
+                    let setup = match Setup::<f32>::with_plan(&options, plan) {
+                        Ok(setup) => setup,
+                        // The radixes are not supported by `max_backend`
+                        Err(_) => continue,
+                    };
+                    let mut env = Env::new(&setup);
+                    let (input_len, output_len) = (setup.input_len(), setup.output_len());
+
+                    let pat: Vec<f32> = (0..input_len)
+                        .map(|x| ((x * 3 + 7) ^ (x * 7 + 3) ^ (x >> 1)) as f32)
+                        .collect();
+                    let mut expected = vec![0.0; setup.buffer_len()];
+                    expected[0..input_len].copy_from_slice(&pat);
+                    env.transform(&mut expected).unwrap();
+
+                    let mut input_buffer = vec![0.0f32; input_len + 16];
+                    let mut output_buffer = vec![0.0f32; output_len + 16];
+                    let input_base = aligned_offset(&input_buffer);
+                    let output_base = aligned_offset(&output_buffer);
+                    for &(input_skew, output_skew) in &[(0, 0), (0, 1), (1, 0), (1, 1)] {
+                        let input_offset = input_base + input_skew;
+                        let output_offset = output_base + output_skew;
+                        input_buffer[input_offset..input_offset + input_len].copy_from_slice(&pat);
+                        for x in output_buffer.iter_mut() {
+                            *x = 42.0;
+                        }
+
+                        env.transform_into(
+                            &input_buffer[input_offset..input_offset + input_len],
+                            &mut output_buffer[output_offset..output_offset + output_len],
+                        )
+                        .unwrap();
+
+                        let context = (len, input_data_format, output_data_format, max_backend);
+                        assert_eq!(
+                            &input_buffer[input_offset..input_offset + input_len],
+                            &pat[..],
+                            "{:?}: the input was modified",
+                            context
+                        );
+                        assert_eq!(
+                            &output_buffer[output_offset..output_offset + output_len],
+                            &expected[0..output_len],
+                            "{:?}, {:?}",
+                            context,
+                            plan
+                        );
+                        assert!(output_buffer[0..output_offset]
+                            .iter()
+                            .chain(&output_buffer[output_offset + output_len..])
+                            .all(|&x| x == 42.0));
+                    }
+                }
+            }
+        }
+    }
+}
+
+#[test]
+fn buffer_too_short() {
+    let setup: Setup<f32> = Setup::new(&Options {
+        input_data_order: DataOrder::Natural,
+        output_data_order: DataOrder::Natural,
+        input_data_format: DataFormat::Real,
+        output_data_format: DataFormat::Complex,
+        len: 64,
+        inverse: false,
+        normalization: Normalization::None,
+        max_backend: None,
+        deterministic: false,
+    })
+    .unwrap();
+    assert_eq!(setup.input_len(), 64);
+    assert_eq!(setup.output_len(), 128);
+    assert_eq!(setup.buffer_len(), 128);
+
+    let mut env = Env::new(&setup);
+    let mut data = vec![0.0f32; 64];
+    assert_eq!(
+        env.transform(&mut data),
+        Err(TransformError::BufferTooShort {
+            required: 128,
+            actual: 64
+        })
+    );
+
+    let mut data = vec![0.0f32; 128];
+    assert_eq!(env.transform(&mut data), Ok(()));
+    assert_eq!(
+        env.transform_into(&[0.0f32; 64], &mut [0.0f32; 127]),
+        Err(TransformError::BufferTooShort {
+            required: 128,
+            actual: 127
+        })
+    );
+}
+
+#[test]
+fn shared_scratch() {
+    let sizes = [8, 15, 64, 40];
+    let setups: Vec<Setup<f32>> = sizes
+        .iter()
+        .map(|&size| {
+            Setup::new(&Options {
+                input_data_order: DataOrder::Natural,
+                output_data_order: DataOrder::Natural,
+                input_data_format: DataFormat::Complex,
+                output_data_format: DataFormat::Complex,
+                len: size,
+                inverse: false,
+                normalization: Normalization::None,
+                max_backend: None,
+                deterministic: false,
+            })
+            .unwrap()
+        })
+        .collect();
+    let scratch_len = setups.iter().map(|s| s.scratch_len()).max().unwrap();
+    let mut scratch = vec![0.0f32; scratch_len];
+
+    for (setup, &size) in setups.iter().zip(sizes.iter()) {
+        let pat = test_patterns::<f32>(size).pop().unwrap();
+        let mut expected = pat.clone();
+        Env::new(setup).transform(&mut expected).unwrap();
+
+        let mut data = pat.clone();
+        setup
+            .transform_with_scratch(&mut data, &mut scratch)
+            .unwrap();
+        assert_eq!(data, expected);
+    }
+}
+
+#[test]
+fn scratch_too_short() {
+    let setup: Setup<f32> = Setup::new(&Options {
+        input_data_order: DataOrder::Natural,
+        output_data_order: DataOrder::Natural,
+        input_data_format: DataFormat::Complex,
+        output_data_format: DataFormat::Complex,
+        len: 7,
+        inverse: false,
+        normalization: Normalization::None,
+        max_backend: None,
+        deterministic: false,
+    })
+    .unwrap();
+    assert!(setup.scratch_len() > 0);
+    let mut data = vec![0.0f32; 14];
+    let mut scratch = vec![0.0f32; setup.scratch_len() - 1];
+    assert_eq!(
+        setup.transform_with_scratch(&mut data, &mut scratch),
+        Err(TransformError::BufferTooShort {
+            required: setup.scratch_len(),
+            actual: setup.scratch_len() - 1
+        })
+    );
+}
+
+#[test]
+fn concurrent() {
+    use std::sync::Arc;
+    use std::thread;
+
+    let setups: Vec<Arc<Setup<f32>>> = [15, 64, 40]
+        .iter()
+        .map(|&size| {
+            Arc::new(
+                Setup::new(&Options {
+                    input_data_order: DataOrder::Natural,
+                    output_data_order: DataOrder::Natural,
+                    input_data_format: DataFormat::Complex,
+                    output_data_format: DataFormat::Complex,
+                    len: size,
+                    inverse: false,
+                    normalization: Normalization::None,
+                    max_backend: None,
+                    deterministic: false,
+                })
+                .unwrap(),
+            )
+        })
+        .collect();
+
+    let threads: Vec<_> = (0..4)
+        .map(|i| {
+            let setups = setups.clone();
+            thread::spawn(move || {
+                for k in 0..30 {
+                    let setup = &setups[(i + k) % setups.len()];
+                    let pat = test_patterns::<f32>(setup.options().len).pop().unwrap();
+                    let mut expected = pat.clone();
+                    Env::new(&**setup).transform(&mut expected).unwrap();
+
+                    let mut data = pat.clone();
+                    setup.transform_concurrent(&mut data).unwrap();
+                    assert_eq!(data, expected);
+                }
+            })
+        })
+        .collect();
+
+    for thread in threads {
+        thread.join().unwrap();
+    }
+}
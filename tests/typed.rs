@@ -0,0 +1,266 @@
+extern crate num_complex;
+extern crate num_traits;
+extern crate yfft;
+
+use num_complex::Complex;
+
+use yfft::*;
+
+fn assert_num_slice_approx_eq<T: yfft::Num>(got: &[T], expected: &[T], releps: T) {
+    assert_eq!(got.len(), expected.len());
+    // We can't use `Iterator::max()` because T doesn't implement Ord
+    let maxabs = expected
+        .iter()
+        .map(|x| x.abs())
+        .fold(T::zero() / T::zero(), |x, y| x.max(y))
+        + T::from(0.01).unwrap();
+    let eps = maxabs * releps;
+    for i in 0..got.len() {
+        let a = got[i];
+        let b = expected[i];
+        if (a - b).abs() > eps {
+            assert!(
+                (a - b).abs() < eps,
+                "assertion failed: `got almost equal to expected` \
+                 (got: `{:?}`, expected: `{:?}`, diff=`{:?}`)",
+                got,
+                expected,
+                (a - b).abs()
+            );
+        }
+    }
+}
+
+fn test_pattern<T: yfft::Num>(size: usize) -> Vec<T> {
+    (0..size)
+        .map(|x| -> T { T::from((x * 3 + 7) ^ (x * 7 + 3) ^ (x >> 1)).unwrap() })
+        .collect()
+}
+
+fn flatten<T: yfft::Num>(x: &[Complex<T>]) -> Vec<T> {
+    x.iter().flat_map(|c| vec![c.re, c.im]).collect()
+}
+
+fn new_setup<T: yfft::Num>(
+    input_data_format: DataFormat,
+    output_data_format: DataFormat,
+    len: usize,
+    inverse: bool,
+) -> Setup<T> {
+    Setup::new(&Options {
+        input_data_order: DataOrder::Natural,
+        output_data_order: DataOrder::Natural,
+        input_data_format,
+        output_data_format,
+        len,
+        inverse,
+        normalization: Normalization::None,
+        max_backend: None,
+        deterministic: false,
+    })
+    .unwrap()
+}
+
+fn typed_complex<T: Num>() {
+    for size_ref in &[1, 2, 3, 8, 40, 64] {
+        let size = *size_ref;
+        let setup = new_setup::<T>(DataFormat::Complex, DataFormat::Complex, size, false);
+        let mut env = Env::new(&setup);
+
+        let pat = test_pattern::<T>(size * 2);
+        let mut expected = pat.clone();
+        env.transform(&mut expected).unwrap();
+
+        let mut data: Vec<Complex<T>> = (0..size)
+            .map(|i| Complex::new(pat[i * 2], pat[i * 2 + 1]))
+            .collect();
+        let input = data.clone();
+        env.transform_complex(&mut data).unwrap();
+        assert_num_slice_approx_eq(&flatten(&data), &expected, T::from(1.0e-3).unwrap());
+
+        let mut output = vec![Complex::new(T::zero(), T::zero()); size];
+        env.transform_complex_into(&input, &mut output).unwrap();
+        assert_num_slice_approx_eq(&flatten(&output), &expected, T::from(1.0e-3).unwrap());
+    }
+}
+
+#[test]
+fn typed_complex_f32() {
+    typed_complex::<f32>();
+}
+
+#[test]
+fn typed_complex_f64() {
+    typed_complex::<f64>();
+}
+
+fn typed_real<T: Num>(output_data_format: DataFormat) {
+    for size_ref in &[2, 4, 6, 16, 40, 64] {
+        let size = *size_ref;
+        let setup = new_setup::<T>(DataFormat::Real, output_data_format, size, false);
+        let mut env = Env::new(&setup);
+
+        let num_bins = if output_data_format == DataFormat::Complex {
+            size
+        } else {
+            size / 2
+        };
+
+        let pat = test_pattern::<T>(size);
+        let mut expected = vec![T::zero(); size * 2];
+        expected[0..size].copy_from_slice(&pat);
+        env.transform(&mut expected).unwrap();
+        expected.truncate(num_bins * 2);
+
+        let mut output = vec![Complex::new(T::zero(), T::zero()); num_bins];
+        env.transform_real_into(&pat, &mut output).unwrap();
+        assert_num_slice_approx_eq(&flatten(&output), &expected, T::from(1.0e-3).unwrap());
+
+        // Round trip
+        if output_data_format == DataFormat::HalfComplex {
+            let setup2 = new_setup::<T>(DataFormat::HalfComplex, DataFormat::Real, size, true);
+            let mut env2 = Env::new(&setup2);
+            let mut result = vec![T::zero(); size];
+            env2.transform_to_real_into(&output, &mut result).unwrap();
+            let scale = T::from(2.0 / size as f64).unwrap();
+            for x in result.iter_mut() {
+                *x = *x * scale;
+            }
+            assert_num_slice_approx_eq(&result, &pat, T::from(1.0e-3).unwrap());
+        }
+    }
+}
+
+#[test]
+fn typed_real_to_complex_f32() {
+    typed_real::<f32>(DataFormat::Complex);
+}
+
+#[test]
+fn typed_real_to_half_complex_f64() {
+    typed_real::<f64>(DataFormat::HalfComplex);
+}
+
+#[test]
+fn typed_format_mismatch() {
+    let setup = new_setup::<f32>(DataFormat::Real, DataFormat::HalfComplex, 16, false);
+    let mut env = Env::new(&setup);
+    let mut data = vec![Complex::new(0.0f32, 0.0); 16];
+    assert_eq!(
+        env.transform_complex(&mut data),
+        Err(TransformError::IncompatibleDataFormat {
+            input: DataFormat::Real,
+            output: DataFormat::HalfComplex,
+        })
+    );
+}
+
+#[test]
+fn typed_length_mismatch() {
+    let setup = new_setup::<f32>(DataFormat::Real, DataFormat::Complex, 16, false);
+    let mut env = Env::new(&setup);
+    let input = vec![0.0f32; 16];
+    // The `Complex` output must have 16 elements
+    let mut output = vec![Complex::new(0.0f32, 0.0); 8];
+    assert_eq!(
+        env.transform_real_into(&input, &mut output),
+        Err(TransformError::LengthMismatch {
+            expected: 16,
+            actual: 8
+        })
+    );
+
+    let mut fft = RealForwardFft::<f32>::new(16).unwrap();
+    assert_eq!(
+        fft.transform_in_place(&mut [0.0f32; 15]),
+        Err(TransformError::LengthMismatch {
+            expected: 16,
+            actual: 15
+        })
+    );
+}
+
+#[test]
+fn complex_fft_roundtrip() {
+    let size = 64;
+    let mut fwd = ComplexFft::<f32>::new(size, false).unwrap();
+    let mut bwd = ComplexFft::<f32>::new(size, true).unwrap();
+    assert_eq!(fwd.len(), size);
+
+    let pat = test_pattern::<f32>(size * 2);
+    let input: Vec<Complex<f32>> = (0..size)
+        .map(|i| Complex::new(pat[i * 2], pat[i * 2 + 1]))
+        .collect();
+    let mut spectrum = vec![Complex::new(0.0, 0.0); size];
+    fwd.transform_into(&input, &mut spectrum).unwrap();
+    bwd.transform(&mut spectrum).unwrap();
+
+    let result: Vec<f32> = flatten(&spectrum).iter().map(|x| x / size as f32).collect();
+    assert_num_slice_approx_eq(&result, &pat, 1.0e-3);
+}
+
+#[test]
+fn real_fft_roundtrip() {
+    for size_ref in &[2, 4, 6, 16, 40, 64] {
+        let size = *size_ref;
+        let mut fwd = RealForwardFft::<f64>::new(size).unwrap();
+        let mut bwd = RealInverseFft::<f64>::new(size).unwrap();
+
+        let pat = test_pattern::<f64>(size);
+        let mut spectrum = vec![Complex::new(0.0, 0.0); size / 2];
+        fwd.transform(&pat, &mut spectrum).unwrap();
+
+        let mut in_place = pat.clone();
+        fwd.transform_in_place(&mut in_place).unwrap();
+        assert_eq!(in_place, flatten(&spectrum));
+
+        let mut result = vec![0.0; size];
+        bwd.transform(&spectrum, &mut result).unwrap();
+        bwd.transform_in_place(&mut in_place).unwrap();
+        assert_eq!(in_place, result);
+
+        let result: Vec<f64> = result.iter().map(|x| x * 2.0 / size as f64).collect();
+        assert_num_slice_approx_eq(&result, &pat, 1.0e-3);
+    }
+}
+
+#[test]
+fn real_fft_roundtrip_by_len() {
+    let size = 64;
+    let options = TransformOptions {
+        normalization: Normalization::ByLen,
+        ..TransformOptions::default()
+    };
+    let mut fwd = RealForwardFft::<f32>::with_options(size, &options).unwrap();
+    let mut bwd = RealInverseFft::<f32>::with_options(size, &options).unwrap();
+    assert_eq!(fwd.len(), size);
+    assert_eq!(bwd.len(), size);
+
+    let pat = test_pattern::<f32>(size);
+    let mut spectrum = vec![Complex::new(0.0, 0.0); size / 2];
+    let mut result = vec![0.0; size];
+    fwd.transform(&pat, &mut spectrum).unwrap();
+    bwd.transform(&spectrum, &mut result).unwrap();
+    // No rescaling is needed
+    assert_num_slice_approx_eq(&result, &pat, 1.0e-3);
+
+    // The data formats and the direction are determined by the type
+    let fwd_options = fwd.setup().options();
+    assert_eq!(fwd_options.input_data_format, DataFormat::Real);
+    assert_eq!(fwd_options.output_data_format, DataFormat::HalfComplex);
+    assert!(!fwd_options.inverse);
+    let bwd_options = bwd.setup().options();
+    assert_eq!(bwd_options.input_data_format, DataFormat::HalfComplex);
+    assert_eq!(bwd_options.output_data_format, DataFormat::Real);
+    assert!(bwd_options.inverse);
+
+    let fft = ComplexFft::<f32>::with_options(size, true, &options).unwrap();
+    assert_eq!(fft.setup().options().normalization, Normalization::ByLen);
+    assert!(fft.setup().options().inverse);
+}
+
+#[test]
+fn real_fft_odd_len() {
+    assert!(RealForwardFft::<f32>::new(15).is_err());
+    assert!(RealInverseFft::<f32>::new(15).is_err());
+}
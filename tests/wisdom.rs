@@ -0,0 +1,160 @@
+extern crate yfft;
+
+use std::time::Duration;
+use yfft::*;
+
+fn new_options(len: usize) -> Options {
+    Options {
+        input_data_order: DataOrder::Natural,
+        output_data_order: DataOrder::Natural,
+        input_data_format: DataFormat::Real,
+        output_data_format: DataFormat::HalfComplex,
+        len,
+        inverse: false,
+        normalization: Normalization::None,
+        max_backend: None,
+        deterministic: false,
+    }
+}
+
+#[test]
+fn wisdom_round_trip() {
+    let mut wisdom = Wisdom::new();
+    for &len in &[2, 24, 128] {
+        let setup =
+            Setup::<f32>::new_measured(&new_options(len), Duration::from_millis(5)).unwrap();
+        wisdom.insert(&setup);
+        let setup = Setup::<f64>::new(&new_options(len)).unwrap();
+        wisdom.insert(&setup);
+    }
+    assert_eq!(wisdom.len(), 6);
+
+    let text = wisdom.to_string();
+    assert!(text.starts_with("yfft-wisdom 1\n"));
+    let parsed: Wisdom = text.parse().unwrap();
+    assert_eq!(parsed, wisdom);
+    assert_eq!(
+        parsed.get::<f32>(&new_options(24)),
+        wisdom.get::<f32>(&new_options(24))
+    );
+    assert_eq!(parsed.get::<f32>(&new_options(48)), None);
+}
+
+#[test]
+fn wisdom_register() {
+    // A length no other test uses
+    let options = new_options(4 * 4 * 4 * 2 * 2 * 2);
+    let plan = Plan {
+        radixes: vec![2, 4, 2, 4, 4],
+        decimation: Decimation::Time,
+        bit_reversal_backend: Backend::Generic,
+    };
+
+    let mut wisdom = Wisdom::new();
+    wisdom.insert(&Setup::<f32>::with_plan(&options, &plan).unwrap());
+    let text = wisdom.to_string();
+
+    let wisdom: Wisdom = text.parse().unwrap();
+    wisdom.register();
+    assert_eq!(Wisdom::global().get::<f32>(&options), Some(&plan));
+
+    assert_eq!(Setup::<f32>::new(&options).unwrap().plan(), &plan);
+    assert_eq!(
+        Setup::<f32>::new_measured(&options, Duration::from_secs(60))
+            .unwrap()
+            .plan(),
+        &plan
+    );
+
+    // Not used for the other precision or the deterministic mode
+    assert_ne!(Setup::<f64>::new(&options).unwrap().plan(), &plan);
+    let mut deterministic = options;
+    deterministic.deterministic = true;
+    assert_ne!(Setup::<f32>::new(&deterministic).unwrap().plan(), &plan);
+}
+
+#[test]
+fn wisdom_foreign_features() {
+    let text = "yfft-wisdom 1\n\
+                # recorded on another machine\n\
+                plan precision=f32 features=unknown len=16 input=natural/complex \
+                output=natural/complex inverse=false decimation=time radixes=2,8 \
+                bit-reversal=generic\n";
+    let wisdom: Wisdom = text.parse().unwrap();
+    assert_eq!(wisdom.len(), 1);
+
+    let mut options = new_options(16);
+    options.input_data_format = DataFormat::Complex;
+    options.output_data_format = DataFormat::Complex;
+    assert_eq!(wisdom.get::<f32>(&options), None);
+    assert_eq!(
+        wisdom.to_string(),
+        text.replace("# recorded on another machine\n", "")
+    );
+}
+
+#[test]
+fn wisdom_errors() {
+    assert_eq!("".parse::<Wisdom>(), Err(WisdomError::MissingHeader));
+    assert_eq!("fftw 1".parse::<Wisdom>(), Err(WisdomError::MissingHeader));
+    assert_eq!(
+        "yfft-wisdom 2".parse::<Wisdom>(),
+        Err(WisdomError::UnsupportedVersion { version: 2 })
+    );
+    assert_eq!("yfft-wisdom 1\n".parse::<Wisdom>(), Ok(Wisdom::new()));
+
+    let line = "plan precision=f32 features=x len=16 input=natural/complex \
+                output=natural/complex inverse=false decimation=time radixes=2,8 \
+                bit-reversal=generic";
+    for bad in &[
+        line.replace("len=16", "len=17"),
+        line.replace("radixes=2,8", "radixes=2,2"),
+        line.replace("output=natural", "output=swizzled"),
+        line.replace("precision=f32", "precision=f16"),
+        line.replace(" inverse=false", ""),
+        line.replace("plan ", "setup "),
+        format!("{} len=16", line),
+        // Malformed or overflowing radix lists
+        line.replace("radixes=2,8", "radixes=16,1152921504606846977"),
+        line.replace("radixes=2,8", "radixes=2,8,0"),
+        line.replace("radixes=2,8", "radixes=2,,8"),
+        line.replace("radixes=2,8", "radixes=2,-8"),
+        line.replace("radixes=2,8", "radixes=2,99999999999999999999999"),
+        line.replace("radixes=2,8", "radixes=16,1"),
+        line.replace("radixes=2,8", "radixes=32"),
+        // Lengths too large to represent or allocate
+        line.replace("len=16", "len=99999999999999999999999"),
+        line.replace("len=16", "len=18446744073709551557")
+            .replace("radixes=2,8", "radixes=18446744073709551557"),
+    ] {
+        assert_eq!(
+            format!("yfft-wisdom 1\n\n{}", bad).parse::<Wisdom>(),
+            Err(WisdomError::Malformed { line: 3 }),
+            "{}",
+            bad
+        );
+    }
+}
+
+/// A large prime length must be handled without factorizing it. The first
+/// one exceeds the length limit.
+#[test]
+#[cfg(target_pointer_width = "64")]
+fn wisdom_large_prime_len() {
+    let text = "yfft-wisdom 1\n\
+                plan precision=f32 features=x len=2305843009213693951 input=natural/complex \
+                output=natural/complex inverse=false decimation=time radixes=2305843009213693951 \
+                bit-reversal=generic\n";
+    assert_eq!(
+        text.parse::<Wisdom>(),
+        Err(WisdomError::Malformed { line: 2 })
+    );
+
+    let text = text.replace("2305843009213693951", "2147483647");
+    let result = text.parse::<Wisdom>();
+    if cfg!(feature = "backend-generic") {
+        assert_eq!(result.map(|w| w.len()), Ok(1));
+    } else {
+        assert_eq!(result, Err(WisdomError::Malformed { line: 2 }));
+    }
+}